@@ -0,0 +1,238 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use tui_tree_widget::TreeItem;
+
+use crate::config_dir;
+use crate::library::Library;
+
+/// How the browser groups tracks. `Files` is the directory (or playlist) tree;
+/// the others are built from the library index.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BrowseMode {
+    Files,
+    Artists,
+    Genres,
+    Years,
+}
+
+impl BrowseMode {
+    pub fn next(self) -> Self {
+        match self {
+            BrowseMode::Files => BrowseMode::Artists,
+            BrowseMode::Artists => BrowseMode::Genres,
+            BrowseMode::Genres => BrowseMode::Years,
+            BrowseMode::Years => BrowseMode::Files,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BrowseMode::Files => "Files",
+            BrowseMode::Artists => "Artists",
+            BrowseMode::Genres => "Genres",
+            BrowseMode::Years => "Years",
+        }
+    }
+}
+
+pub fn load_browse_mode() -> BrowseMode {
+    match std::fs::read_to_string(config_dir().join("browse_mode"))
+        .unwrap_or_default()
+        .trim()
+    {
+        "artists" => BrowseMode::Artists,
+        "genres" => BrowseMode::Genres,
+        "years" => BrowseMode::Years,
+        _ => BrowseMode::Files,
+    }
+}
+
+pub fn save_browse_mode(mode: BrowseMode) {
+    let dir = config_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let _ = std::fs::write(dir.join("browse_mode"), mode.label().to_ascii_lowercase());
+}
+
+// Group nodes get identifiers containing NUL, which no real path can, so they are
+// never mistaken for files.
+const ALBUM_PREFIX: &str = "\0album\0";
+
+fn group_id(kind: &str, name: &str) -> PathBuf {
+    PathBuf::from(format!("\0{kind}\0{name}"))
+}
+
+/// True for album nodes, which play in track order when selected.
+pub fn is_album_node(id: &Path) -> bool {
+    id.to_str().is_some_and(|s| s.starts_with(ALBUM_PREFIX))
+}
+
+struct Track {
+    path: PathBuf,
+    title: String,
+    disc: u32,
+    number: Option<u32>,
+}
+
+#[derive(Default)]
+struct Album {
+    artist: String,
+    title: String,
+    year: Option<String>,
+    tracks: Vec<Track>,
+}
+
+/// Sort key that puts the "Unknown …" bucket last and ignores case.
+fn group_key(name: &str, known: bool) -> (bool, String) {
+    (!known, name.to_lowercase())
+}
+
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+const UNKNOWN_ALBUM: &str = "Unknown Album";
+const UNKNOWN_GENRE: &str = "Unknown Genre";
+const UNKNOWN_YEAR: &str = "Unknown Year";
+
+/// Group `files` into albums keyed by (album artist, album), tracks in disc/track order.
+fn collect_albums(files: &[PathBuf], library: &Library) -> BTreeMap<(String, String), Album> {
+    let mut albums: BTreeMap<(String, String), Album> = BTreeMap::new();
+    for path in files {
+        let meta = library.get(path).map(|e| &e.meta);
+        let artist = meta
+            .and_then(|m| m.album_artist.clone().or_else(|| m.artist.clone()))
+            .unwrap_or_else(|| UNKNOWN_ARTIST.into());
+        let album_title = meta
+            .and_then(|m| m.album.clone())
+            .unwrap_or_else(|| UNKNOWN_ALBUM.into());
+        let title = meta.and_then(|m| m.title.clone()).unwrap_or_else(|| {
+            path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        let album = albums
+            .entry((artist.to_lowercase(), album_title.to_lowercase()))
+            .or_insert_with(|| Album {
+                artist: artist.clone(),
+                title: album_title.clone(),
+                ..Default::default()
+            });
+        if album.year.is_none() {
+            album.year = meta.and_then(|m| m.year());
+        }
+        album.tracks.push(Track {
+            path: path.clone(),
+            title,
+            disc: meta.and_then(|m| m.disc_number).unwrap_or(1),
+            number: meta.and_then(|m| m.track_number),
+        });
+    }
+    for album in albums.values_mut() {
+        album.tracks.sort_by(|a, b| {
+            (a.disc, a.number.unwrap_or(u32::MAX), a.title.to_lowercase())
+                .cmp(&(b.disc, b.number.unwrap_or(u32::MAX), b.title.to_lowercase()))
+        });
+    }
+    albums
+}
+
+fn album_item(album: &Album, label: String) -> Option<TreeItem<'static, PathBuf>> {
+    let multi_disc = album.tracks.iter().any(|t| t.disc > 1);
+    let leaves = album
+        .tracks
+        .iter()
+        .map(|t| {
+            let text = match t.number {
+                Some(n) if multi_disc => format!("{}-{n:02}. {}", t.disc, t.title),
+                Some(n) => format!("{n:02}. {}", t.title),
+                None => t.title.clone(),
+            };
+            TreeItem::new_leaf(t.path.clone(), text)
+        })
+        .collect();
+    let id = PathBuf::from(format!("{ALBUM_PREFIX}{}\0{}", album.artist, album.title));
+    TreeItem::new(id, label, leaves).ok()
+}
+
+/// Albums labelled by `label`: in release order under an artist, alphabetical
+/// by label everywhere else.
+fn album_items(
+    mut albums: Vec<&Album>,
+    label: fn(&Album) -> String,
+    chronological: bool,
+) -> Vec<TreeItem<'static, PathBuf>> {
+    if chronological {
+        albums.sort_by_key(|a| {
+            (a.year.is_none(), a.year.clone(), group_key(&a.title, a.title != UNKNOWN_ALBUM))
+        });
+    } else {
+        albums.sort_by_key(|a| label(a).to_lowercase());
+    }
+    albums
+        .into_iter()
+        .filter_map(|a| album_item(a, label(a)))
+        .collect()
+}
+
+fn artist_album_label(a: &Album) -> String {
+    format!("{} – {}", a.artist, a.title)
+}
+
+fn album_year_label(a: &Album) -> String {
+    match a.year {
+        Some(ref y) => format!("{} ({y})", a.title),
+        None => a.title.clone(),
+    }
+}
+
+/// Tree for a library view: group → album → tracks.
+pub fn build_view(
+    mode: BrowseMode,
+    files: &[PathBuf],
+    library: &Library,
+) -> Vec<TreeItem<'static, PathBuf>> {
+    let albums = collect_albums(files, library);
+    // (group name, None when the tag is missing; album)
+    let keyed: Vec<(Option<String>, &Album)> = match mode {
+        BrowseMode::Files => return Vec::new(),
+        BrowseMode::Artists => albums
+            .values()
+            .map(|a| (Some(a.artist.clone()).filter(|n| n != UNKNOWN_ARTIST), a))
+            .collect(),
+        BrowseMode::Genres => albums
+            .values()
+            .map(|a| {
+                let genre = a
+                    .tracks
+                    .first()
+                    .and_then(|t| library.get(&t.path))
+                    .and_then(|e| e.meta.genre.clone());
+                (genre, a)
+            })
+            .collect(),
+        BrowseMode::Years => albums.values().map(|a| (a.year.clone(), a)).collect(),
+    };
+    let (kind, unknown, label): (&str, &str, fn(&Album) -> String) = match mode {
+        BrowseMode::Genres => ("genre", UNKNOWN_GENRE, artist_album_label),
+        BrowseMode::Years => ("year", UNKNOWN_YEAR, artist_album_label),
+        _ => ("artist", UNKNOWN_ARTIST, album_year_label),
+    };
+
+    let mut groups: BTreeMap<(bool, String), (String, Vec<&Album>)> = BTreeMap::new();
+    for (name, album) in keyed {
+        let known = name.is_some();
+        let name = name.unwrap_or_else(|| unknown.to_string());
+        groups
+            .entry(group_key(&name, known))
+            .or_insert_with(|| (name, Vec::new()))
+            .1
+            .push(album);
+    }
+    groups
+        .into_values()
+        .filter_map(|(name, albums)| {
+            let children = album_items(albums, label, mode == BrowseMode::Artists);
+            TreeItem::new(group_id(kind, &name), name, children).ok()
+        })
+        .collect()
+}
@@ -0,0 +1,394 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::{cache_hash, config_dir};
+
+const DEFAULT_MAX_MB: u64 = 200;
+const DEFAULT_NEGATIVE_HOURS: u64 = 24;
+
+/// Suffix of the marker left when a lookup found nothing.
+const NEGATIVE_SUFFIX: &str = ".none";
+
+/// Size of the cache in bytes as of the last scan plus what `put` wrote since, so
+/// the directory is only walked again once the cap is crossed. None until the
+/// first write, and after `prune`/`clear`. "Not found" markers are empty files, so
+/// `put_negative` leaves it unchanged.
+static TRACKED_BYTES: Mutex<Option<u64>> = Mutex::new(None);
+
+/// What a cache entry holds. Each kind lives in its own subdirectory.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Fetched lyrics as JSON, keyed by artist and title
+    Lyrics,
+    /// Downloaded cover images, keyed by URL
+    Art,
+    /// Measured loudness as JSON, keyed by path, mtime and size
+    Loudness,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [Kind::Lyrics, Kind::Art, Kind::Loudness];
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Lyrics => "lyrics",
+            Kind::Art => "art",
+            Kind::Loudness => "loudness",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Kind::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Kind::Lyrics | Kind::Loudness => ".json",
+            Kind::Art => "",
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/tui-player`, or `~/.cache/tui-player`.
+pub fn cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| {
+            let home = env::var("HOME").expect("HOME not set");
+            PathBuf::from(home).join(".cache")
+        });
+    base.join("tui-player")
+}
+
+fn entry_path(kind: Kind, key: &str) -> PathBuf {
+    cache_dir()
+        .join(kind.name())
+        .join(format!("{}{}", cache_hash(key), kind.extension()))
+}
+
+fn negative_path(kind: Kind, key: &str) -> PathBuf {
+    cache_dir()
+        .join(kind.name())
+        .join(format!("{}{NEGATIVE_SUFFIX}", cache_hash(key)))
+}
+
+struct Settings {
+    max_bytes: u64,
+    negative_ttl: Duration,
+}
+
+/// Read `~/.config/tui-player/cache_limits` (`key = value` lines, `#` comments):
+/// `max_size_mb` caps the cache, `negative_ttl_hours` is how long a lookup that
+/// found nothing is trusted before trying again.
+fn load_settings() -> Settings {
+    parse_settings(&fs::read_to_string(config_dir().join("cache_limits")).unwrap_or_default())
+}
+
+fn parse_settings(content: &str) -> Settings {
+    let mut settings = Settings {
+        max_bytes: DEFAULT_MAX_MB * 1024 * 1024,
+        negative_ttl: Duration::from_secs(DEFAULT_NEGATIVE_HOURS * 3600),
+    };
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let Ok(value) = value.trim().parse::<u64>() else {
+            continue;
+        };
+        match key.trim() {
+            "max_size_mb" => settings.max_bytes = value.saturating_mul(1024 * 1024),
+            "negative_ttl_hours" => {
+                settings.negative_ttl = Duration::from_secs(value.saturating_mul(3600));
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+/// Cached data for `key`. A hit counts as a use for LRU eviction.
+pub fn get(kind: Kind, key: &str) -> Option<Vec<u8>> {
+    let path = entry_path(kind, key);
+    let data = fs::read(&path).ok()?;
+    if let Ok(file) = fs::File::options().write(true).open(&path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(data)
+}
+
+/// Store `data` for `key`, replacing any "not found" marker, then evict the least
+/// recently used entries if the cache has grown past its cap.
+pub fn put(kind: Kind, key: &str, data: &[u8]) {
+    let path = entry_path(kind, key);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::remove_file(negative_path(kind, key));
+    let replaced = fs::metadata(&path).map_or(0, |m| m.len());
+    if fs::write(&path, data).is_err() {
+        return;
+    }
+    let max_bytes = load_settings().max_bytes;
+    let mut tracked = TRACKED_BYTES.lock().unwrap_or_else(|e| e.into_inner());
+    let total = match *tracked {
+        Some(bytes) => (bytes + data.len() as u64).saturating_sub(replaced),
+        None => scan().iter().map(|e| e.size).sum(),
+    };
+    *tracked = Some(if total > max_bytes {
+        let mut entries = scan();
+        evict(&mut entries, max_bytes);
+        entries.iter().map(|e| e.size).sum()
+    } else {
+        total
+    });
+}
+
+/// Make the next `put` scan the cache again, after entries were removed.
+fn forget_size() {
+    *TRACKED_BYTES.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Remember that nothing was found for `key`. The marker is an empty file, so it
+/// doesn't count against the size cap.
+pub fn put_negative(kind: Kind, key: &str) {
+    let path = negative_path(kind, key);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, b"");
+}
+
+/// Whether a recent lookup for `key` found nothing. Expired markers are removed.
+pub fn is_negative(kind: Kind, key: &str) -> bool {
+    let path = negative_path(kind, key);
+    let Some(age) = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(|t| t.elapsed().unwrap_or_default())
+    else {
+        return false;
+    };
+    if age < load_settings().negative_ttl {
+        return true;
+    }
+    let _ = fs::remove_file(path);
+    false
+}
+
+/// Forget `key`, found or not, so the next lookup starts fresh.
+pub fn remove(kind: Kind, key: &str) {
+    let _ = fs::remove_file(entry_path(kind, key));
+    let _ = fs::remove_file(negative_path(kind, key));
+}
+
+struct Entry {
+    path: PathBuf,
+    kind: Kind,
+    size: u64,
+    used: SystemTime,
+    negative: bool,
+}
+
+/// Every file in the cache, oldest use first.
+fn scan() -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for kind in Kind::ALL {
+        let Ok(rd) = fs::read_dir(cache_dir().join(kind.name())) else {
+            continue;
+        };
+        for entry in rd.flatten() {
+            let Ok(md) = entry.metadata() else {
+                continue;
+            };
+            if !md.is_file() {
+                continue;
+            }
+            entries.push(Entry {
+                path: entry.path(),
+                kind,
+                size: md.len(),
+                used: md.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                negative: entry.file_name().to_string_lossy().ends_with(NEGATIVE_SUFFIX),
+            });
+        }
+    }
+    entries.sort_by_key(|e| e.used);
+    entries
+}
+
+/// Delete the least recently used entries until the rest fit in `max_bytes`.
+/// Returns (entries removed, bytes freed).
+fn evict(entries: &mut Vec<Entry>, max_bytes: u64) -> (usize, u64) {
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+    let mut removed = (0, 0);
+    entries.retain(|e| {
+        if total <= max_bytes || fs::remove_file(&e.path).is_err() {
+            return true;
+        }
+        total -= e.size;
+        removed = (removed.0 + 1, removed.1 + e.size);
+        false
+    });
+    removed
+}
+
+/// Drop expired "not found" markers, then evict down to the size cap.
+/// Returns (entries removed, bytes freed).
+pub fn prune() -> (usize, u64) {
+    let settings = load_settings();
+    let mut entries = scan();
+    let mut removed = (0, 0);
+    entries.retain(|e| {
+        let expired = e.negative
+            && e.used.elapsed().unwrap_or_default() >= settings.negative_ttl
+            && fs::remove_file(&e.path).is_ok();
+        if expired {
+            removed.0 += 1;
+        }
+        !expired
+    });
+    let (count, bytes) = evict(&mut entries, settings.max_bytes);
+    forget_size();
+    (removed.0 + count, removed.1 + bytes)
+}
+
+/// Delete everything of `kind`, or the whole cache. Returns (entries removed, bytes freed).
+pub fn clear(kind: Option<Kind>) -> (usize, u64) {
+    let mut removed = (0, 0);
+    for entry in scan() {
+        if kind.is_none_or(|k| k == entry.kind) && fs::remove_file(&entry.path).is_ok() {
+            removed = (removed.0 + 1, removed.1 + entry.size);
+        }
+    }
+    forget_size();
+    removed
+}
+
+/// Older versions kept the cache in `~/.config/tui-player/cache`; move it over.
+pub fn migrate() {
+    let old = config_dir().join("cache");
+    if !old.is_dir() {
+        return;
+    }
+    let new = cache_dir();
+    if !new.exists() {
+        if let Some(parent) = new.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if fs::rename(&old, &new).is_ok() {
+            return;
+        }
+    }
+    // Already migrated, or on another filesystem: it's only a cache
+    let _ = fs::remove_dir_all(old);
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{b} B"),
+    }
+}
+
+fn print_stats(dir: &Path) {
+    let settings = load_settings();
+    let entries = scan();
+    println!("Cache: {}", dir.display());
+    for kind in Kind::ALL {
+        let of_kind = || entries.iter().filter(move |e| e.kind == kind);
+        let negative = of_kind().filter(|e| e.negative).count();
+        let size: u64 = of_kind().map(|e| e.size).sum();
+        println!(
+            "  {:<8}{:>7} entries{:>11}   {negative} not found",
+            kind.name(),
+            of_kind().count() - negative,
+            format_size(size),
+        );
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    println!(
+        "  {:<8}{:>7} files  {:>11}   of {} max; \"not found\" kept {} h",
+        "total",
+        entries.len(),
+        format_size(total),
+        format_size(settings.max_bytes),
+        settings.negative_ttl.as_secs() / 3600,
+    );
+}
+
+const HELP: &str = "\
+Commands:
+  stats                          Entries and size per kind
+  prune                          Drop expired \"not found\" results and evict down to the size cap
+  clear [lyrics|art|loudness]    Delete the whole cache, or one kind";
+
+/// `tui-player cache <command>`. Returns the process exit code.
+pub fn run_cli(args: &[String]) -> i32 {
+    migrate();
+    let report = |(count, bytes): (usize, u64)| {
+        println!("Removed {count} entries, {}", format_size(bytes));
+    };
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["stats"] => print_stats(&cache_dir()),
+        ["prune"] => report(prune()),
+        ["clear"] => report(clear(None)),
+        ["clear", name] => match Kind::parse(name) {
+            Some(kind) => report(clear(Some(kind))),
+            None => {
+                eprintln!("Unknown cache kind: {name}\n\n{HELP}");
+                return 2;
+            }
+        },
+        ["help" | "--help"] => println!("Usage: tui-player cache <command>\n\n{HELP}"),
+        _ => {
+            eprintln!("Usage: tui-player cache <command>\n\n{HELP}");
+            return 2;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        for kind in Kind::ALL {
+            assert!(Kind::parse(kind.name()) == Some(kind));
+        }
+        assert!(Kind::parse("Lyrics").is_none());
+        assert!(Kind::parse("").is_none());
+        assert_eq!(Kind::Art.extension(), "");
+        assert_eq!(Kind::Loudness.extension(), ".json");
+    }
+
+    #[test]
+    fn settings() {
+        let defaults = parse_settings("");
+        assert_eq!(defaults.max_bytes, DEFAULT_MAX_MB * 1024 * 1024);
+        assert_eq!(defaults.negative_ttl, Duration::from_secs(DEFAULT_NEGATIVE_HOURS * 3600));
+
+        let s = parse_settings("# limits\nmax_size_mb = 50  # small\n negative_ttl_hours=2\n");
+        assert_eq!(s.max_bytes, 50 * 1024 * 1024);
+        assert_eq!(s.negative_ttl, Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn bad_settings() {
+        // Unparsable values keep the default; huge ones saturate instead of overflowing
+        let s = parse_settings("max_size_mb = -1\nnegative_ttl_hours = lots\n");
+        assert_eq!(s.max_bytes, DEFAULT_MAX_MB * 1024 * 1024);
+        let s = parse_settings(&format!("max_size_mb = {0}\nnegative_ttl_hours = {0}", u64::MAX));
+        assert_eq!(s.max_bytes, u64::MAX);
+        assert_eq!(s.negative_ttl, Duration::from_secs(u64::MAX));
+    }
+}
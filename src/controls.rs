@@ -38,6 +38,12 @@ fn build_control_spans(
     spans.extend([
         Span::styled(" e ", key_style),
         Span::raw(" EQ  "),
+        Span::styled(" [/] ", key_style),
+        Span::raw(" A-B Loop  "),
+        Span::styled(" b ", key_style),
+        Span::raw(" Stereo  "),
+        Span::styled(" z ", key_style),
+        Span::raw(" Sleep  "),
     ]);
     if has_browser {
         spans.extend([
@@ -68,6 +74,10 @@ fn build_control_spans(
             ),
             Span::styled(" f ", key_style),
             Span::raw(" Files  "),
+            Span::styled(" u ", key_style),
+            Span::raw(" Queue  "),
+            Span::styled(" w ", key_style),
+            Span::raw(" Save Playlist  "),
         ]);
     }
     spans.extend([
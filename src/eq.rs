@@ -2,13 +2,14 @@ use std::sync::{Arc, Mutex};
 
 use biquad::{Biquad, Coefficients, DirectForm2Transposed, ToHertz, Type};
 use ratatui::{
-    layout::Rect,
+    layout::{Alignment, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Clear, Paragraph},
     Frame,
 };
 
+use crate::expand_tilde;
 use crate::theme::Theme;
 
 pub const NUM_BANDS: usize = 32;
@@ -20,13 +21,278 @@ pub const BAND_FREQS: [f32; NUM_BANDS] = [
     6300.0, 8000.0, 10000.0, 12500.0, 16000.0, 20000.0,
 ];
 
-const MAX_GAIN: f32 = 12.0;
+pub const MAX_GAIN: f32 = 12.0;
 const EQ_Q: f32 = 4.3; // 1/3-octave bandwidth
 
+// Parametric filter limits
+const MIN_FREQ: f32 = 10.0;
+const MAX_FREQ: f32 = 24000.0;
+const MAX_PARAM_GAIN: f32 = 30.0;
+const MIN_Q: f32 = 0.1;
+const MAX_Q: f32 = 20.0;
+pub const MAX_FILTERS: usize = 32;
+/// Q used for shelves and passes given without one: ≈ 1/√2, a Butterworth response
+const DEFAULT_Q: f32 = 0.71;
+
+/// Which curve the EQ applies.
+#[derive(Clone, Copy, PartialEq)]
+pub enum EqMode {
+    /// 32 fixed 1/3-octave bands
+    Graphic,
+    /// User-defined filters plus preamp
+    Parametric,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    Notch,
+}
+
+impl FilterKind {
+    const ALL: [FilterKind; 6] = [
+        FilterKind::Peaking,
+        FilterKind::LowShelf,
+        FilterKind::HighShelf,
+        FilterKind::LowPass,
+        FilterKind::HighPass,
+        FilterKind::Notch,
+    ];
+
+    /// EqualizerAPO filter type.
+    fn code(self) -> &'static str {
+        match self {
+            FilterKind::Peaking => "PK",
+            FilterKind::LowShelf => "LSC",
+            FilterKind::HighShelf => "HSC",
+            FilterKind::LowPass => "LPQ",
+            FilterKind::HighPass => "HPQ",
+            FilterKind::Notch => "NO",
+        }
+    }
+
+    fn parse(code: &str) -> Option<Self> {
+        match code.to_ascii_uppercase().as_str() {
+            "PK" | "PEQ" => Some(FilterKind::Peaking),
+            "LSC" | "LS" => Some(FilterKind::LowShelf),
+            "HSC" | "HS" => Some(FilterKind::HighShelf),
+            "LPQ" | "LP" => Some(FilterKind::LowPass),
+            "HPQ" | "HP" => Some(FilterKind::HighPass),
+            "NO" => Some(FilterKind::Notch),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterKind::Peaking => "Peak",
+            FilterKind::LowShelf => "Low shelf",
+            FilterKind::HighShelf => "High shelf",
+            FilterKind::LowPass => "Low pass",
+            FilterKind::HighPass => "High pass",
+            FilterKind::Notch => "Notch",
+        }
+    }
+
+    /// Whether the gain setting means anything for this type.
+    pub fn has_gain(self) -> bool {
+        matches!(self, FilterKind::Peaking | FilterKind::LowShelf | FilterKind::HighShelf)
+    }
+
+    fn cycle(self, up: bool) -> Self {
+        let i = Self::ALL.iter().position(|&k| k == self).unwrap_or(0);
+        let n = Self::ALL.len();
+        Self::ALL[if up { (i + 1) % n } else { (i + n - 1) % n }]
+    }
+
+    fn biquad_type(self, gain_db: f32) -> Type<f32> {
+        match self {
+            FilterKind::Peaking => Type::PeakingEQ(gain_db),
+            FilterKind::LowShelf => Type::LowShelf(gain_db),
+            FilterKind::HighShelf => Type::HighShelf(gain_db),
+            FilterKind::LowPass => Type::LowPass,
+            FilterKind::HighPass => Type::HighPass,
+            FilterKind::Notch => Type::Notch,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ParamFilter {
+    pub kind: FilterKind,
+    pub freq: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+/// Column of the parametric filter table the cursor is on.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ParamField {
+    Kind,
+    Freq,
+    Gain,
+    Q,
+}
+
+impl ParamField {
+    const ALL: [ParamField; 4] =
+        [ParamField::Kind, ParamField::Freq, ParamField::Gain, ParamField::Q];
+
+    pub fn cycle(self, right: bool) -> Self {
+        let i = Self::ALL.iter().position(|&f| f == self).unwrap_or(0);
+        Self::ALL[if right { (i + 1).min(3) } else { i.saturating_sub(1) }]
+    }
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct ParametricEq {
+    pub preamp_db: f32,
+    pub filters: Vec<ParamFilter>,
+}
+
+impl ParametricEq {
+    /// Nudge one setting. Row 0 is the preamp, row `n` is filter `n`.
+    pub fn adjust(&mut self, row: usize, field: ParamField, up: bool) {
+        let sign = if up { 1.0 } else { -1.0 };
+        // Half-dB steps, kept on the 0.1 dB grid imported values use
+        let step_db = |db: f32| {
+            (((db + 0.5 * sign) * 10.0).round() / 10.0).clamp(-MAX_PARAM_GAIN, MAX_PARAM_GAIN)
+        };
+        if row == 0 {
+            self.preamp_db = step_db(self.preamp_db);
+            return;
+        }
+        let Some(f) = self.filters.get_mut(row - 1) else {
+            return;
+        };
+        match field {
+            ParamField::Kind => f.kind = f.kind.cycle(up),
+            // 1/12 octave per step
+            ParamField::Freq => {
+                let freq = f.freq * 2f32.powf(sign / 12.0);
+                f.freq = freq.round().clamp(MIN_FREQ, MAX_FREQ);
+            }
+            ParamField::Gain => f.gain_db = step_db(f.gain_db),
+            ParamField::Q => {
+                let q = f.q * 1.1f32.powf(sign);
+                f.q = ((q * 100.0).round() / 100.0).clamp(MIN_Q, MAX_Q);
+            }
+        }
+    }
+
+    /// Add a flat peaking filter after `row` (0 = first); returns its row.
+    pub fn add_filter(&mut self, row: usize) -> usize {
+        if self.filters.len() >= MAX_FILTERS {
+            return row;
+        }
+        let at = row.min(self.filters.len());
+        let freq = self.filters.get(at.wrapping_sub(1)).map_or(1000.0, |f| f.freq);
+        self.filters.insert(
+            at,
+            ParamFilter { kind: FilterKind::Peaking, freq, gain_db: 0.0, q: 1.0 },
+        );
+        at + 1
+    }
+
+    pub fn remove_filter(&mut self, row: usize) {
+        if row > 0 && row <= self.filters.len() {
+            self.filters.remove(row - 1);
+        }
+    }
+
+    /// EqualizerAPO text, the same format `parse_apo` reads.
+    pub fn to_apo(&self) -> String {
+        let mut out = format!("Preamp: {} dB\n", self.preamp_db);
+        for (i, f) in self.filters.iter().enumerate() {
+            out.push_str(&format!("Filter {}: ON {} Fc {} Hz", i + 1, f.kind.code(), f.freq));
+            if f.kind.has_gain() {
+                out.push_str(&format!(" Gain {} dB", f.gain_db));
+            }
+            out.push_str(&format!(" Q {}\n", f.q));
+        }
+        out
+    }
+}
+
+/// Parse an EqualizerAPO config such as AutoEQ's `ParametricEQ.txt`: a `Preamp: -6.2 dB`
+/// line and `Filter 1: ON PK Fc 105 Hz Gain -2.9 dB Q 0.70` lines. Filters that are
+/// OFF or of a type without a biquad equivalent are skipped. None if no line was
+/// understood.
+pub fn parse_apo(text: &str) -> Option<ParametricEq> {
+    // NaN would survive clamping and silence the whole chain
+    let number = |v: &str| v.parse::<f32>().ok().filter(|n| n.is_finite());
+    let mut eq = ParametricEq::default();
+    let mut recognised = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Preamp:") {
+            if let Some(db) = rest.split_whitespace().next().and_then(number) {
+                eq.preamp_db = f32::clamp(db, -MAX_PARAM_GAIN, MAX_PARAM_GAIN);
+                recognised = true;
+            }
+            continue;
+        }
+        if !line.starts_with("Filter") || eq.filters.len() >= MAX_FILTERS {
+            continue;
+        }
+        let Some((_, spec)) = line.split_once(':') else {
+            continue;
+        };
+        let mut tokens = spec.split_whitespace();
+        if tokens.next() != Some("ON") {
+            continue;
+        }
+        let Some(kind) = tokens.next().and_then(FilterKind::parse) else {
+            continue;
+        };
+        let (mut freq, mut gain_db, mut q) = (None, 0.0, DEFAULT_Q);
+        while let Some(token) = tokens.next() {
+            let mut value = || tokens.next().and_then(number);
+            match token {
+                "Fc" => freq = value(),
+                "Gain" => gain_db = value().unwrap_or(0.0),
+                "Q" => q = value().unwrap_or(DEFAULT_Q),
+                _ => {}
+            }
+        }
+        let Some(freq) = freq else {
+            continue;
+        };
+        eq.filters.push(ParamFilter {
+            kind,
+            freq: freq.clamp(MIN_FREQ, MAX_FREQ),
+            gain_db: gain_db.clamp(-MAX_PARAM_GAIN, MAX_PARAM_GAIN),
+            q: q.clamp(MIN_Q, MAX_Q),
+        });
+        recognised = true;
+    }
+    recognised.then_some(eq)
+}
+
+/// Read an EqualizerAPO / AutoEQ file. `~/` is expanded.
+pub fn import_apo(path: &str) -> Result<ParametricEq, String> {
+    let path = expand_tilde(path);
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_apo(&text).ok_or_else(|| format!("No EqualizerAPO filters in {}", path.display()))
+}
+
+#[derive(Clone, PartialEq)]
 pub struct EqParams {
     pub enabled: bool,
     pub gains: [f32; NUM_BANDS],
     pub preset_index: usize,
+    pub mode: EqMode,
+    pub parametric: ParametricEq,
+    /// Name of the user preset last loaded, shown instead of the built-in one
+    pub user_preset: Option<String>,
+    /// Lower the level by the curve's highest boost so it can't clip
+    pub auto_preamp: bool,
+    /// True-peak limiter at the end of the chain
+    pub limiter: bool,
 }
 
 impl Default for EqParams {
@@ -35,6 +301,11 @@ impl Default for EqParams {
             enabled: true,
             gains: [0.0; NUM_BANDS],
             preset_index: 0,
+            mode: EqMode::Graphic,
+            parametric: ParametricEq::default(),
+            user_preset: None,
+            auto_preamp: true,
+            limiter: false,
         }
     }
 }
@@ -106,56 +377,138 @@ pub const PRESETS: &[(&str, [f32; NUM_BANDS])] = &[
     ]),
 ];
 
-fn make_filter(freq: f32, gain_db: f32, sample_rate: f32) -> DirectForm2Transposed<f32> {
+/// Graphic bands keep the tuning they have always had: biquad's `from_params`
+/// normalizes by 2·fs, which puts each band two octaves below its label. Saved
+/// curves and the built-in presets were made to sound right that way.
+fn graphic_coefficients(gain_db: f32, freq: f32, sample_rate: f32) -> Coefficients<f32> {
     let max_freq = sample_rate / 2.0 - 1.0;
     let clamped_freq = freq.min(max_freq).max(1.0);
-    let coeffs = Coefficients::<f32>::from_params(
-        Type::PeakingEQ(gain_db),
-        sample_rate.hz(),
-        clamped_freq.hz(),
-        EQ_Q,
-    )
-    .unwrap_or_else(|_| {
+    let peaking = |gain| {
         Coefficients::<f32>::from_params(
-            Type::PeakingEQ(0.0),
+            Type::PeakingEQ(gain),
             sample_rate.hz(),
             clamped_freq.hz(),
             EQ_Q,
         )
-        .unwrap()
-    });
-    DirectForm2Transposed::<f32>::new(coeffs)
+    };
+    peaking(gain_db).unwrap_or_else(|_| peaking(0.0).unwrap())
+}
+
+fn coefficients(kind: Type<f32>, freq: f32, q: f32, sample_rate: f32) -> Coefficients<f32> {
+    let max_freq = sample_rate / 2.0 - 1.0;
+    let clamped_freq = freq.min(max_freq).max(1.0);
+    // biquad's from_params divides by 2·fs where it should multiply by 2, which puts
+    // every filter two octaves low; normalize to Nyquist ourselves
+    let normalized = 2.0 * clamped_freq / sample_rate;
+    Coefficients::<f32>::from_normalized_params(kind, normalized, q).unwrap_or_else(|_| {
+        Coefficients::<f32>::from_normalized_params(Type::PeakingEQ(0.0), normalized, EQ_Q)
+            .unwrap()
+    })
+}
+
+/// Filter stages of the active curve, and the linear gain applied before them.
+fn stages(params: &EqParams, sample_rate: f32) -> (Vec<Coefficients<f32>>, f32) {
+    match params.mode {
+        EqMode::Graphic => {
+            let stages = BAND_FREQS
+                .iter()
+                .zip(params.gains)
+                .map(|(&freq, gain)| graphic_coefficients(gain, freq, sample_rate))
+                .collect();
+            (stages, 1.0)
+        }
+        EqMode::Parametric => {
+            let p = &params.parametric;
+            let stages = p
+                .filters
+                .iter()
+                .map(|f| coefficients(f.kind.biquad_type(f.gain_db), f.freq, f.q, sample_rate))
+                .collect();
+            (stages, 10f32.powf(p.preamp_db / 20.0))
+        }
+    }
+}
+
+/// Gain of the active curve at `freq`, in dB (preamp included).
+pub fn response_db(params: &EqParams, freq: f32, sample_rate: f32) -> f32 {
+    let (stages, preamp) = stages(params, sample_rate);
+    stages_response_db(&stages, preamp, freq, sample_rate)
+}
+
+fn stages_response_db(
+    stages: &[Coefficients<f32>],
+    preamp: f32,
+    freq: f32,
+    sample_rate: f32,
+) -> f32 {
+    let w = std::f32::consts::TAU * freq / sample_rate;
+    let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+    let mut db = 20.0 * preamp.log10();
+    for c in stages {
+        let (nr, ni) = (c.b0 + c.b1 * c1 + c.b2 * c2, -(c.b1 * s1 + c.b2 * s2));
+        let (dr, di) = (1.0 + c.a1 * c1 + c.a2 * c2, -(c.a1 * s1 + c.a2 * s2));
+        db += 10.0 * ((nr * nr + ni * ni) / (dr * dr + di * di)).log10();
+    }
+    db
+}
+
+/// Gain the auto preamp applies, in dB: minus the highest point of the curve
+/// (0 when it never boosts).
+pub fn auto_preamp_db(params: &EqParams, sample_rate: f32) -> f32 {
+    let (stages, preamp) = stages(params, sample_rate);
+    let top = (sample_rate / 2.0 * 0.95).min(20000.0);
+    // A dense log grid plus every band and filter center, where the peaks are
+    let grid = (0..=240).map(|i| 20.0 * (top / 20.0).powf(i as f32 / 240.0));
+    let centers: Vec<f32> = match params.mode {
+        EqMode::Graphic => BAND_FREQS.to_vec(),
+        EqMode::Parametric => params.parametric.filters.iter().map(|f| f.freq).collect(),
+    };
+    let peak = grid
+        .chain(centers.into_iter().filter(|&f| f < top))
+        .map(|freq| stages_response_db(&stages, preamp, freq, sample_rate))
+        .fold(0f32, f32::max);
+    -peak
 }
 
 pub struct EqFilters {
-    /// filters[channel][band]
-    filters: Vec<[DirectForm2Transposed<f32>; NUM_BANDS]>,
-    cached_gains: [f32; NUM_BANDS],
-    cached_enabled: bool,
+    /// filters[channel][stage]
+    filters: Vec<Vec<DirectForm2Transposed<f32>>>,
+    /// Linear gain ahead of the filters (parametric preamp)
+    preamp: f32,
+    cached: EqParams,
     sample_rate: f32,
 }
 
 impl EqFilters {
     pub fn new(channels: u16, sample_rate: f32, params: &EqParams) -> Self {
-        let filters: Vec<_> = (0..channels as usize)
-            .map(|_| {
-                std::array::from_fn(|i| make_filter(BAND_FREQS[i], params.gains[i], sample_rate))
-            })
-            .collect();
-        EqFilters {
-            filters,
-            cached_gains: params.gains,
-            cached_enabled: params.enabled,
+        let mut eq = EqFilters {
+            filters: vec![Vec::new(); channels as usize],
+            preamp: 1.0,
+            cached: params.clone(),
             sample_rate,
+        };
+        eq.rebuild();
+        eq
+    }
+
+    fn rebuild(&mut self) {
+        let (stages, preamp) = stages(&self.cached, self.sample_rate);
+        self.preamp = preamp;
+        if self.cached.auto_preamp {
+            let db = auto_preamp_db(&self.cached, self.sample_rate);
+            self.preamp *= 10f32.powf(db / 20.0);
+        }
+        for ch_filters in &mut self.filters {
+            *ch_filters = stages.iter().map(|&c| DirectForm2Transposed::<f32>::new(c)).collect();
         }
     }
 
     pub fn process(&mut self, sample: f32, channel: usize) -> f32 {
-        if !self.cached_enabled {
+        if !self.cached.enabled {
             return sample;
         }
         let ch_filters = &mut self.filters[channel];
-        let mut out = sample;
+        let mut out = sample * self.preamp;
         for filter in ch_filters.iter_mut() {
             out = filter.run(out);
         }
@@ -163,18 +516,18 @@ impl EqFilters {
     }
 
     pub fn update_if_changed(&mut self, params: &EqParams) {
-        if params.enabled == self.cached_enabled && params.gains == self.cached_gains {
+        if *params == self.cached {
             return;
         }
-        self.cached_enabled = params.enabled;
-        if params.gains != self.cached_gains {
-            self.cached_gains = params.gains;
-            for ch_filters in &mut self.filters {
-                for (i, filter) in ch_filters.iter_mut().enumerate() {
-                    *filter =
-                        make_filter(BAND_FREQS[i], self.cached_gains[i], self.sample_rate);
-                }
-            }
+        let curve_changed = params.mode != self.cached.mode
+            || params.auto_preamp != self.cached.auto_preamp
+            || match params.mode {
+                EqMode::Graphic => params.gains != self.cached.gains,
+                EqMode::Parametric => params.parametric != self.cached.parametric,
+            };
+        self.cached = params.clone();
+        if curve_changed {
+            self.rebuild();
         }
     }
 }
@@ -189,6 +542,12 @@ fn config_path() -> std::path::PathBuf {
         .join("eq")
 }
 
+/// Parametric filters are kept apart, in EqualizerAPO format, so the file can be
+/// swapped for any AutoEQ `ParametricEQ.txt`.
+fn parametric_path() -> std::path::PathBuf {
+    config_path().with_file_name("eq_parametric")
+}
+
 pub fn load_eq() -> EqParams {
     let content = match std::fs::read_to_string(config_path()) {
         Ok(c) => c,
@@ -210,10 +569,26 @@ pub fn load_eq() -> EqParams {
             gains[i] = g.clamp(-MAX_GAIN, MAX_GAIN);
         }
     }
+    let mode = match lines.next().map(str::trim) {
+        Some("parametric") => EqMode::Parametric,
+        _ => EqMode::Graphic,
+    };
+    let parametric = std::fs::read_to_string(parametric_path())
+        .ok()
+        .and_then(|text| parse_apo(&text))
+        .unwrap_or_default();
+    let user_preset = lines.next().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+    let auto_preamp = lines.next().map(|s| s.trim() != "false").unwrap_or(true);
+    let limiter = lines.next().map(|s| s.trim() == "true").unwrap_or(false);
     EqParams {
         enabled,
         gains,
         preset_index,
+        mode,
+        parametric,
+        user_preset,
+        auto_preamp,
+        limiter,
     }
 }
 
@@ -222,12 +597,20 @@ pub fn save_eq(params: &EqParams) {
     let _ = std::fs::create_dir_all(&dir);
     let gains_str: Vec<String> = params.gains.iter().map(|g| format!("{g}")).collect();
     let content = format!(
-        "{}\n{}\n{}",
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}",
         if params.enabled { "true" } else { "false" },
         params.preset_index,
-        gains_str.join(",")
+        gains_str.join(","),
+        match params.mode {
+            EqMode::Graphic => "graphic",
+            EqMode::Parametric => "parametric",
+        },
+        params.user_preset.as_deref().unwrap_or(""),
+        params.auto_preamp,
+        params.limiter,
     );
     let _ = std::fs::write(config_path(), content);
+    let _ = std::fs::write(parametric_path(), params.parametric.to_apo());
 }
 
 // --- Drawing ---
@@ -249,7 +632,15 @@ fn format_freq(f: f32) -> String {
     }
 }
 
-pub fn draw_eq(frame: &mut Frame, params: &EqParams, selected_band: usize, hover_band: Option<usize>, theme: &Theme) -> Rect {
+pub fn draw_eq(
+    frame: &mut Frame,
+    params: &EqParams,
+    selected_band: usize,
+    hover_band: Option<usize>,
+    param_cursor: (usize, ParamField),
+    import_path: Option<&str>,
+    theme: &Theme,
+) -> Rect {
     let area = frame.area();
     // 32 bars × 2 chars = 64, + 1 leading + 4 dB label + 2 border = 71
     let popup_width = 74u16.min(area.width);
@@ -260,10 +651,16 @@ pub fn draw_eq(frame: &mut Frame, params: &EqParams, selected_band: usize, hover
 
     frame.render_widget(Clear, popup_area);
 
-    let preset_name = PRESETS
-        .get(params.preset_index)
-        .map(|(name, _)| *name)
-        .unwrap_or("Custom");
+    if params.mode == EqMode::Parametric {
+        return draw_parametric(frame, popup_area, params, param_cursor, import_path, theme);
+    }
+
+    let preset_name = params.user_preset.as_deref().unwrap_or_else(|| {
+        PRESETS
+            .get(params.preset_index)
+            .map(|(name, _)| *name)
+            .unwrap_or("Custom")
+    });
 
     let status = if params.enabled { "ON" } else { "OFF" };
     let sel_freq = format_freq(BAND_FREQS[selected_band]);
@@ -278,8 +675,9 @@ pub fn draw_eq(frame: &mut Frame, params: &EqParams, selected_band: usize, hover
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .title(format!(" Equalizer [{status}] "))
+        .title(protection_title(params))
         .title_bottom(Line::from(
-            " ←/→: Band  ↑/↓: Gain  p: Preset  0: Flat  s: Toggle ",
+            " ←/→: Band  ↑/↓: Gain  p/P: Preset/Presets  0: Flat  s: Toggle  m: Mode ",
         ));
 
     let inner = block.inner(popup_area);
@@ -435,3 +833,239 @@ pub fn draw_eq(frame: &mut Frame, params: &EqParams, selected_band: usize, hover
 
     inner
 }
+
+/// Right-hand title: auto preamp and limiter state, with their keys.
+fn protection_title(params: &EqParams) -> Line<'static> {
+    let preamp = if params.auto_preamp {
+        format!("auto {} dB", format_db(auto_preamp_db(params, 48000.0)))
+    } else {
+        "off".to_string()
+    };
+    let limiter = if params.limiter { "on" } else { "off" };
+    Line::from(format!(" A: Preamp {preamp}  l: Limiter {limiter} ")).alignment(Alignment::Right)
+}
+
+fn format_db(db: f32) -> String {
+    if db >= 0.0 { format!("+{db:.1}") } else { format!("{db:.1}") }
+}
+
+/// Curve of the parametric EQ over a log frequency axis, then the filter table.
+fn draw_parametric(
+    frame: &mut Frame,
+    popup_area: Rect,
+    params: &EqParams,
+    (row, field): (usize, ParamField),
+    import_path: Option<&str>,
+    theme: &Theme,
+) -> Rect {
+    let status = if params.enabled { "ON" } else { "OFF" };
+    let p = &params.parametric;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(match &params.user_preset {
+            Some(name) => format!(" Parametric EQ [{status}] · {name} "),
+            None => format!(" Parametric EQ [{status}] "),
+        })
+        .title(protection_title(params))
+        .title_bottom(Line::from(
+            " ↑↓ Row  ←→ Field  +/- Adjust  a Add  d Del  i Import  P Presets  m Mode ",
+        ));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+    if inner.height < 12 || inner.width < 40 {
+        return inner;
+    }
+
+    let dim = Style::default().fg(theme.dimmed);
+    let mut lines: Vec<Line> = Vec::new();
+
+    // Response graph, 20 Hz – 20 kHz; the scale grows to fit big corrections
+    let graph_width = inner.width.saturating_sub(6) as usize;
+    let graph_height = 7usize;
+    let curve: Vec<f32> = (0..graph_width)
+        .map(|x| {
+            let freq = 20.0 * 1000f32.powf(x as f32 / (graph_width - 1).max(1) as f32);
+            response_db(params, freq, 48000.0)
+        })
+        .collect();
+    let peak = curve.iter().fold(0f32, |m, db| m.max(db.abs()));
+    let range = if peak > MAX_GAIN { (peak / 6.0).ceil() * 6.0 } else { MAX_GAIN };
+    let zero_row = graph_height / 2;
+    let curve_rows: Vec<usize> = curve
+        .iter()
+        .map(|db| ((range - db) / (2.0 * range) * (graph_height - 1) as f32).round() as usize)
+        .collect();
+    for r in 0..graph_height {
+        let line: String = curve_rows
+            .iter()
+            .map(|&curve_row| {
+                if r == curve_row {
+                    '•'
+                } else if (curve_row < r && r < zero_row) || (zero_row < r && r < curve_row) {
+                    '│'
+                } else if r == zero_row {
+                    '─'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        let label = match r {
+            0 => format!(" +{range:.0}"),
+            r if r == zero_row => "  0".to_string(),
+            r if r == graph_height - 1 => format!(" -{range:.0}"),
+            _ => String::new(),
+        };
+        lines.push(Line::from(vec![
+            Span::raw(" "),
+            Span::styled(line, Style::default().fg(theme.accent)),
+            Span::styled(label, dim),
+        ]));
+    }
+    let mut axis = vec![' '; graph_width];
+    for (freq, label) in [(20.0, "20"), (100.0, "100"), (1000.0, "1k"), (10000.0, "10k")] {
+        let col = ((freq / 20.0f32).log10() / 3.0 * (graph_width - 1) as f32) as usize;
+        for (j, ch) in label.chars().enumerate() {
+            if let Some(c) = axis.get_mut(col + j) {
+                *c = ch;
+            }
+        }
+    }
+    lines.push(Line::from(vec![
+        Span::raw(" "),
+        Span::styled(axis.into_iter().collect::<String>(), Style::default().fg(theme.secondary)),
+        Span::styled(" Hz", dim),
+    ]));
+    lines.push(Line::styled(
+        format!("  {:<4}{:<12}{:>10}{:>10}{:>8}", "#", "Type", "Freq", "Gain", "Q"),
+        dim.add_modifier(Modifier::BOLD),
+    ));
+
+    // Filter table: the preamp row, then one row per filter, scrolled to the cursor
+    let footer = usize::from(import_path.is_some());
+    let visible = (inner.height as usize).saturating_sub(lines.len() + footer).max(1);
+    let scroll = row.saturating_sub(visible - 1);
+    let cell = |r: usize, f: ParamField, text: String| {
+        let style = if r == row && (r == 0 || f == field) {
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else if r == row {
+            Style::default().fg(theme.secondary)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        Span::styled(text, style)
+    };
+    for r in (scroll..=p.filters.len()).take(visible) {
+        let marker = if r == row { "▸ " } else { "  " };
+        let spans = match r {
+            0 => vec![
+                Span::raw(marker),
+                Span::styled(format!("{:<16}", "Preamp"), Style::default().fg(theme.text)),
+                Span::raw(" ".repeat(10)),
+                cell(0, ParamField::Gain, format!("{:>7} dB", format_db(p.preamp_db))),
+            ],
+            r => {
+                let f = &p.filters[r - 1];
+                let gain = if f.kind.has_gain() {
+                    format!("{:>7} dB", format_db(f.gain_db))
+                } else {
+                    format!("{:>10}", "—")
+                };
+                vec![
+                    Span::raw(marker),
+                    Span::styled(format!("{r:<4}"), dim),
+                    cell(r, ParamField::Kind, format!("{:<12}", f.kind.label())),
+                    cell(r, ParamField::Freq, format!("{:>7.0} Hz", f.freq)),
+                    cell(r, ParamField::Gain, gain),
+                    cell(r, ParamField::Q, format!("{:>8.2}", f.q)),
+                ]
+            }
+        };
+        lines.push(Line::from(spans));
+    }
+    if p.filters.is_empty() && visible > 1 {
+        lines.push(Line::styled("  No filters — a: add, i: import ParametricEQ.txt", dim));
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    if let Some(path) = import_path {
+        let prompt = Line::from(vec![
+            Span::styled(" Import: ", Style::default().fg(theme.secondary)),
+            Span::styled(format!("{path}▏"), Style::default().fg(theme.text)),
+        ]);
+        let y = inner.y + inner.height - 1;
+        frame.render_widget(Paragraph::new(prompt), Rect::new(inner.x, y, inner.width, 1));
+    }
+
+    inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(kind: FilterKind, freq: f32, gain_db: f32, q: f32) -> ParamFilter {
+        ParamFilter { kind, freq, gain_db, q }
+    }
+
+    #[test]
+    fn apo_autoeq_file() {
+        let text = "Preamp: -6.2 dB\n\
+            Filter 1: ON LSC Fc 105 Hz Gain 5.5 dB Q 0.70\n\
+            Filter 2: ON PK Fc 2500 Hz Gain -2.9 dB Q 1.41\n\
+            Filter 3: OFF PK Fc 4000 Hz Gain 3.0 dB Q 2.00\n\
+            Filter 4: ON BP Fc 1000 Hz Q 1.00\n\
+            Filter 5: ON HPQ Fc 20 Hz\n";
+        let eq = parse_apo(text).unwrap();
+        assert_eq!(eq.preamp_db, -6.2);
+        assert!(
+            eq.filters
+                == [
+                    filter(FilterKind::LowShelf, 105.0, 5.5, 0.7),
+                    filter(FilterKind::Peaking, 2500.0, -2.9, 1.41),
+                    filter(FilterKind::HighPass, 20.0, 0.0, DEFAULT_Q),
+                ]
+        );
+    }
+
+    #[test]
+    fn apo_round_trips() {
+        let eq = ParametricEq {
+            preamp_db: -3.5,
+            filters: vec![
+                filter(FilterKind::Peaking, 1000.0, 4.0, 2.0),
+                filter(FilterKind::Notch, 60.0, 0.0, 10.0),
+                filter(FilterKind::HighShelf, 8000.0, -1.5, 0.71),
+            ],
+        };
+        assert!(parse_apo(&eq.to_apo()) == Some(eq));
+    }
+
+    #[test]
+    fn apo_rejects_non_finite_and_clamps() {
+        let text = "Preamp: NaN dB\n\
+            Filter 1: ON PK Fc inf Hz Gain 3 dB Q 1\n\
+            Filter 2: ON PK Fc 1000 Hz Gain nan dB Q -inf\n\
+            Filter 3: ON PK Fc 99999 Hz Gain 100 dB Q 0\n";
+        let eq = parse_apo(text).unwrap();
+        assert_eq!(eq.preamp_db, 0.0);
+        assert!(
+            eq.filters
+                == [
+                    filter(FilterKind::Peaking, 1000.0, 0.0, DEFAULT_Q),
+                    filter(FilterKind::Peaking, MAX_FREQ, MAX_PARAM_GAIN, MIN_Q),
+                ]
+        );
+    }
+
+    #[test]
+    fn apo_limits() {
+        assert!(parse_apo("").is_none());
+        assert!(parse_apo("Preamp: loud\nFilter 1: OFF PK Fc 100 Hz").is_none());
+        let many: String =
+            (0..40).map(|i| format!("Filter {i}: ON PK Fc {} Hz\n", 100 + i)).collect();
+        assert_eq!(parse_apo(&many).unwrap().filters.len(), MAX_FILTERS);
+    }
+}
@@ -0,0 +1,415 @@
+use std::{fs, io, path::PathBuf};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::eq::{self, EqMode, EqParams, ParametricEq, BAND_FREQS, MAX_GAIN, NUM_BANDS, PRESETS};
+use crate::theme::Theme;
+use crate::{config_dir, expand_tilde};
+
+/// The curve a preset sets, for the mode it was saved in.
+#[derive(Clone)]
+pub enum Curve {
+    Graphic([f32; NUM_BANDS]),
+    Parametric(ParametricEq),
+}
+
+impl Curve {
+    /// Whatever the EQ is currently playing.
+    pub fn from_params(params: &EqParams) -> Self {
+        match params.mode {
+            EqMode::Graphic => Curve::Graphic(params.gains),
+            EqMode::Parametric => Curve::Parametric(params.parametric.clone()),
+        }
+    }
+
+    /// EqualizerAPO text: a `GraphicEQ:` line, or preamp and filter lines.
+    fn to_text(&self) -> String {
+        match self {
+            Curve::Graphic(gains) => {
+                let points: Vec<String> = BAND_FREQS
+                    .iter()
+                    .zip(gains)
+                    .map(|(freq, gain)| format!("{freq} {gain}"))
+                    .collect();
+                format!("GraphicEQ: {}\n", points.join("; "))
+            }
+            Curve::Parametric(p) => p.to_apo(),
+        }
+    }
+
+    /// Parse what `to_text` writes, or any EqualizerAPO / AutoEQ `GraphicEQ.txt` or
+    /// `ParametricEQ.txt`. Graphic points are interpolated onto the 32 bands.
+    fn parse(text: &str) -> Option<Self> {
+        let graphic = text
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("GraphicEQ:"));
+        let Some(graphic) = graphic else {
+            return eq::parse_apo(text).map(Curve::Parametric);
+        };
+        let points: Vec<(f32, f32)> = graphic
+            .split(';')
+            .filter_map(|pair| {
+                let mut nums = pair
+                    .split_whitespace()
+                    .map(|v| v.parse::<f32>().ok().filter(|n| n.is_finite()));
+                Some((nums.next()??, nums.next()??))
+            })
+            .filter(|&(freq, _)| freq > 0.0)
+            .collect();
+        (!points.is_empty()).then(|| Curve::Graphic(bands_from_points(points)))
+    }
+
+    fn mode_label(&self) -> &'static str {
+        match self {
+            Curve::Graphic(_) => "graphic",
+            Curve::Parametric(_) => "parametric",
+        }
+    }
+}
+
+/// Gain at each band, interpolated linearly over log frequency between the
+/// nearest points and held flat past either end.
+fn bands_from_points(mut points: Vec<(f32, f32)>) -> [f32; NUM_BANDS] {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut gains = [0.0; NUM_BANDS];
+    for (gain, &freq) in gains.iter_mut().zip(&BAND_FREQS) {
+        let above = points.partition_point(|&(f, _)| f < freq);
+        let db = match (points.get(above.wrapping_sub(1)), points.get(above)) {
+            (Some(&(f0, g0)), Some(&(f1, g1))) => {
+                let t = (freq / f0).ln() / (f1 / f0).ln();
+                g0 + (g1 - g0) * t
+            }
+            (Some(&(_, g)), None) | (None, Some(&(_, g))) => g,
+            (None, None) => 0.0,
+        };
+        *gain = ((db * 10.0).round() / 10.0).clamp(-MAX_GAIN, MAX_GAIN);
+    }
+    gains
+}
+
+pub struct Preset {
+    pub name: String,
+    pub curve: Curve,
+    /// One of `eq::PRESETS`; these can't be renamed or deleted
+    pub builtin: bool,
+}
+
+/// Presets saved by the user, one EqualizerAPO text file each.
+fn presets_dir() -> PathBuf {
+    config_dir().join("eq_presets")
+}
+
+fn preset_path(name: &str) -> PathBuf {
+    presets_dir().join(format!("{name}.txt"))
+}
+
+/// Names become file names, so no separators or hidden files.
+fn check_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\0']) {
+        return Err(format!("Invalid preset name: \"{name}\""));
+    }
+    Ok(name)
+}
+
+/// Built-in presets, then the user's in name order.
+pub fn list() -> Vec<Preset> {
+    let mut presets: Vec<Preset> = PRESETS
+        .iter()
+        .map(|(name, gains)| Preset {
+            name: name.to_string(),
+            curve: Curve::Graphic(*gains),
+            builtin: true,
+        })
+        .collect();
+    let mut user: Vec<Preset> = fs::read_dir(presets_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "txt" {
+                return None;
+            }
+            let curve = Curve::parse(&fs::read_to_string(&path).ok()?)?;
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some(Preset { name, curve, builtin: false })
+        })
+        .collect();
+    user.sort_by_key(|p| p.name.to_lowercase());
+    presets.extend(user);
+    presets
+}
+
+/// The preset called `name`; a user preset shadows a built-in of the same name.
+pub fn find(name: &str) -> Option<Preset> {
+    list().into_iter().rfind(|p| p.name == name)
+}
+
+/// Save `curve` as `name`, replacing any user preset of that name.
+pub fn save(name: &str, curve: &Curve) -> Result<(), String> {
+    let name = check_name(name)?;
+    fs::create_dir_all(presets_dir())
+        .and_then(|_| fs::write(preset_path(name), curve.to_text()))
+        .map_err(|e| format!("Saving preset failed: {e}"))
+}
+
+pub fn rename(from: &str, to: &str) -> Result<(), String> {
+    let to = check_name(to)?;
+    if preset_path(to).exists() {
+        return Err(format!("A preset called \"{to}\" already exists"));
+    }
+    fs::rename(preset_path(from), preset_path(to)).map_err(|e| format!("Renaming failed: {e}"))
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(preset_path(name))
+}
+
+/// Add a preset from an EqualizerAPO / AutoEQ file, named after it. Returns the name.
+pub fn import(path: &str) -> Result<String, String> {
+    let path = expand_tilde(path);
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let curve = Curve::parse(&text)
+        .ok_or_else(|| format!("No EqualizerAPO filters in {}", path.display()))?;
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Imported".into());
+    // Don't overwrite a preset of the same name
+    let name = (1..)
+        .map(|n| if n == 1 { stem.clone() } else { format!("{stem} ({n})") })
+        .find(|name| !preset_path(name).exists())
+        .unwrap_or(stem);
+    save(&name, &curve)?;
+    Ok(name)
+}
+
+/// Write a preset to `path`, or to `<path>/<name>.txt` when that is a directory.
+pub fn export(preset: &Preset, path: &str) -> Result<PathBuf, String> {
+    let mut dest = expand_tilde(path);
+    if dest.is_dir() {
+        dest.push(format!("{}.txt", preset.name));
+    }
+    fs::write(&dest, preset.curve.to_text()).map_err(|e| format!("{}: {e}", dest.display()))?;
+    Ok(dest)
+}
+
+/// Load a preset into the EQ, switching to the mode it was saved in.
+pub fn apply(preset: &Preset, params: &mut EqParams) {
+    match &preset.curve {
+        Curve::Graphic(gains) => {
+            params.mode = EqMode::Graphic;
+            params.gains = *gains;
+        }
+        Curve::Parametric(p) => {
+            params.mode = EqMode::Parametric;
+            params.parametric = p.clone();
+        }
+    }
+    if preset.builtin {
+        params.preset_index = PRESETS.iter().position(|(n, _)| *n == preset.name).unwrap_or(0);
+        params.user_preset = None;
+    } else {
+        params.user_preset = Some(preset.name.clone());
+    }
+}
+
+/// What the picker's text prompt is for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Prompt {
+    SaveAs,
+    Rename,
+    Import,
+    Export,
+}
+
+impl Prompt {
+    fn label(self) -> &'static str {
+        match self {
+            Prompt::SaveAs => "Save as",
+            Prompt::Rename => "Rename to",
+            Prompt::Import => "Import file",
+            Prompt::Export => "Export to",
+        }
+    }
+}
+
+/// Preset picker popup over the equalizer.
+pub struct PresetPicker {
+    pub presets: Vec<Preset>,
+    pub selected: usize,
+    /// Text being typed, and what it's for
+    pub prompt: Option<(Prompt, String)>,
+    /// User preset the last action renamed (to the new name) or deleted (`None`),
+    /// for the EQ to stop naming the old one
+    pub moved: Option<(String, Option<String>)>,
+}
+
+impl PresetPicker {
+    /// Open with the preset currently in use selected.
+    pub fn open(params: &EqParams) -> Self {
+        let mut picker =
+            PresetPicker { presets: Vec::new(), selected: 0, prompt: None, moved: None };
+        let current = match &params.user_preset {
+            Some(name) => name.as_str(),
+            None => PRESETS.get(params.preset_index).map_or("", |(name, _)| name),
+        };
+        picker.reload(current);
+        picker
+    }
+
+    /// Re-read the presets and select `name` if it is a user preset, or the
+    /// built-in of that name.
+    fn reload(&mut self, name: &str) {
+        self.presets = list();
+        self.selected = self
+            .presets
+            .iter()
+            .rposition(|p| p.name == name)
+            .unwrap_or(self.selected)
+            .min(self.presets.len() - 1);
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.presets.len() - 1);
+    }
+
+    pub fn selected(&self) -> &Preset {
+        &self.presets[self.selected]
+    }
+
+    /// Start typing for `prompt`. Renaming needs a user preset.
+    pub fn start(&mut self, prompt: Prompt) -> Result<(), String> {
+        let name = &self.selected().name;
+        let text = match prompt {
+            Prompt::Rename if self.selected().builtin => {
+                return Err("Built-in presets can't be renamed".into());
+            }
+            Prompt::Rename => name.clone(),
+            Prompt::Export => format!("~/{name}.txt"),
+            Prompt::SaveAs | Prompt::Import => String::new(),
+        };
+        self.prompt = Some((prompt, text));
+        Ok(())
+    }
+
+    /// Act on the typed text. Returns a message for the notice line.
+    pub fn submit(&mut self, params: &EqParams) -> Result<String, String> {
+        let Some((prompt, text)) = self.prompt.take() else {
+            return Ok(String::new());
+        };
+        match prompt {
+            Prompt::SaveAs => {
+                save(&text, &Curve::from_params(params))?;
+                self.reload(text.trim());
+                Ok(format!("Saved EQ preset \"{}\"", text.trim()))
+            }
+            Prompt::Rename => {
+                let from = self.selected().name.clone();
+                rename(&from, &text)?;
+                self.reload(text.trim());
+                self.moved = Some((from.clone(), Some(text.trim().to_string())));
+                Ok(format!("Renamed \"{from}\" to \"{}\"", text.trim()))
+            }
+            Prompt::Import => {
+                let name = import(&text)?;
+                self.reload(&name);
+                Ok(format!("Imported EQ preset \"{name}\""))
+            }
+            Prompt::Export => {
+                let dest = export(self.selected(), &text)?;
+                Ok(format!("Exported to {}", dest.display()))
+            }
+        }
+    }
+
+    /// Delete the selected user preset.
+    pub fn delete_selected(&mut self) -> Result<String, String> {
+        let preset = self.selected();
+        if preset.builtin {
+            return Err("Built-in presets can't be deleted".into());
+        }
+        let name = preset.name.clone();
+        delete(&name).map_err(|e| format!("Deleting failed: {e}"))?;
+        self.reload("");
+        self.moved = Some((name.clone(), None));
+        Ok(format!("Deleted EQ preset \"{name}\""))
+    }
+}
+
+// --- Drawing ---
+
+pub fn draw_preset_picker(frame: &mut Frame, picker: &PresetPicker, theme: &Theme) {
+    let area = frame.area();
+    let popup_w = 76u16.min(area.width);
+    let popup_h = (picker.presets.len() as u16 + 4).clamp(8, 20).min(area.height);
+    let popup_x = area.width.saturating_sub(popup_w) / 2;
+    let popup_y = area.height.saturating_sub(popup_h) / 2;
+    let popup_area = Rect::new(popup_x, popup_y, popup_w, popup_h);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" EQ Presets ")
+        .title_bottom(Line::from(
+            " Enter: Load  s: Save as  r: Rename  d: Delete  i: Import  x: Export ",
+        ));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    // Leave the last row for the prompt
+    let visible = (inner.height as usize).saturating_sub(2).max(1);
+    let scroll = picker.selected.saturating_sub(visible - 1);
+    let name_w = (inner.width as usize).saturating_sub(28);
+    let mut lines = vec![Line::styled(
+        "  t / a / g: Use for this track / album / genre",
+        Style::default().fg(theme.dimmed),
+    )];
+    for (i, preset) in picker.presets.iter().enumerate().skip(scroll).take(visible) {
+        let is_sel = i == picker.selected;
+        let name: String = preset.name.chars().take(name_w).collect();
+        let style = if is_sel {
+            Style::default()
+                .fg(Color::Black)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { " > " } else { "   " }, Style::default().fg(theme.accent)),
+            Span::styled(format!(" {name:<name_w$} "), style),
+            Span::styled(
+                format!(" {:<11}", preset.curve.mode_label()),
+                Style::default().fg(theme.secondary),
+            ),
+            Span::styled(
+                if preset.builtin { "built-in" } else { "" },
+                Style::default().fg(theme.dimmed),
+            ),
+        ]));
+    }
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    if let Some((prompt, text)) = &picker.prompt {
+        let line = Line::from(vec![
+            Span::styled(format!(" {}: ", prompt.label()), Style::default().fg(theme.secondary)),
+            Span::styled(format!("{text}▏"), Style::default().fg(theme.text)),
+        ]);
+        let y = inner.y + inner.height.saturating_sub(1);
+        frame.render_widget(Paragraph::new(line), Rect::new(inner.x, y, inner.width, 1));
+    }
+}
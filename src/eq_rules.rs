@@ -0,0 +1,121 @@
+use std::{fs, io, path::Path};
+
+use crate::{config_dir, expand_tilde, TrackMeta};
+
+/// What a rule matches on. Earlier scopes win when several rules match.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// A file, or every file below a directory
+    Path,
+    Album,
+    Genre,
+}
+
+impl Scope {
+    fn name(self) -> &'static str {
+        match self {
+            Scope::Path => "path",
+            Scope::Album => "album",
+            Scope::Genre => "genre",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Scope::Path, Scope::Album, Scope::Genre]
+            .into_iter()
+            .find(|s| s.name() == name)
+    }
+}
+
+/// Play a track matching `pattern` with the EQ preset called `preset`.
+pub struct Rule {
+    pub scope: Scope,
+    pub pattern: String,
+    pub preset: String,
+}
+
+impl Rule {
+    /// For the progress bar and notices: "Heavy Metal (genre metal)".
+    pub fn label(&self) -> String {
+        let pattern = match self.scope {
+            // The file or folder name is enough to recognise it
+            Scope::Path => Path::new(&self.pattern)
+                .file_name()
+                .map_or(self.pattern.clone(), |n| n.to_string_lossy().into_owned()),
+            _ => self.pattern.clone(),
+        };
+        format!("{} ({} {pattern})", self.preset, self.scope.name())
+    }
+
+    fn matches(&self, path: &Path, meta: &TrackMeta) -> bool {
+        let same = |tag: &str| tag.trim().eq_ignore_ascii_case(&self.pattern);
+        match self.scope {
+            Scope::Path => path.starts_with(expand_tilde(&self.pattern)),
+            Scope::Album => meta.album.as_deref().is_some_and(same),
+            // Multi-valued tags: "Rock; Pop", "Rock/Pop"
+            Scope::Genre => meta
+                .genre
+                .as_deref()
+                .is_some_and(|g| g.split([';', '/', ',', '\0']).any(same)),
+        }
+    }
+}
+
+fn rules_path() -> std::path::PathBuf {
+    config_dir().join("eq_rules")
+}
+
+/// Read `~/.config/tui-player/eq_rules`: `<path|album|genre> <pattern> = <preset>`
+/// lines, `#` comments.
+pub fn load_rules() -> Vec<Rule> {
+    let content = fs::read_to_string(rules_path()).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (scope, rest) = line.split_once(char::is_whitespace)?;
+            let (pattern, preset) = rest.rsplit_once('=')?;
+            let (pattern, preset) = (pattern.trim(), preset.trim());
+            if pattern.is_empty() || preset.is_empty() {
+                return None;
+            }
+            Some(Rule {
+                scope: Scope::parse(&scope.to_ascii_lowercase())?,
+                pattern: pattern.to_string(),
+                preset: preset.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The rule for this track: path rules first (the longest, most specific prefix),
+/// then album, then genre, each in file order.
+pub fn find<'a>(rules: &'a [Rule], path: &Path, meta: &TrackMeta) -> Option<&'a Rule> {
+    let matching = |scope| rules.iter().filter(move |r| r.scope == scope && r.matches(path, meta));
+    matching(Scope::Path)
+        .max_by_key(|r| expand_tilde(&r.pattern).components().count())
+        .or_else(|| matching(Scope::Album).next())
+        .or_else(|| matching(Scope::Genre).next())
+}
+
+/// Add a rule, replacing one with the same scope and pattern. Comments and the
+/// order of the other lines are kept.
+pub fn add_rule(rule: &Rule) -> io::Result<()> {
+    let content = fs::read_to_string(rules_path()).unwrap_or_default();
+    let replaced = |line: &str| {
+        let line = line.trim();
+        !line.starts_with('#')
+            && line.split_once(char::is_whitespace).is_some_and(|(scope, rest)| {
+                scope.eq_ignore_ascii_case(rule.scope.name())
+                    && rest.rsplit_once('=').is_some_and(|(p, _)| p.trim() == rule.pattern)
+            })
+    };
+    let mut lines: Vec<&str> = content.lines().filter(|l| !replaced(l)).collect();
+    let new_line = format!("{} {} = {}", rule.scope.name(), rule.pattern, rule.preset);
+    lines.push(&new_line);
+    fs::create_dir_all(config_dir())?;
+    fs::write(rules_path(), lines.join("\n") + "\n")
+}
@@ -1,4 +1,8 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
 use ratatui::{
     layout::Rect,
@@ -9,6 +13,7 @@ use ratatui::{
 };
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
+use crate::search::SearchHit;
 use crate::theme::Theme;
 
 pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "wav", "aac", "m4a"];
@@ -53,6 +58,51 @@ pub fn scan_directory(root: &Path) -> Vec<TreeItem<'static, PathBuf>> {
     items
 }
 
+/// Build the tree `scan_directory` would produce from a known list of audio files
+/// below `root`, without touching the filesystem.
+pub fn build_tree(root: &Path, files: &[PathBuf]) -> Vec<TreeItem<'static, PathBuf>> {
+    let mut subdirs: HashMap<OsString, Vec<PathBuf>> = HashMap::new();
+    let mut leaves: Vec<PathBuf> = Vec::new();
+    for file in files {
+        let Ok(rel) = file.strip_prefix(root) else {
+            continue;
+        };
+        let mut components = rel.components();
+        let Some(first) = components.next() else {
+            continue;
+        };
+        if components.next().is_some() {
+            subdirs
+                .entry(first.as_os_str().to_os_string())
+                .or_default()
+                .push(file.clone());
+        } else {
+            leaves.push(file.clone());
+        }
+    }
+
+    let mut dir_names: Vec<OsString> = subdirs.keys().cloned().collect();
+    dir_names.sort_by_key(|n| n.to_ascii_lowercase());
+    leaves.sort_by_key(|p| p.file_name().map(|n| n.to_ascii_lowercase()));
+
+    let mut items = Vec::new();
+    for name in dir_names {
+        let path = root.join(&name);
+        let children = build_tree(&path, &subdirs[&name]);
+        if let Ok(item) = TreeItem::new(path, name.to_string_lossy().to_string(), children) {
+            items.push(item);
+        }
+    }
+    for path in leaves {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        items.push(TreeItem::new_leaf(path, name));
+    }
+    items
+}
+
 /// Collect all audio file paths from the tree in display order (depth-first).
 pub fn collect_audio_files(items: &[TreeItem<'static, PathBuf>]) -> Vec<PathBuf> {
     let mut files = Vec::new();
@@ -69,36 +119,40 @@ pub fn collect_audio_files(items: &[TreeItem<'static, PathBuf>]) -> Vec<PathBuf>
     files
 }
 
-/// Fuzzy match: query chars must appear in order (case-insensitive).
-fn fuzzy_match(query: &str, haystack: &str) -> bool {
-    let mut chars = query.chars().flat_map(|c| c.to_lowercase());
-    let mut current = match chars.next() {
-        Some(c) => c,
-        None => return true,
-    };
-    for h in haystack.chars().flat_map(|c| c.to_lowercase()) {
-        if h == current {
-            current = match chars.next() {
-                Some(c) => c,
-                None => return true,
-            };
+/// Every audio leaf in the tree, without checking the files still exist.
+pub fn tree_files(items: &[TreeItem<'static, PathBuf>]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    fn walk(items: &[TreeItem<'_, PathBuf>], out: &mut Vec<PathBuf>) {
+        for item in items {
+            if item.children().is_empty() && is_audio_file(item.identifier()) {
+                out.push(item.identifier().clone());
+            }
+            walk(item.children(), out);
         }
     }
-    false
+    walk(items, &mut files);
+    files
 }
 
-/// Filter audio files by fuzzy matching against filenames. Returns matching paths.
-pub fn filter_files(items: &[TreeItem<'static, PathBuf>], query: &str) -> Vec<PathBuf> {
-    let all = collect_audio_files(items);
-    if query.is_empty() {
-        return all;
+/// `path` with its `from` prefix replaced by `to`, when it lies below `from`.
+pub fn renamed(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+        Ok(rest) => to.join(rest),
+        Err(_) => path.to_path_buf(),
     }
-    all.into_iter()
-        .filter(|p| {
-            let name = p.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
-            fuzzy_match(query, &name)
-        })
-        .collect()
+}
+
+/// Length of the longest prefix of the identifier path `ids` that exists in the tree.
+pub fn existing_depth(items: &[TreeItem<'static, PathBuf>], ids: &[PathBuf]) -> usize {
+    let mut level = items;
+    for (depth, id) in ids.iter().enumerate() {
+        match level.iter().find(|item| item.identifier() == id) {
+            Some(item) => level = item.children(),
+            None => return depth,
+        }
+    }
+    ids.len()
 }
 
 pub fn selected_file(state: &TreeState<PathBuf>) -> Option<PathBuf> {
@@ -111,6 +165,26 @@ pub fn selected_file(state: &TreeState<PathBuf>) -> Option<PathBuf> {
     }
 }
 
+/// Audio files covered by the current selection: the file itself, or every track
+/// below a selected directory or group in display order.
+pub fn selected_files(state: &TreeState<PathBuf>, items: &[TreeItem<'static, PathBuf>]) -> Vec<PathBuf> {
+    let mut level = items;
+    let mut node = None;
+    for id in state.selected() {
+        match level.iter().find(|item| item.identifier() == id) {
+            Some(item) => {
+                level = item.children();
+                node = Some(item);
+            }
+            None => return Vec::new(),
+        }
+    }
+    match node {
+        Some(item) => collect_audio_files(std::slice::from_ref(item)),
+        None => Vec::new(),
+    }
+}
+
 fn popup_area(frame: &Frame) -> Rect {
     let area = frame.area();
     let popup_width = (area.width * 80 / 100).max(40).min(area.width);
@@ -126,9 +200,9 @@ pub fn draw_file_browser(
     state: &mut TreeState<PathBuf>,
     searching: bool,
     search: &str,
-    filtered: &[PathBuf],
+    filtered: &[SearchHit],
     filter_idx: usize,
-    root_dir: Option<&Path>,
+    mode_label: &str,
     theme: &Theme,
 ) {
     let popup = popup_area(frame);
@@ -142,8 +216,10 @@ pub fn draw_file_browser(
                 Block::default()
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
-                    .title(" Files ")
-                    .title_bottom(" Enter: Play  ←/→: Expand  /: Search  Esc: Close "),
+                    .title(format!(" {mode_label} "))
+                    .title_bottom(
+                        " Enter: Play  a: Queue  p: Play Next  ←/→: Expand  /: Search  Tab: View  Esc: Close ",
+                    ),
             )
             .highlight_style(
                 Style::default()
@@ -156,18 +232,24 @@ pub fn draw_file_browser(
         frame.render_stateful_widget(tree, popup, state);
     } else {
         // Search mode: list with search query in bottom border
+        let match_style = Style::default()
+            .fg(theme.secondary)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
         let list_items: Vec<ListItem> = filtered
             .iter()
-            .map(|p| {
-                let display = root_dir
-                    .and_then(|r| p.strip_prefix(r).ok())
-                    .map(|rel| rel.to_string_lossy().to_string())
-                    .unwrap_or_else(|| {
-                        p.file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_default()
-                    });
-                ListItem::new(display)
+            .map(|hit| {
+                let spans: Vec<Span> = hit
+                    .segments
+                    .iter()
+                    .map(|(text, matched)| {
+                        if *matched {
+                            Span::styled(text.clone(), match_style)
+                        } else {
+                            Span::raw(text.clone())
+                        }
+                    })
+                    .collect();
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
@@ -179,7 +261,7 @@ pub fn draw_file_browser(
                 Style::default().fg(theme.text),
             ),
             Span::styled(
-                format!("  ({match_count} matches) "),
+                format!("  ({match_count} matches)  Tab: Queue "),
                 Style::default().fg(theme.dimmed),
             ),
         ]);
@@ -189,7 +271,7 @@ pub fn draw_file_browser(
                 Block::default()
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
-                    .title(" Files ")
+                    .title(format!(" {mode_label} "))
                     .title_bottom(bottom_title),
             )
             .highlight_style(
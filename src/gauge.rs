@@ -16,6 +16,7 @@ pub struct RoundedGauge<'a> {
     dimmed_color: Color,
     block: Option<Block<'a>>,
     waveform: Option<&'a [f32]>,
+    markers: Vec<(f64, char, Color)>,
 }
 
 impl<'a> RoundedGauge<'a> {
@@ -29,6 +30,7 @@ impl<'a> RoundedGauge<'a> {
             dimmed_color: Color::DarkGray,
             block: None,
             waveform: None,
+            markers: Vec::new(),
         }
     }
 
@@ -48,6 +50,12 @@ impl<'a> RoundedGauge<'a> {
         self
     }
 
+    /// Draw `symbol` at `ratio` along the bar, on top of the fill.
+    pub fn marker(mut self, ratio: f64, symbol: char, color: Color) -> Self {
+        self.markers.push((ratio.clamp(0.0, 1.0), symbol, color));
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -137,6 +145,11 @@ impl Widget for RoundedGauge<'_> {
             }
         }
 
+        for (ratio, symbol, color) in &self.markers {
+            let col = ((ratio * width as f64) as usize).min(width - 1);
+            buf[(inner.x + col as u16, y)].set_char(*symbol).set_fg(*color);
+        }
+
         let label_len = self.label.len();
         if label_len <= width {
             let start = inner.x + (width - label_len) as u16 / 2;
@@ -0,0 +1,351 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use image::{
+    imageops::{self, ColorMap},
+    Rgb, RgbImage,
+};
+use ratatui::{buffer::Buffer, layout::Rect, Frame};
+
+use crate::config_dir;
+use crate::now_playing::AlbumArt;
+
+/// How album art reaches the screen.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// kitty graphics protocol (kitty, Ghostty, Konsole)
+    Kitty,
+    /// DEC sixel (foot, WezTerm, xterm -ti vt340, mlterm, Windows Terminal)
+    Sixel,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm2,
+    /// Unicode half blocks, two pixels per cell; works everywhere
+    HalfBlocks,
+}
+
+impl Protocol {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "kitty" => Some(Protocol::Kitty),
+            "sixel" => Some(Protocol::Sixel),
+            "iterm2" | "iterm" => Some(Protocol::Iterm2),
+            "blocks" | "halfblocks" | "off" => Some(Protocol::HalfBlocks),
+            _ => None,
+        }
+    }
+}
+
+/// Protocol forced by the `graphics` config file; anything else (or no file) means detect.
+fn load_graphics_setting() -> Option<Protocol> {
+    std::fs::read_to_string(config_dir().join("graphics"))
+        .ok()
+        .and_then(|s| Protocol::parse(&s))
+}
+
+/// Pick the best protocol the terminal supports. Must run in raw mode, before the
+/// event loop starts reading input, since it may query the terminal.
+pub fn detect() -> Protocol {
+    if let Some(protocol) = load_graphics_setting() {
+        return protocol;
+    }
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    // Multiplexers swallow or mangle graphics escapes unless specially configured
+    if env::var_os("TMUX").is_some() || var("TERM").starts_with("screen") {
+        return Protocol::HalfBlocks;
+    }
+    if var("TERM") == "xterm-kitty"
+        || env::var_os("KITTY_WINDOW_ID").is_some()
+        || var("TERM_PROGRAM") == "ghostty"
+    {
+        return Protocol::Kitty;
+    }
+    if var("TERM_PROGRAM") == "iTerm.app"
+        || var("LC_TERMINAL") == "iTerm2"
+        || var("TERM_PROGRAM") == "WezTerm"
+    {
+        return Protocol::Iterm2;
+    }
+    query_terminal().unwrap_or(Protocol::HalfBlocks)
+}
+
+/// Ask the terminal directly: a kitty graphics query answered with OK means kitty
+/// support, and attribute 4 in the Primary Device Attributes reply means sixel.
+/// Terminals ignore the kitty query if they don't know it, but all answer DA1.
+fn query_terminal() -> Option<Protocol> {
+    let mut out = io::stdout();
+    out.write_all(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c").ok()?;
+    out.flush().ok()?;
+
+    let tty = std::fs::File::open("/dev/tty").ok()?;
+    let fd = std::os::fd::AsRawFd::as_raw_fd(&tty);
+    let deadline = Instant::now() + Duration::from_millis(500);
+    let mut reply = Vec::new();
+    // Read until the DA1 reply (ESC [ ? … c) is complete
+    while !da1_complete(&reply) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return None;
+        }
+        let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut pfd, 1, left.as_millis() as i32) } <= 0 {
+            return None;
+        }
+        let mut buf = [0u8; 256];
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            return None;
+        }
+        reply.extend_from_slice(&buf[..n as usize]);
+    }
+
+    let text = String::from_utf8_lossy(&reply);
+    if text.contains("_Gi=31;OK") {
+        return Some(Protocol::Kitty);
+    }
+    let da1 = &text[text.rfind("\x1b[?")? + 3..];
+    let params = da1.trim_end_matches('c');
+    params
+        .split(';')
+        .any(|p| p == "4")
+        .then_some(Protocol::Sixel)
+}
+
+fn da1_complete(reply: &[u8]) -> bool {
+    reply
+        .windows(3)
+        .rposition(|w| w == b"\x1b[?")
+        .is_some_and(|start| reply[start..].contains(&b'c'))
+}
+
+// --- Layout ---
+
+/// Blank `rect` and mark it skipped, so ratatui never paints over the image there.
+pub fn reserve(frame: &mut Frame, rect: Rect) {
+    let buf = frame.buffer_mut();
+    for y in rect.top()..rect.bottom() {
+        for x in rect.left()..rect.right() {
+            buf[(x, y)].reset();
+            buf[(x, y)].set_skip(true);
+        }
+    }
+}
+
+/// True once a popup has drawn over part of a reserved rect (its `Clear` resets the
+/// skip flag); the image is then hidden so it can't cover the popup.
+pub fn covered(buf: &Buffer, rect: Rect) -> bool {
+    (rect.top()..rect.bottom())
+        .any(|y| (rect.left()..rect.right()).any(|x| !buf[(x, y)].skip))
+}
+
+// --- Output ---
+
+/// kitty image id; one image is shown at a time, so retransmitting replaces it.
+const KITTY_IMAGE_ID: u32 = 7041;
+
+/// Fallback cell size in pixels when the terminal doesn't report one.
+const DEFAULT_CELL_PX: (u32, u32) = (10, 20);
+
+/// Tracks the image on screen and redraws it when the art, its rect or the
+/// terminal size changes.
+pub struct Graphics {
+    pub protocol: Protocol,
+    /// (art id, cell rect, terminal size) currently on screen
+    shown: Option<(u32, Rect, Rect)>,
+}
+
+impl Graphics {
+    pub fn new(protocol: Protocol) -> Self {
+        Graphics { protocol, shown: None }
+    }
+
+    /// Whether album art should be reserved for this renderer instead of drawn as half blocks.
+    pub fn high_res(&self) -> bool {
+        self.protocol != Protocol::HalfBlocks
+    }
+
+    /// Make the screen match the last frame: `rect` is where `art` goes, None to hide it.
+    /// Returns true when the caller must clear the terminal, because sixel and iTerm2
+    /// images only go away when their cells are repainted; they are placed again
+    /// after the next draw.
+    pub fn sync(
+        &mut self,
+        art: Option<&AlbumArt>,
+        rect: Option<Rect>,
+        screen: Rect,
+    ) -> io::Result<bool> {
+        let want = art.zip(rect).map(|(a, r)| (a.id, r, screen));
+        if want == self.shown {
+            return Ok(false);
+        }
+        if self.shown.take().is_some() {
+            match self.protocol {
+                Protocol::Kitty => kitty_delete(&mut io::stdout())?,
+                Protocol::Sixel | Protocol::Iterm2 => return Ok(true),
+                Protocol::HalfBlocks => {}
+            }
+        }
+        if let (Some(art), Some(rect)) = (art, rect) {
+            let payload = match self.protocol {
+                Protocol::Kitty => kitty_image(&art.image, rect),
+                Protocol::Iterm2 => iterm2_image(&art.image, rect),
+                Protocol::Sixel => sixel_image(&art.image, rect),
+                Protocol::HalfBlocks => return Ok(false),
+            };
+            let mut out = io::stdout().lock();
+            // Save the cursor, jump to the rect, draw, restore
+            write!(out, "\x1b7\x1b[{};{}H", rect.y + 1, rect.x + 1)?;
+            out.write_all(payload.as_bytes())?;
+            out.write_all(b"\x1b8")?;
+            out.flush()?;
+            self.shown = want;
+        }
+        Ok(false)
+    }
+}
+
+fn kitty_delete(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\")?;
+    out.flush()
+}
+
+fn png_bytes(image: &RgbImage) -> Vec<u8> {
+    let mut png = io::Cursor::new(Vec::new());
+    let _ = image.write_to(&mut png, image::ImageFormat::Png);
+    png.into_inner()
+}
+
+/// PNG sent in 4 KiB base64 chunks, scaled by the terminal to fill `rect`.
+fn kitty_image(image: &RgbImage, rect: Rect) -> String {
+    let data = base64(&png_bytes(image));
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 16 + 64);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=100,i={KITTY_IMAGE_ID},c={},r={},C=1,q=2,m={more};{chunk}\x1b\\",
+                rect.width, rect.height
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    out
+}
+
+fn iterm2_image(image: &RgbImage, rect: Rect) -> String {
+    let png = png_bytes(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0;\
+         doNotMoveCursor=1:{}\x07",
+        png.len(),
+        rect.width,
+        rect.height,
+        base64(&png)
+    )
+}
+
+/// 6×6×6 color cube; small enough for every sixel terminal's palette.
+struct Cube;
+
+fn cube_level(v: u8) -> usize {
+    (v as usize * 5 + 127) / 255
+}
+
+impl ColorMap for Cube {
+    type Color = Rgb<u8>;
+
+    fn index_of(&self, c: &Rgb<u8>) -> usize {
+        cube_level(c[0]) * 36 + cube_level(c[1]) * 6 + cube_level(c[2])
+    }
+
+    fn map_color(&self, c: &mut Rgb<u8>) {
+        for v in c.0.iter_mut() {
+            *v = (cube_level(*v) * 51) as u8;
+        }
+    }
+}
+
+/// Pixel size of one terminal cell.
+fn cell_px() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(ws) if ws.width > 0 && ws.height > 0 && ws.columns > 0 && ws.rows > 0 => (
+            ws.width as u32 / ws.columns as u32,
+            ws.height as u32 / ws.rows as u32,
+        ),
+        _ => DEFAULT_CELL_PX,
+    }
+}
+
+/// Scale to the rect's pixel size, dither to the cube and encode as sixel bands.
+fn sixel_image(image: &RgbImage, rect: Rect) -> String {
+    let (cw, ch) = cell_px();
+    let (w, h) = (rect.width as u32 * cw, rect.height as u32 * ch);
+    let mut scaled = imageops::resize(image, w, h, imageops::FilterType::Triangle);
+    imageops::dither(&mut scaled, &Cube);
+    let indices = imageops::index_colors(&scaled, &Cube);
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{w};{h}");
+    for i in 0..216 {
+        let pct = |level: usize| level * 20;
+        out.push_str(&format!("#{i};2;{};{};{}", pct(i / 36), pct(i / 6 % 6), pct(i % 6)));
+    }
+    for band in (0..h).step_by(6) {
+        // Per color: one sixel (6 vertical bits) per column
+        let mut rows: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+        for dy in 0..6.min(h - band) {
+            for x in 0..w {
+                let color = indices.get_pixel(x, band + dy)[0];
+                rows.entry(color).or_insert_with(|| vec![0; w as usize])[x as usize] |= 1 << dy;
+            }
+        }
+        for (n, (color, bits)) in rows.iter().enumerate() {
+            if n > 0 {
+                out.push('$'); // back to the start of the band for the next color
+            }
+            out.push_str(&format!("#{color}"));
+            push_sixel_run(&mut out, bits);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Append one color's sixels with run-length encoding (`!count char`).
+fn push_sixel_run(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let run = bits[i..].iter().take_while(|&&b| b == bits[i]).count();
+        let c = (63 + bits[i]) as char;
+        if run > 3 {
+            out.push_str(&format!("!{run}{c}"));
+        } else {
+            (0..run).for_each(|_| out.push(c));
+        }
+        i += run;
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
@@ -0,0 +1,279 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Relative (`+5`, `-5`) or absolute (`30`) argument.
+#[derive(Clone, Copy)]
+pub enum Amount {
+    Absolute(f64),
+    Relative(f64),
+}
+
+pub enum IpcRequest {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    Next,
+    Prev,
+    /// Seconds
+    Seek(Amount),
+    /// Percent (100 = unity)
+    Volume(Amount),
+    /// Playback speed factor (1.0 = normal)
+    Speed(Amount),
+    Load(PathBuf),
+    Enqueue(PathBuf),
+    Status,
+    Quit,
+}
+
+/// A parsed command plus the channel its JSON reply goes back on.
+pub struct IpcMessage {
+    pub request: IpcRequest,
+    pub reply: mpsc::Sender<String>,
+}
+
+pub const HELP: &str = "\
+Commands:
+  play | pause | toggle | stop
+  next | prev
+  seek <secs|+secs|-secs>
+  volume <percent|+percent|-percent>
+  speed <factor|+step|-step>
+  load <file>
+  enqueue <file-or-directory>
+  status
+  quit";
+
+/// `$XDG_RUNTIME_DIR/tui-player.sock`, or a per-user socket in /tmp.
+pub fn socket_path() -> PathBuf {
+    match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("tui-player.sock"),
+        _ => {
+            let uid = unsafe { libc::getuid() };
+            PathBuf::from(format!("/tmp/tui-player-{uid}.sock"))
+        }
+    }
+}
+
+/// A number, `+n` or `-n`. `inf` and `nan` are refused: clamping keeps NaN, and
+/// huge values can't become a Duration.
+fn parse_amount(s: &str) -> Option<Amount> {
+    let s = s.trim();
+    let number = |v: &str| v.parse::<f64>().ok().filter(|n| n.is_finite());
+    if let Some(rest) = s.strip_prefix('+') {
+        number(rest).map(Amount::Relative)
+    } else if s.starts_with('-') {
+        number(s).map(Amount::Relative)
+    } else {
+        number(s).map(Amount::Absolute)
+    }
+}
+
+pub fn parse_command(line: &str) -> Result<IpcRequest, String> {
+    let line = line.trim();
+    let (cmd, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+    let need_arg = |what: &str| -> Result<&str, String> {
+        if arg.is_empty() {
+            Err(format!("{cmd}: missing {what}"))
+        } else {
+            Ok(arg)
+        }
+    };
+    match cmd.to_ascii_lowercase().as_str() {
+        "play" => Ok(IpcRequest::Play),
+        "pause" => Ok(IpcRequest::Pause),
+        "toggle" | "play-pause" => Ok(IpcRequest::Toggle),
+        "stop" => Ok(IpcRequest::Stop),
+        "next" => Ok(IpcRequest::Next),
+        "prev" | "previous" => Ok(IpcRequest::Prev),
+        "seek" => parse_amount(need_arg("seconds")?)
+            .map(IpcRequest::Seek)
+            .ok_or_else(|| format!("seek: invalid amount '{arg}'")),
+        "volume" | "vol" => parse_amount(need_arg("percent")?)
+            .map(IpcRequest::Volume)
+            .ok_or_else(|| format!("volume: invalid amount '{arg}'")),
+        "speed" => parse_amount(need_arg("factor")?)
+            .map(IpcRequest::Speed)
+            .ok_or_else(|| format!("speed: invalid amount '{arg}'")),
+        "load" => Ok(IpcRequest::Load(PathBuf::from(need_arg("path")?))),
+        "enqueue" => Ok(IpcRequest::Enqueue(PathBuf::from(need_arg("path")?))),
+        "status" => Ok(IpcRequest::Status),
+        "quit" => Ok(IpcRequest::Quit),
+        "" => Err("empty command".into()),
+        other => Err(format!("unknown command '{other}'")),
+    }
+}
+
+pub fn error_reply(msg: &str) -> String {
+    serde_json::json!({ "ok": false, "error": msg }).to_string()
+}
+
+fn handle_client(stream: UnixStream, tx: mpsc::Sender<IpcMessage>) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match parse_command(&line) {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                if tx.send(IpcMessage { request, reply: reply_tx }).is_err() {
+                    break;
+                }
+                reply_rx
+                    .recv_timeout(REPLY_TIMEOUT)
+                    .unwrap_or_else(|_| error_reply("player did not respond"))
+            }
+            Err(e) => error_reply(&e),
+        };
+        if writeln!(writer, "{reply}").is_err() {
+            break;
+        }
+    }
+}
+
+/// Listening control socket. The socket file is removed on drop.
+pub struct IpcServer {
+    path: PathBuf,
+    pub requests: mpsc::Receiver<IpcMessage>,
+}
+
+impl IpcServer {
+    /// Bind the control socket. Returns None if another instance already owns it
+    /// or the socket can't be created.
+    pub fn start() -> Option<Self> {
+        let path = socket_path();
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return None; // another player is listening
+            }
+            let _ = fs::remove_file(&path); // stale socket from a crashed run
+        }
+        let listener = UnixListener::bind(&path).ok()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || handle_client(stream, tx));
+            }
+        });
+        Some(IpcServer { path, requests: rx })
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// `tui-player ctl <command> [arg]` — send one command to the running player and
+/// print its JSON reply. Returns the process exit code.
+pub fn run_client(args: &[String]) -> i32 {
+    if args.is_empty() || args[0] == "help" || args[0] == "--help" {
+        eprintln!("Usage: tui-player ctl <command> [arg]\n\n{HELP}");
+        return if args.is_empty() { 2 } else { 0 };
+    }
+    let mut args = args.to_vec();
+    // Resolve paths against the caller's cwd, not the player's
+    if matches!(args[0].as_str(), "load" | "enqueue") && args.len() > 1 {
+        let joined = args[1..].join(" ");
+        args.truncate(1);
+        args.push(
+            fs::canonicalize(&joined)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or(joined),
+        );
+    }
+    let line = args.join(" ");
+    if let Err(e) = parse_command(&line) {
+        eprintln!("{e}\n\n{HELP}");
+        return 2;
+    }
+    let path = socket_path();
+    let result = (|| -> io::Result<String> {
+        let mut stream = UnixStream::connect(&path)?;
+        stream.set_read_timeout(Some(REPLY_TIMEOUT + Duration::from_secs(1)))?;
+        writeln!(stream, "{line}")?;
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        Ok(reply)
+    })();
+    match result {
+        Ok(reply) => {
+            println!("{}", reply.trim_end());
+            let ok = serde_json::from_str::<serde_json::Value>(&reply)
+                .ok()
+                .and_then(|v| v["ok"].as_bool())
+                .unwrap_or(false);
+            if ok { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("Could not reach tui-player at {}: {e}", path.display());
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts() {
+        assert!(matches!(parse_amount("30"), Some(Amount::Absolute(v)) if v == 30.0));
+        assert!(matches!(parse_amount(" +5 "), Some(Amount::Relative(v)) if v == 5.0));
+        assert!(matches!(parse_amount("-2.5"), Some(Amount::Relative(v)) if v == -2.5));
+        for bad in ["", "+", "abc", "inf", "-inf", "+NaN", "nan", "1e400"] {
+            assert!(parse_amount(bad).is_none(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn commands() {
+        assert!(matches!(parse_command("  PLAY "), Ok(IpcRequest::Play)));
+        assert!(matches!(parse_command("play-pause"), Ok(IpcRequest::Toggle)));
+        assert!(matches!(parse_command("previous"), Ok(IpcRequest::Prev)));
+        assert!(matches!(
+            parse_command("seek -10"),
+            Ok(IpcRequest::Seek(Amount::Relative(v))) if v == -10.0
+        ));
+        assert!(matches!(
+            parse_command("vol\t80"),
+            Ok(IpcRequest::Volume(Amount::Absolute(v))) if v == 80.0
+        ));
+        match parse_command("load  /music/a b.flac ") {
+            Ok(IpcRequest::Load(path)) => assert_eq!(path, PathBuf::from("/music/a b.flac")),
+            _ => panic!("load not parsed"),
+        }
+    }
+
+    #[test]
+    fn command_errors() {
+        let err = |line| parse_command(line).err().unwrap_or_default();
+        assert_eq!(err(""), "empty command");
+        assert_eq!(err("seek"), "seek: missing seconds");
+        assert_eq!(err("seek nan"), "seek: invalid amount 'nan'");
+        assert_eq!(err("speed inf"), "speed: invalid amount 'inf'");
+        assert_eq!(err("enqueue"), "enqueue: missing path");
+        assert_eq!(err("rewind 5"), "unknown command 'rewind'");
+    }
+}
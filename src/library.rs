@@ -0,0 +1,430 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+use tui_tree_widget::TreeItem;
+
+use crate::{config_dir, file_browser, probe_file, ProbeInfo, TrackMeta};
+
+/// Bumped whenever entries gain fields, so an older index is re-probed instead of
+/// silently missing them.
+const LIBRARY_VERSION: u64 = 3;
+
+/// Flush the index to disk after this many newly probed files during a scan, so
+/// an interrupted first scan of a large collection isn't lost.
+const SAVE_EVERY: usize = 500;
+
+/// Everything known about one audio file, valid while its mtime and size match.
+#[derive(Clone)]
+pub struct LibraryEntry {
+    pub mtime_ms: u64,
+    pub size: u64,
+    pub duration: Option<Duration>,
+    pub meta: TrackMeta,
+    pub rg_track_db: Option<f32>,
+    pub rg_album_db: Option<f32>,
+    pub rg_track_peak: Option<f32>,
+    pub rg_album_peak: Option<f32>,
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+impl LibraryEntry {
+    fn from_probe(probe: &ProbeInfo, stamp: (u64, u64)) -> Self {
+        LibraryEntry {
+            mtime_ms: stamp.0,
+            size: stamp.1,
+            duration: probe.duration,
+            meta: probe.meta.clone(),
+            rg_track_db: probe.rg_track_db,
+            rg_album_db: probe.rg_album_db,
+            rg_track_peak: probe.rg_track_peak,
+            rg_album_peak: probe.rg_album_peak,
+            codec: probe.codec.clone(),
+            sample_rate: probe.sample_rate,
+            channels: probe.channels,
+        }
+    }
+
+    fn to_probe(&self) -> ProbeInfo {
+        ProbeInfo {
+            duration: self.duration,
+            meta: self.meta.clone(),
+            rg_track_db: self.rg_track_db,
+            rg_album_db: self.rg_album_db,
+            rg_track_peak: self.rg_track_peak,
+            rg_album_peak: self.rg_album_peak,
+            codec: self.codec.clone(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    fn to_json(&self, path: &str) -> Value {
+        json!({
+            "path": path,
+            "mtime": self.mtime_ms,
+            "size": self.size,
+            "duration": self.duration.map(|d| d.as_secs_f64()),
+            "title": self.meta.title,
+            "artist": self.meta.artist,
+            "album": self.meta.album,
+            "date": self.meta.date,
+            "genre": self.meta.genre,
+            "album_artist": self.meta.album_artist,
+            "track": self.meta.track_number,
+            "disc": self.meta.disc_number,
+            "rg_track": self.rg_track_db,
+            "rg_album": self.rg_album_db,
+            "rg_track_peak": self.rg_track_peak,
+            "rg_album_peak": self.rg_album_peak,
+            "codec": self.codec,
+            "sample_rate": self.sample_rate,
+            "channels": self.channels,
+        })
+    }
+
+    fn from_json(v: &Value) -> Option<(PathBuf, Self)> {
+        let text = |key: &str| v[key].as_str().map(str::to_string);
+        let entry = LibraryEntry {
+            mtime_ms: v["mtime"].as_u64()?,
+            size: v["size"].as_u64()?,
+            duration: v["duration"].as_f64().and_then(|d| Duration::try_from_secs_f64(d).ok()),
+            meta: TrackMeta {
+                title: text("title"),
+                artist: text("artist"),
+                album: text("album"),
+                date: text("date"),
+                genre: text("genre"),
+                album_artist: text("album_artist"),
+                track_number: v["track"].as_u64().map(|n| n as u32),
+                disc_number: v["disc"].as_u64().map(|n| n as u32),
+            },
+            rg_track_db: v["rg_track"].as_f64().map(|g| g as f32),
+            rg_album_db: v["rg_album"].as_f64().map(|g| g as f32),
+            rg_track_peak: v["rg_track_peak"].as_f64().map(|p| p as f32),
+            rg_album_peak: v["rg_album_peak"].as_f64().map(|p| p as f32),
+            codec: text("codec"),
+            sample_rate: v["sample_rate"].as_u64().map(|r| r as u32),
+            channels: v["channels"].as_u64().map(|c| c as u16),
+        };
+        Some((PathBuf::from(v["path"].as_str()?), entry))
+    }
+}
+
+/// (mtime in ms, size) used to tell whether an indexed file changed.
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let md = fs::metadata(path).ok()?;
+    let mtime = md.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((mtime.as_millis() as u64, md.len()))
+}
+
+/// On-disk tag index at `~/.config/tui-player/library`: a version header, then one
+/// JSON object per file.
+pub struct Library {
+    entries: HashMap<PathBuf, LibraryEntry>,
+    dirty: bool,
+}
+
+pub type SharedLibrary = Arc<Mutex<Library>>;
+
+fn library_path() -> PathBuf {
+    config_dir().join("library")
+}
+
+impl Library {
+    pub fn load() -> Self {
+        let mut entries = HashMap::new();
+        if let Ok(file) = fs::File::open(library_path()) {
+            let mut lines = BufReader::new(file).lines().map_while(Result::ok);
+            let version = lines
+                .next()
+                .and_then(|l| serde_json::from_str::<Value>(&l).ok())
+                .and_then(|v| v["version"].as_u64());
+            if version != Some(LIBRARY_VERSION) {
+                return Library { entries, dirty: false };
+            }
+            for line in lines {
+                let parsed = serde_json::from_str::<Value>(&line)
+                    .ok()
+                    .and_then(|v| LibraryEntry::from_json(&v));
+                if let Some((path, entry)) = parsed {
+                    entries.insert(path, entry);
+                }
+            }
+        }
+        Library { entries, dirty: false }
+    }
+
+    pub fn load_shared() -> SharedLibrary {
+        Arc::new(Mutex::new(Self::load()))
+    }
+
+    /// Copy of the entries if anything changed since the last save, marking them saved.
+    /// Lets a shared library be written without holding its lock.
+    fn take_snapshot(&mut self) -> Option<HashMap<PathBuf, LibraryEntry>> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        Some(self.entries.clone())
+    }
+
+    /// Indexed entry for `path` if the file hasn't changed since it was probed.
+    fn fresh(&self, path: &Path, stamp: (u64, u64)) -> Option<&LibraryEntry> {
+        self.entries
+            .get(path)
+            .filter(|e| (e.mtime_ms, e.size) == stamp)
+    }
+
+    /// Indexed entry for `path`, without checking whether the file changed since.
+    pub fn get(&self, path: &Path) -> Option<&LibraryEntry> {
+        self.entries.get(path)
+    }
+
+    fn insert(&mut self, path: PathBuf, entry: LibraryEntry) {
+        self.entries.insert(path, entry);
+        self.dirty = true;
+    }
+
+    /// Indexed audio files below `root`.
+    fn files_under(&self, root: &Path) -> Vec<PathBuf> {
+        self.entries
+            .keys()
+            .filter(|p| p.starts_with(root))
+            .cloned()
+            .collect()
+    }
+
+    /// Forget a deleted file, or everything below a deleted directory.
+    pub fn remove_under(&mut self, path: &Path) {
+        self.prune_under(path, &HashSet::new());
+    }
+
+    /// Follow a rename of a file or directory. Renaming keeps the mtime, so the
+    /// entries stay valid under their new paths.
+    pub fn rename_under(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|p| p.starts_with(from))
+            .cloned()
+            .collect();
+        for old in moved {
+            if let Some(entry) = self.entries.remove(&old) {
+                self.entries.insert(file_browser::renamed(&old, from, to), entry);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Drop entries below `root` that a scan no longer found.
+    fn prune_under(&mut self, root: &Path, present: &HashSet<PathBuf>) {
+        let before = self.entries.len();
+        self.entries
+            .retain(|p, _| !p.starts_with(root) || present.contains(p));
+        self.dirty |= self.entries.len() != before;
+    }
+}
+
+/// Tags and stream info for `path`, from the index when the file is unchanged,
+/// otherwise probed and recorded.
+pub fn probe(library: &SharedLibrary, path: &Path) -> ProbeInfo {
+    let Some(stamp) = file_stamp(path) else {
+        return probe_file(path);
+    };
+    if let Some(entry) = library.lock().unwrap().fresh(path, stamp) {
+        return entry.to_probe();
+    }
+    let probe = probe_file(path);
+    library
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), LibraryEntry::from_probe(&probe, stamp));
+    probe
+}
+
+/// Browser tree for `root`: built straight from the index when it already knows
+/// files there, otherwise by walking the directory.
+pub fn browser_tree(library: &SharedLibrary, root: &Path) -> Vec<TreeItem<'static, PathBuf>> {
+    let files = library.lock().unwrap().files_under(root);
+    if files.is_empty() {
+        file_browser::scan_directory(root)
+    } else {
+        file_browser::build_tree(root, &files)
+    }
+}
+
+/// Collect every audio file below `dir`.
+pub fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            walk(&path, out);
+        } else if file_browser::is_audio_file(&path) {
+            out.push(path);
+        }
+    }
+}
+
+/// Bring the index up to date for everything below `root` on a background thread.
+/// The full file list is sent as soon as the directory walk finishes; new or
+/// modified files (by mtime and size) are then probed, and vanished ones dropped.
+pub fn spawn_update(library: SharedLibrary, root: PathBuf) -> mpsc::Receiver<Vec<PathBuf>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut files = Vec::new();
+        walk(&root, &mut files);
+        let _ = tx.send(files.clone());
+
+        let present: HashSet<PathBuf> = files.iter().cloned().collect();
+        library.lock().unwrap().prune_under(&root, &present);
+        index_files(&library, files);
+    });
+    rx
+}
+
+/// Write the index if anything changed. The entries are cloned under the library
+/// lock and serialized outside it, so lookups from the UI thread don't wait on the
+/// disk.
+pub fn save_shared(library: &SharedLibrary) {
+    // Held from snapshot to rename, so concurrent saves reach the disk in the order
+    // their snapshots were taken and an older one never replaces a newer one
+    static SAVING: Mutex<()> = Mutex::new(());
+    let _saving = SAVING.lock().unwrap();
+    let Some(entries) = library.lock().unwrap().take_snapshot() else {
+        return;
+    };
+    if write_index(&entries).is_err() {
+        library.lock().unwrap().dirty = true;
+    }
+}
+
+/// Write `entries` to a temp file and rename it over the index, so a crash
+/// mid-write can't truncate it.
+fn write_index(entries: &HashMap<PathBuf, LibraryEntry>) -> std::io::Result<()> {
+    let dir = config_dir();
+    let _ = fs::create_dir_all(&dir);
+    let tmp = dir.join("library.tmp");
+    let mut out = std::io::BufWriter::new(fs::File::create(&tmp)?);
+    writeln!(out, "{}", json!({ "version": LIBRARY_VERSION }))?;
+    for (path, entry) in entries {
+        // Non-UTF-8 paths can't round-trip through JSON; they're just re-probed
+        if let Some(p) = path.to_str() {
+            writeln!(out, "{}", entry.to_json(p))?;
+        }
+    }
+    out.flush()?;
+    fs::rename(&tmp, library_path())
+}
+
+/// Probe whichever of `files` are new or changed since they were indexed, then save.
+fn index_files(library: &SharedLibrary, files: Vec<PathBuf>) {
+    let mut probed = 0usize;
+    for path in files {
+        let Some(stamp) = file_stamp(&path) else {
+            continue;
+        };
+        if library.lock().unwrap().fresh(&path, stamp).is_some() {
+            continue;
+        }
+        // Probe without holding the lock so playback never waits on a scan
+        let entry = LibraryEntry::from_probe(&probe_file(&path), stamp);
+        library.lock().unwrap().insert(path, entry);
+        probed += 1;
+        if probed.is_multiple_of(SAVE_EVERY) {
+            save_shared(library);
+        }
+    }
+    save_shared(library);
+}
+
+/// Index files that appeared or changed while running, on a background thread.
+/// The returned channel disconnects once they are all in the index.
+pub fn spawn_index(library: SharedLibrary, files: Vec<PathBuf>) -> mpsc::Receiver<()> {
+    let (tx, rx) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        index_files(&library, files);
+        drop(tx);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> LibraryEntry {
+        LibraryEntry {
+            mtime_ms: 1_700_000_000_123,
+            size: 4_567_890,
+            duration: Some(Duration::from_millis(215_500)),
+            meta: TrackMeta {
+                title: Some("Song".into()),
+                artist: Some("Artist".into()),
+                album: Some("Album".into()),
+                track_number: Some(3),
+                ..Default::default()
+            },
+            rg_track_db: Some(-6.5),
+            rg_album_db: None,
+            rg_track_peak: Some(0.98),
+            rg_album_peak: None,
+            codec: Some("flac".into()),
+            sample_rate: Some(44_100),
+            channels: Some(2),
+        }
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let json = entry().to_json("/music/a.flac");
+        let (path, back) = LibraryEntry::from_json(&json).unwrap();
+        assert_eq!(path, Path::new("/music/a.flac"));
+        assert_eq!((back.mtime_ms, back.size), (1_700_000_000_123, 4_567_890));
+        assert_eq!(back.duration, Some(Duration::from_millis(215_500)));
+        assert_eq!(back.meta.title.as_deref(), Some("Song"));
+        assert_eq!(back.meta.track_number, Some(3));
+        assert_eq!(back.meta.genre, None);
+        assert_eq!((back.rg_track_db, back.rg_album_db), (Some(-6.5), None));
+        assert_eq!(back.rg_track_peak, Some(0.98));
+        assert_eq!(back.codec.as_deref(), Some("flac"));
+        assert_eq!((back.sample_rate, back.channels), (Some(44_100), Some(2)));
+    }
+
+    #[test]
+    fn corrupt_entries() {
+        let mut json = entry().to_json("/music/a.flac");
+        for bad in [json!(-1.0), json!(1e300), json!("long")] {
+            json["duration"] = bad;
+            let (_, back) = LibraryEntry::from_json(&json).unwrap();
+            assert_eq!(back.duration, None);
+        }
+        // Entries without a stamp or path can't be trusted at all
+        for key in ["mtime", "size", "path"] {
+            let mut json = entry().to_json("/music/a.flac");
+            json[key] = Value::Null;
+            assert!(LibraryEntry::from_json(&json).is_none(), "{key}");
+        }
+    }
+
+    #[test]
+    fn snapshot_only_when_changed() {
+        let mut lib = Library { entries: HashMap::new(), dirty: false };
+        assert!(lib.take_snapshot().is_none());
+        lib.insert(PathBuf::from("/music/a.flac"), entry());
+        assert_eq!(lib.take_snapshot().map(|e| e.len()), Some(1));
+        assert!(lib.take_snapshot().is_none());
+    }
+}
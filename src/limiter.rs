@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+/// Output ceiling: -1 dBTP leaves room for lossy encoders and DACs that overshoot.
+const CEILING: f32 = 0.891;
+const LOOKAHEAD_SECS: f32 = 0.0015;
+const RELEASE_SECS: f32 = 0.1;
+
+/// Inter-sample peaks are estimated at these fractions between two samples.
+const PHASES: [f32; 3] = [0.25, 0.5, 0.75];
+/// Interpolation taps: 4 samples either side of the gap being estimated.
+const TAPS: usize = 8;
+
+/// Lanczos kernel (a = 4).
+fn lanczos(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        return 1.0;
+    }
+    if x.abs() >= 4.0 {
+        return 0.0;
+    }
+    let px = std::f32::consts::PI * x;
+    4.0 * px.sin() * (px / 4.0).sin() / (px * px)
+}
+
+/// Look-ahead peak limiter working on interleaved frames, with all channels
+/// sharing one gain. Peaks are measured between samples as well (4× oversampled)
+/// so the reconstructed waveform stays under the ceiling, not just the samples.
+/// The gain ramps down over the look-ahead window ahead of each peak and
+/// recovers over `RELEASE_SECS`, so the audio is delayed by about 1.6 ms.
+pub struct Limiter {
+    channels: usize,
+    lookahead: usize,
+    /// Interpolation coefficients per phase
+    kernel: [[f32; TAPS]; PHASES.len()],
+    /// Last `TAPS` samples per channel, oldest first
+    history: Vec<[f32; TAPS]>,
+    /// Incoming frame being assembled
+    frame: Vec<f32>,
+    /// Delayed audio, interleaved
+    delay: VecDeque<f32>,
+    /// Frames in `delay` that came from the source (the rest is flush padding)
+    real_frames: usize,
+    /// (frame number, gain) candidates for the sliding minimum, increasing gain
+    min_window: VecDeque<(u64, f32)>,
+    /// Sliding minimums being averaged into a smooth attack
+    ramp: VecDeque<f32>,
+    ramp_sum: f32,
+    gain: f32,
+    release: f32,
+    frames_in: u64,
+    out: VecDeque<f32>,
+    /// Switched off, the gain recovers to unity but the delay stays
+    enabled: bool,
+    /// `CEILING`, lowered when the output is amplified after the limiter
+    ceiling: f32,
+}
+
+impl Limiter {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        let lookahead = ((LOOKAHEAD_SECS * sample_rate as f32).round() as usize).max(1);
+        let mut kernel = [[0.0; TAPS]; PHASES.len()];
+        for (taps, frac) in kernel.iter_mut().zip(PHASES) {
+            // Estimating between history[3] and history[4]
+            for (k, tap) in taps.iter_mut().enumerate() {
+                *tap = lanczos(k as f32 - 3.0 - frac);
+            }
+        }
+        Limiter {
+            channels,
+            lookahead,
+            kernel,
+            history: vec![[0.0; TAPS]; channels],
+            frame: Vec::with_capacity(channels),
+            delay: VecDeque::new(),
+            real_frames: 0,
+            min_window: VecDeque::new(),
+            ramp: std::iter::repeat_n(1.0, lookahead).collect(),
+            ramp_sum: lookahead as f32,
+            gain: 1.0,
+            release: 1.0 - (-1.0 / (RELEASE_SECS * sample_rate as f32)).exp(),
+            frames_in: 0,
+            out: VecDeque::new(),
+            enabled: true,
+            ceiling: CEILING,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Gain applied after the limiter (the sink volume). Above unity the ceiling
+    /// drops by as much, so the amplified output still peaks at `CEILING`.
+    pub fn set_output_gain(&mut self, gain: f32) {
+        self.ceiling = CEILING / gain.max(1.0);
+    }
+
+    /// Feed one interleaved sample.
+    pub fn push(&mut self, sample: f32) {
+        self.frame.push(sample);
+        if self.frame.len() == self.channels {
+            let frame = std::mem::take(&mut self.frame);
+            self.process_frame(&frame, true);
+            self.frame = frame;
+            self.frame.clear();
+        }
+    }
+
+    /// Next limited sample, once enough audio has been pushed.
+    pub fn pop(&mut self) -> Option<f32> {
+        self.out.pop_front()
+    }
+
+    /// The source ended: push the delayed audio out. Returns false once
+    /// nothing is left.
+    pub fn flush(&mut self) -> bool {
+        // A partial last frame is padded rather than dropped
+        if !self.frame.is_empty() {
+            self.frame.resize(self.channels, 0.0);
+            let frame = std::mem::take(&mut self.frame);
+            self.process_frame(&frame, true);
+        }
+        let silence = vec![0.0; self.channels];
+        while self.out.is_empty() && self.real_frames > 0 {
+            self.process_frame(&silence, false);
+        }
+        !self.out.is_empty()
+    }
+
+    fn process_frame(&mut self, frame: &[f32], real: bool) {
+        // Peak of the frame that just left the middle of the interpolation window
+        let mut peak = 0f32;
+        for (history, &x) in self.history.iter_mut().zip(frame) {
+            history.rotate_left(1);
+            history[TAPS - 1] = x;
+            peak = peak.max(history[3].abs()).max(history[4].abs());
+            for taps in &self.kernel {
+                let y: f32 = taps.iter().zip(history.iter()).map(|(c, s)| c * s).sum();
+                peak = peak.max(y.abs());
+            }
+        }
+        let needed = if self.enabled && peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+
+        // Sliding minimum of the needed gain over the look-ahead window
+        let n = self.frames_in;
+        self.frames_in += 1;
+        while self.min_window.back().is_some_and(|&(_, g)| g >= needed) {
+            self.min_window.pop_back();
+        }
+        self.min_window.push_back((n, needed));
+        while self.min_window.front().is_some_and(|&(i, _)| i + (self.lookahead as u64) <= n) {
+            self.min_window.pop_front();
+        }
+        let window_min = self.min_window.front().map_or(1.0, |&(_, g)| g);
+
+        // Averaging the minimums ramps the gain down across the window, reaching
+        // the peak's own requirement exactly as it plays
+        self.ramp_sum += window_min - self.ramp.pop_front().unwrap_or(1.0);
+        self.ramp.push_back(window_min);
+        if n.is_multiple_of(1 << 16) {
+            // Re-add from scratch now and then so rounding errors can't pile up
+            self.ramp_sum = self.ramp.iter().sum();
+        }
+        let target = self.ramp_sum / self.lookahead as f32;
+        self.gain = if target < self.gain {
+            target
+        } else {
+            self.gain + (target - self.gain) * self.release
+        };
+
+        self.delay.extend(frame);
+        if real {
+            self.real_frames += 1;
+        }
+        // The detected frame sits 4 frames back in `history`, so delay 3 more
+        let delay_frames = self.lookahead + 3;
+        if self.delay.len() / self.channels > delay_frames {
+            let gain = self.gain;
+            for _ in 0..self.channels {
+                let x = self.delay.pop_front().unwrap_or(0.0);
+                if self.real_frames > 0 {
+                    self.out.push_back(x * gain);
+                }
+            }
+            self.real_frames = self.real_frames.saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    f64::consts::PI,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use biquad::{Biquad, Coefficients, DirectForm2Transposed};
+use rodio::{Decoder, Source};
+use serde_json::{json, Value};
+
+use crate::cache;
+
+/// ReplayGain 2.0 reference level: a track measuring this plays at 0 dB gain.
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+/// Gating blocks are 400 ms long and start every 100 ms.
+const BLOCK_STEPS: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Integrated loudness and sample peak of one file.
+#[derive(Clone, Copy)]
+pub struct Loudness {
+    pub lufs: f64,
+    pub peak: f32,
+}
+
+impl Loudness {
+    /// Gain that brings the file to the reference level, in dB.
+    pub fn gain_db(&self) -> f32 {
+        (REFERENCE_LUFS - self.lufs) as f32
+    }
+}
+
+/// Mean-square power of every gating block, kept so the blocks of a whole album
+/// can be gated together.
+pub struct Analysis {
+    pub blocks: Vec<f64>,
+    pub peak: f32,
+}
+
+impl Analysis {
+    /// None for files shorter than one block, or silent throughout.
+    pub fn loudness(&self) -> Option<Loudness> {
+        Some(Loudness { lufs: integrated(&self.blocks)?, peak: self.peak })
+    }
+}
+
+fn block_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Gated integrated loudness (ITU-R BS.1770-4) of `blocks`, in LUFS.
+pub fn integrated(blocks: &[f64]) -> Option<f64> {
+    let mean_above = |gate: f64| {
+        let (sum, count) = blocks
+            .iter()
+            .filter(|&&p| block_lufs(p) > gate)
+            .fold((0.0, 0), |(sum, count), p| (sum + p, count + 1));
+        (count > 0).then(|| sum / count as f64)
+    };
+    let relative = block_lufs(mean_above(ABSOLUTE_GATE)?) + RELATIVE_GATE;
+    Some(block_lufs(mean_above(relative.max(ABSOLUTE_GATE))?))
+}
+
+/// K-weighting: the BS.1770 shelf and high-pass, derived for any sample rate the
+/// way libebur128 does rather than using the 48 kHz table.
+fn k_weighting(sample_rate: f64) -> [Coefficients<f64>; 2] {
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Coefficients {
+        b0: (vh + vb * k / q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Coefficients {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+    [shelf, high_pass]
+}
+
+/// 5.0 and 5.1 layouts: the surrounds count 1.41×, the LFE not at all.
+fn channel_weight(channel: usize, channels: usize) -> f64 {
+    match (channels, channel) {
+        (6, 3) => 0.0,
+        (5, 3 | 4) | (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+/// Decode `path` and measure it. None when it can't be opened or decoded.
+pub fn analyze(path: &Path) -> Option<Analysis> {
+    let file = fs::File::open(path).ok()?;
+    let source = Decoder::new(io::BufReader::new(file)).ok()?;
+    let channels = source.channels() as usize;
+    let sample_rate = source.sample_rate();
+    let step_frames = (sample_rate as usize / 10).max(1);
+    let weights: Vec<f64> = (0..channels).map(|c| channel_weight(c, channels)).collect();
+    let stages = k_weighting(sample_rate as f64);
+    let mut filters: Vec<[DirectForm2Transposed<f64>; 2]> = (0..channels)
+        .map(|_| stages.map(DirectForm2Transposed::<f64>::new))
+        .collect();
+
+    let mut blocks = Vec::new();
+    let mut steps: VecDeque<f64> = VecDeque::with_capacity(BLOCK_STEPS);
+    let mut step_energy = 0.0;
+    let mut peak: f32 = 0.0;
+    let mut frame_count = 0;
+    let mut ch_count = 0;
+
+    for sample in source {
+        peak = peak.max(sample.abs());
+        let [shelf, high_pass] = &mut filters[ch_count];
+        let y = high_pass.run(shelf.run(sample as f64));
+        step_energy += weights[ch_count] * y * y;
+        ch_count += 1;
+        if ch_count >= channels {
+            ch_count = 0;
+            frame_count += 1;
+            if frame_count >= step_frames {
+                if steps.len() == BLOCK_STEPS {
+                    steps.pop_front();
+                }
+                steps.push_back(step_energy);
+                step_energy = 0.0;
+                frame_count = 0;
+                if steps.len() == BLOCK_STEPS {
+                    let energy: f64 = steps.iter().sum();
+                    blocks.push(energy / (BLOCK_STEPS * step_frames) as f64);
+                }
+            }
+        }
+    }
+    Some(Analysis { blocks, peak })
+}
+
+/// Measurements are only valid for the file as it was: the key changes with its
+/// mtime and size.
+fn cache_key(path: &Path) -> Option<String> {
+    let md = fs::metadata(path).ok()?;
+    let mtime = md.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis();
+    Some(format!("{}\0{mtime}\0{}", path.display(), md.len()))
+}
+
+/// The measurement of `path`, if it was scanned since it last changed.
+pub fn cached(path: &Path) -> Option<Loudness> {
+    let data = cache::get(cache::Kind::Loudness, &cache_key(path)?)?;
+    let v: Value = serde_json::from_slice(&data).ok()?;
+    Some(Loudness {
+        lufs: v["lufs"].as_f64()?,
+        peak: v["peak"].as_f64()? as f32,
+    })
+}
+
+/// Remember a measurement, or that `path` couldn't be measured.
+pub fn store(path: &Path, loudness: Option<Loudness>) {
+    let Some(key) = cache_key(path) else {
+        return;
+    };
+    match loudness {
+        Some(l) => {
+            let data = json!({ "lufs": l.lufs, "peak": l.peak }).to_string();
+            cache::put(cache::Kind::Loudness, &key, data.as_bytes());
+        }
+        None => cache::put_negative(cache::Kind::Loudness, &key),
+    }
+}
+
+/// Background worker measuring one file at a time. Results land in the cache; the
+/// path is sent on `done` once it's there.
+pub struct Scanner {
+    jobs: mpsc::Sender<PathBuf>,
+    pub done: mpsc::Receiver<PathBuf>,
+    /// Everything asked for this session, so a file is never queued twice
+    requested: HashSet<PathBuf>,
+}
+
+impl Scanner {
+    pub fn start() -> Self {
+        let (jobs, job_rx) = mpsc::channel::<PathBuf>();
+        let (done_tx, done) = mpsc::channel();
+        thread::spawn(move || {
+            for path in job_rx {
+                let failed_before = cache_key(&path)
+                    .is_some_and(|key| cache::is_negative(cache::Kind::Loudness, &key));
+                if !failed_before && cached(&path).is_none() {
+                    store(&path, analyze(&path).and_then(|a| a.loudness()));
+                }
+                if done_tx.send(path).is_err() {
+                    break;
+                }
+            }
+        });
+        Scanner { jobs, done, requested: HashSet::new() }
+    }
+
+    /// Queue `path` for measuring unless it was already asked for.
+    pub fn request(&mut self, path: &Path) {
+        if self.requested.insert(path.to_path_buf()) {
+            let _ = self.jobs.send(path.to_path_buf());
+        }
+    }
+}
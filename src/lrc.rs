@@ -0,0 +1,343 @@
+use std::{fs, path::Path, time::Duration};
+
+/// One timed lyric line. `words` holds enhanced-LRC word timings as (time, byte
+/// offset into `text` where the word starts); empty for plain line timing.
+pub struct SyncedLine {
+    pub at: Duration,
+    pub text: String,
+    pub words: Vec<(Duration, usize)>,
+}
+
+/// Parse `[mm:ss]`, `[mm:ss.xx]`, `[mm:ss.xxx]` or `[mm:ss:xx]`.
+fn parse_timestamp(s: &str) -> Option<Duration> {
+    let (min, rest) = s.split_once(':')?;
+    let min: u64 = min.trim().parse().ok()?;
+    let (sec, frac) = match rest.split_once(['.', ':']) {
+        Some((sec, frac)) => (sec, frac),
+        None => (rest, ""),
+    };
+    let sec: u64 = sec.trim().parse().ok()?;
+    if sec >= 60 || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // ".5" is half a second, ".05" and ".050" are 50 ms; digits past the
+    // millisecond are dropped
+    let digits = &frac[..frac.len().min(3)];
+    let frac_ms = match digits.len() {
+        0 => 0,
+        n => digits.parse::<u64>().ok()? * 10u64.pow(3 - n as u32),
+    };
+    let ms = min.checked_mul(60)?.checked_add(sec)?.checked_mul(1000)?.checked_add(frac_ms)?;
+    Some(Duration::from_millis(ms))
+}
+
+/// Split off enhanced-LRC `<mm:ss.xx>` word stamps, returning the clean, trimmed
+/// text. Word offsets are byte offsets into that text.
+fn parse_words(raw: &str) -> (String, Vec<(Duration, usize)>) {
+    let mut text = String::new();
+    let mut words = Vec::new();
+    let mut rest = raw;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>').map(|c| open + c) else {
+            break;
+        };
+        text.push_str(&rest[..open]);
+        match parse_timestamp(&rest[open + 1..close]) {
+            Some(at) => words.push((at, text.len())),
+            None => text.push_str(&rest[open..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    // Offsets were taken before trimming: trimming removes whole characters, so
+    // shifting and clamping them keeps them on character boundaries
+    let lead = text.len() - text.trim_start().len();
+    let text = text.trim().to_string();
+    for word in &mut words {
+        word.1 = word.1.saturating_sub(lead).min(text.len());
+    }
+    (text, words)
+}
+
+/// Parse LRC text. Lines may carry several timestamps; `[offset:±ms]` shifts all of
+/// them and other `[tag:…]` headers are ignored. None when nothing is timed, so
+/// plain lyrics that happen to be passed in stay unsynced.
+pub fn parse_lrc(content: &str) -> Option<Vec<SyncedLine>> {
+    let mut offset_ms: i64 = 0;
+    let mut lines = Vec::new();
+    for raw in content.lines() {
+        let mut rest = raw.trim();
+        let mut stamps = Vec::new();
+        while let Some(inner) = rest.strip_prefix('[') {
+            let Some(close) = inner.find(']') else {
+                break;
+            };
+            let tag = &inner[..close];
+            match parse_timestamp(tag) {
+                Some(at) => stamps.push(at),
+                None => {
+                    if let Some(ms) = tag.strip_prefix("offset:") {
+                        offset_ms = ms.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            rest = inner[close + 1..].trim_start();
+        }
+        if stamps.is_empty() {
+            continue;
+        }
+        let (text, words) = parse_words(rest);
+        // Word stamps are written for the first occurrence; repeats move with their line
+        let first = stamps[0];
+        for at in stamps {
+            let words = words
+                .iter()
+                .map(|&(w, pos)| ((w + at).saturating_sub(first), pos))
+                .collect();
+            lines.push(SyncedLine { at, text: text.clone(), words });
+        }
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    // A positive offset means the lyrics come sooner
+    let shift = |d: Duration| {
+        let ms = d.as_millis() as i128 - offset_ms as i128;
+        Duration::from_millis(ms.clamp(0, u64::MAX as i128) as u64)
+    };
+    for line in &mut lines {
+        line.at = shift(line.at);
+        for word in &mut line.words {
+            word.0 = shift(word.0);
+        }
+    }
+    lines.sort_by_key(|l| l.at);
+    Some(lines)
+}
+
+fn format_timestamp(d: Duration) -> String {
+    let cs = d.as_millis() / 10;
+    format!("{:02}:{:02}.{:02}", cs / 6000, cs / 100 % 60, cs % 100)
+}
+
+/// Serialize back to (enhanced) LRC, for the lyrics cache.
+pub fn to_lrc(lines: &[SyncedLine]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&format!("[{}]", format_timestamp(line.at)));
+        let mut last = 0;
+        for &(at, pos) in &line.words {
+            out.push_str(&line.text[last..pos]);
+            out.push_str(&format!("<{}>", format_timestamp(at)));
+            last = pos;
+        }
+        out.push_str(&line.text[last..]);
+        out.push('\n');
+    }
+    out
+}
+
+/// Plain text of synced lyrics, one line per timed line.
+pub fn plain_text(lines: &[SyncedLine]) -> String {
+    lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n")
+}
+
+/// Index of the line being sung at `pos`, if the first line has started.
+pub fn current_line(lines: &[SyncedLine], pos: Duration) -> Option<usize> {
+    lines.partition_point(|l| l.at <= pos).checked_sub(1)
+}
+
+// --- ID3v2 SYLT ---
+
+pub fn syncsafe(b: &[u8]) -> usize {
+    b.iter().fold(0, |n, &x| (n << 7) | (x & 0x7f) as usize)
+}
+
+/// Decode a string in ID3 text encoding `enc` up to its terminator. Returns the
+/// text and the bytes consumed, terminator included.
+pub fn id3_text(enc: u8, data: &[u8]) -> (String, usize) {
+    if enc == 1 || enc == 2 {
+        let end = data
+            .chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .map_or(data.len() & !1, |i| i * 2);
+        let mut units: Vec<u16> = data[..end]
+            .chunks_exact(2)
+            .map(|c| match enc {
+                2 => u16::from_be_bytes([c[0], c[1]]),
+                _ => u16::from_le_bytes([c[0], c[1]]),
+            })
+            .collect();
+        // UTF-16 with BOM: FFFE read as little endian is the BOM itself
+        if enc == 1 && units.first() == Some(&0xfffe) {
+            units = units.iter().map(|u| u.swap_bytes()).collect();
+        }
+        if units.first() == Some(&0xfeff) {
+            units.remove(0);
+        }
+        let consumed = (end + 2).min(data.len());
+        return (String::from_utf16_lossy(&units), consumed);
+    }
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    let text = if enc == 3 {
+        String::from_utf8_lossy(&data[..end]).into_owned()
+    } else {
+        data[..end].iter().map(|&b| b as char).collect() // ISO-8859-1
+    };
+    (text, (end + 1).min(data.len()))
+}
+
+/// Parse the body of a SYLT frame with millisecond timestamps.
+fn parse_sylt(frame: &[u8]) -> Option<Vec<SyncedLine>> {
+    let enc = *frame.first()?;
+    // Only absolute milliseconds; MPEG frame counts would need the frame rate
+    if *frame.get(4)? != 2 {
+        return None;
+    }
+    let (_, used) = id3_text(enc, frame.get(6..)?);
+    let mut data = &frame[6 + used..];
+    let mut entries: Vec<(Duration, String)> = Vec::new();
+    while !data.is_empty() {
+        let (text, used) = id3_text(enc, data);
+        let stamp = data.get(used..used + 4)?;
+        let at = Duration::from_millis(u32::from_be_bytes(stamp.try_into().ok()?) as u64);
+        entries.push((at, text.replace('\r', "\n")));
+        data = &data[used + 4..];
+    }
+
+    // Syllable-timed frames mark line starts with a newline; otherwise every
+    // entry is a whole line
+    let syllables = entries.iter().skip(1).any(|(_, t)| t.starts_with('\n'));
+    let mut lines: Vec<SyncedLine> = Vec::new();
+    for (at, text) in entries {
+        match lines.last_mut() {
+            Some(line) if syllables && !text.starts_with('\n') => {
+                if line.words.is_empty() {
+                    line.words.push((line.at, 0));
+                }
+                line.words.push((at, line.text.len()));
+                line.text.push_str(&text);
+            }
+            _ => lines.push(SyncedLine {
+                at,
+                text: text.trim_start_matches('\n').to_string(),
+                words: Vec::new(),
+            }),
+        }
+    }
+    (!lines.is_empty()).then_some(lines)
+}
+
+/// Synchronized lyrics from an ID3v2.3/2.4 SYLT frame at the start of `path`.
+pub fn read_sylt(path: &Path) -> Option<Vec<SyncedLine>> {
+    let data = fs::read(path).ok()?;
+    let header = data.get(..10)?;
+    if &header[..3] != b"ID3" || !(3..=4).contains(&header[3]) {
+        return None;
+    }
+    let version = header[3];
+    // Unsynchronised tags would need their 0xFF 0x00 pairs undone first
+    if header[5] & 0x80 != 0 {
+        return None;
+    }
+    let end = (10 + syncsafe(&header[6..10])).min(data.len());
+    let mut pos = 10;
+    if header[5] & 0x40 != 0 {
+        // Extended header: its size excludes itself in v2.3
+        let size = data.get(pos..pos + 4)?;
+        pos += match version {
+            4 => syncsafe(size),
+            _ => u32::from_be_bytes(size.try_into().ok()?) as usize + 4,
+        };
+    }
+    while pos + 10 <= end {
+        let id = &data[pos..pos + 4];
+        if id[0] == 0 {
+            break; // padding
+        }
+        let raw_size = &data[pos + 4..pos + 8];
+        let size = match version {
+            4 => syncsafe(raw_size),
+            _ => u32::from_be_bytes(raw_size.try_into().ok()?) as usize,
+        };
+        let body = data.get(pos + 10..pos + 10 + size)?;
+        if id == b"SYLT"
+            && let Some(lines) = parse_sylt(body)
+        {
+            return Some(lines);
+        }
+        pos += 10 + size;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02.5"), Some(ms(62_500)));
+        assert_eq!(parse_timestamp("01:02.05"), Some(ms(62_050)));
+        assert_eq!(parse_timestamp("01:02.0509"), Some(ms(62_050)));
+        assert_eq!(parse_timestamp("00:01.000000000000000000000001"), Some(ms(1000)));
+        assert_eq!(parse_timestamp("00:60.00"), None);
+        assert_eq!(parse_timestamp("99999999999999999999:00"), None);
+        assert_eq!(parse_timestamp("18446744073709551615:00.00"), None);
+    }
+
+    #[test]
+    fn words_offsets_follow_trimming() {
+        let (text, words) = parse_words("<00:01.00>Hello <00:01.50>world <00:02.00>");
+        assert_eq!(text, "Hello world");
+        assert_eq!(words, vec![(ms(1000), 0), (ms(1500), 6), (ms(2000), 11)]);
+
+        let (text, words) = parse_words("  <00:01.00> héllo <00:02.00>  ");
+        assert_eq!(text, "héllo");
+        assert_eq!(words, vec![(ms(1000), 0), (ms(2000), text.len())]);
+        assert!(words.iter().all(|&(_, pos)| text.is_char_boundary(pos)));
+    }
+
+    #[test]
+    fn words_keep_unparsed_brackets() {
+        let (text, words) = parse_words("a <b> c");
+        assert_eq!(text, "a <b> c");
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn to_lrc_round_trips() {
+        let src = "[00:01.00]<00:01.00>Hello <00:01.50>world <00:02.00>\n[00:03.00]Plain\n";
+        let lines = parse_lrc(src).unwrap();
+        let out = to_lrc(&lines);
+        assert_eq!(out, "[00:01.00]<00:01.00>Hello <00:01.50>world<00:02.00>\n[00:03.00]Plain\n");
+        let again = parse_lrc(&out).unwrap();
+        assert_eq!(again.len(), 2);
+        assert_eq!(again[0].text, "Hello world");
+        assert_eq!(again[0].words, lines[0].words);
+    }
+
+    #[test]
+    fn offset_shifts_and_saturates() {
+        let lines = parse_lrc("[offset:500]\n[00:01.00]<00:01.00>a\n[00:00.20]b").unwrap();
+        assert_eq!(lines[0].at, Duration::ZERO);
+        assert_eq!(lines[1].at, ms(500));
+        assert_eq!(lines[1].words, vec![(ms(500), 0)]);
+
+        let early = parse_lrc("[offset:9223372036854775807]\n[00:01.00]a").unwrap();
+        assert_eq!(early[0].at, Duration::ZERO);
+        let late = parse_lrc("[offset:-9223372036854775808]\n[99:59.99]a").unwrap();
+        assert_eq!(late[0].at, ms(5_999_990 + (1 << 63)));
+    }
+
+    #[test]
+    fn repeated_stamps_shift_words() {
+        let lines = parse_lrc("[00:01.00][00:10.00]<00:01.00>a <00:01.50>b").unwrap();
+        assert_eq!(lines[1].at, ms(10_000));
+        assert_eq!(lines[1].words, vec![(ms(10_000), 0), (ms(10_500), 2)]);
+    }
+}
@@ -1,21 +1,52 @@
-use std::{fs, path::PathBuf, sync::mpsc, thread};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, StandardTagKey, Value},
+    probe::Hint,
+};
 
+use crate::cache::{self, Kind};
 use crate::{cache_hash, config_dir};
 
 use ratatui::{
     layout::Rect,
-    style::Style,
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Paragraph},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
     Frame,
 };
 
+use crate::lrc::{self, SyncedLine};
 use crate::theme::Theme;
 
 pub struct LyricsResult {
     pub text: String,
     pub url: String,
     pub art_url: Option<String>,
+    /// Timed lines when the source had them; `text` then holds the same lines untimed
+    pub synced: Option<Vec<SyncedLine>>,
+    /// Name of the provider the lyrics came from
+    pub provider: String,
+}
+
+impl LyricsResult {
+    fn from_lrc(lines: Vec<SyncedLine>, url: String) -> Self {
+        LyricsResult {
+            text: lrc::plain_text(&lines),
+            url,
+            art_url: None,
+            synced: Some(lines),
+            provider: String::new(),
+        }
+    }
 }
 
 fn url_encode(s: &str) -> String {
@@ -32,15 +63,46 @@ fn url_encode(s: &str) -> String {
     out
 }
 
-fn fetch_lyrics_ovh(artist: &str, title: &str) -> Option<LyricsResult> {
+/// Why a provider has no lyrics for a track.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Miss {
+    /// The service answered and has nothing: worth remembering
+    NotFound,
+    /// Timeout, no network or a broken answer: worth asking again
+    Failed,
+}
+
+impl From<ureq::Error> for Miss {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::StatusCode(404) => Miss::NotFound,
+            _ => Miss::Failed,
+        }
+    }
+}
+
+/// GET `url` and parse the body as JSON.
+fn get_json(agent: &ureq::Agent, url: &str) -> Result<serde_json::Value, Miss> {
+    let body = agent.get(url).call()?.body_mut().read_to_string()?;
+    serde_json::from_str(&body).map_err(|_| Miss::Failed)
+}
+
+fn fetch_lyrics_ovh(
+    agent: &ureq::Agent,
+    artist: &str,
+    title: &str,
+) -> Result<LyricsResult, Miss> {
     let artist_enc = url_encode(artist);
     let title_enc = url_encode(title);
     let url = format!("https://api.lyrics.ovh/v1/{artist_enc}/{title_enc}");
 
-    let body = ureq::get(&url).call().ok()?.body_mut().read_to_string().ok()?;
-    let json: serde_json::Value = serde_json::from_str(&body).ok()?;
-    let text = json.get("lyrics")?.as_str()?.trim().to_string();
-    if text.is_empty() { None } else { Some(LyricsResult { text, url, art_url: None }) }
+    let json = get_json(agent, &url)?;
+    let text = json["lyrics"].as_str().unwrap_or_default().trim().to_string();
+    if text.is_empty() {
+        Err(Miss::NotFound)
+    } else {
+        Ok(LyricsResult { text, url, art_url: None, synced: None, provider: String::new() })
+    }
 }
 
 fn html_to_text(html: &str) -> String {
@@ -121,27 +183,49 @@ fn decode_entity(entity: &str) -> String {
     }
 }
 
-fn fetch_lyrics_genius(artist: &str, title: &str) -> Option<LyricsResult> {
-    // Search Genius API
+/// One song from a Genius search.
+struct GeniusHit {
+    artist: String,
+    title: String,
+    url: String,
+    art_url: Option<String>,
+}
+
+fn genius_search(
+    agent: &ureq::Agent,
+    artist: &str,
+    title: &str,
+) -> Result<Vec<GeniusHit>, Miss> {
     let query = if artist.is_empty() {
         title.to_string()
     } else {
         format!("{artist} {title}")
     };
     let search_url = format!("https://genius.com/api/search?q={}", url_encode(&query));
-    let body = ureq::get(&search_url).call().ok()?.body_mut().read_to_string().ok()?;
-    let json: serde_json::Value = serde_json::from_str(&body).ok()?;
-
-    // Get first hit's URL and art
-    let hits = json.get("response")?.get("hits")?.as_array()?;
-    let result = hits.first()?.get("result")?;
-    let song_url = result.get("url")?.as_str()?.to_string();
-    let art_url = result.get("song_art_image_thumbnail_url")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+    let json = get_json(agent, &search_url)?;
+    let hits = json["response"]["hits"].as_array().cloned().unwrap_or_default();
+    Ok(hits
+        .iter()
+        .filter_map(|hit| {
+            let result = hit.get("result")?;
+            Some(GeniusHit {
+                artist: result["artist_names"]
+                    .as_str()
+                    .or(result["primary_artist"]["name"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                title: result["title"].as_str().unwrap_or_default().to_string(),
+                url: result.get("url")?.as_str()?.to_string(),
+                art_url: result["song_art_image_thumbnail_url"].as_str().map(str::to_string),
+            })
+        })
+        .collect())
+}
 
-    // Fetch song page
-    let page = ureq::get(&song_url).call().ok()?.body_mut().read_to_string().ok()?;
+/// Lyrics from a Genius song page.
+fn genius_lyrics(agent: &ureq::Agent, hit: GeniusHit) -> Result<LyricsResult, Miss> {
+    let song_url = hit.url;
+    let page = agent.get(&song_url).call()?.body_mut().read_to_string()?;
 
     // Extract lyrics from <div data-lyrics-container="true"> elements
     let mut lyrics = String::new();
@@ -189,71 +273,821 @@ fn fetch_lyrics_genius(artist: &str, title: &str) -> Option<LyricsResult> {
             text = text[after..].trim().to_string();
         }
     }
-    if text.is_empty() { None } else { Some(LyricsResult { text, url: song_url, art_url }) }
+    if text.is_empty() {
+        Err(Miss::NotFound)
+    } else {
+        Ok(LyricsResult {
+            text,
+            url: song_url,
+            art_url: hit.art_url,
+            synced: None,
+            provider: String::new(),
+        })
+    }
 }
 
-fn lyrics_cache_path(artist: &str, title: &str) -> PathBuf {
-    let key = format!("{}\0{}", artist.to_lowercase(), title.to_lowercase());
-    config_dir()
-        .join("cache")
-        .join("lyrics")
-        .join(format!("{}.json", cache_hash(&key)))
+/// Genius search; takes the first hit.
+fn fetch_lyrics_genius(
+    agent: &ureq::Agent,
+    artist: &str,
+    title: &str,
+) -> Result<LyricsResult, Miss> {
+    let hit = genius_search(agent, artist, title)?.into_iter().next().ok_or(Miss::NotFound)?;
+    genius_lyrics(agent, hit)
 }
 
-fn load_cached_lyrics(artist: &str, title: &str) -> Option<LyricsResult> {
-    let data = fs::read_to_string(lyrics_cache_path(artist, title)).ok()?;
-    let v: serde_json::Value = serde_json::from_str(&data).ok()?;
+fn lrclib_search(
+    agent: &ureq::Agent,
+    artist: &str,
+    title: &str,
+) -> Result<Vec<serde_json::Value>, Miss> {
+    let url = format!(
+        "https://lrclib.net/api/search?artist_name={}&track_name={}",
+        url_encode(artist),
+        url_encode(title)
+    );
+    let json = get_json(agent, &url)?;
+    json.as_array().cloned().ok_or(Miss::Failed)
+}
+
+/// Lyrics of one LRCLIB record, timed when it has them.
+fn lrclib_lyrics(hit: &serde_json::Value) -> Option<LyricsResult> {
+    let url = format!("https://lrclib.net/api/get/{}", hit["id"]);
+    if let Some(lines) = hit["syncedLyrics"].as_str().and_then(lrc::parse_lrc) {
+        return Some(LyricsResult::from_lrc(lines, url));
+    }
+    let text = hit["plainLyrics"].as_str()?.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(LyricsResult {
+        text: text.to_string(),
+        url,
+        art_url: None,
+        synced: None,
+        provider: String::new(),
+    })
+}
+
+/// LRCLIB search; the first hit with timed lyrics wins, else the first plain one.
+fn fetch_lyrics_lrclib(
+    agent: &ureq::Agent,
+    artist: &str,
+    title: &str,
+) -> Result<LyricsResult, Miss> {
+    let hits = lrclib_search(agent, artist, title)?;
+    let mut results = hits.iter().filter_map(lrclib_lyrics);
+    let first = results.next().ok_or(Miss::NotFound)?;
+    if first.synced.is_some() {
+        return Ok(first);
+    }
+    Ok(results.find(|r| r.synced.is_some()).unwrap_or(first))
+}
+
+/// The lyrics tag (ID3 USLT, Vorbis `LYRICS` / `UNSYNCEDLYRICS`, MP4 `©lyr`), when
+/// the file has one.
+fn embedded_lyrics(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let mut tags = Vec::new();
+    if let Some(rev) = probed.metadata.get().and_then(|mut m| m.skip_to_latest().cloned()) {
+        tags.extend(rev.tags().iter().cloned());
+    }
+    if let Some(rev) = probed.format.metadata().skip_to_latest() {
+        tags.extend(rev.tags().iter().cloned());
+    }
+    tags.into_iter()
+        .filter(|t| {
+            t.std_key == Some(StandardTagKey::Lyrics)
+                || ["LYRICS", "UNSYNCEDLYRICS", "UNSYNCED LYRICS"]
+                    .iter()
+                    .any(|k| t.key.eq_ignore_ascii_case(k))
+        })
+        .find_map(|t| match t.value {
+            Value::String(s) if !s.trim().is_empty() => Some(s),
+            _ => None,
+        })
+}
+
+/// Lyrics text that may or may not carry LRC timestamps.
+fn lyrics_from_text(text: &str, url: String) -> Option<LyricsResult> {
+    if let Some(lines) = lrc::parse_lrc(text) {
+        return Some(LyricsResult::from_lrc(lines, url));
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(LyricsResult {
+        text: text.to_string(),
+        url,
+        art_url: None,
+        synced: None,
+        provider: String::new(),
+    })
+}
+
+/// A `.lrc` or `.txt` file next to the track, with the same stem.
+fn sidecar_lyrics(path: &Path) -> Option<LyricsResult> {
+    ["lrc", "txt"].iter().find_map(|ext| {
+        let sidecar = path.with_extension(ext);
+        let text = fs::read_to_string(&sidecar).ok()?;
+        lyrics_from_text(&text, sidecar.display().to_string())
+    })
+}
+
+/// Lyrics inside the file: an ID3 SYLT frame, else the lyrics tag (timed if it
+/// holds LRC text).
+fn tagged_lyrics(path: &Path) -> Option<LyricsResult> {
+    if let Some(lines) = lrc::read_sylt(path) {
+        return Some(LyricsResult::from_lrc(lines, "Embedded SYLT".into()));
+    }
+    lyrics_from_text(&embedded_lyrics(path)?, "Embedded lyrics".into())
+}
+
+// --- Providers ---
+
+/// What a provider looks lyrics up by.
+pub struct LyricsQuery {
+    pub path: PathBuf,
+    pub artist: String,
+    pub title: String,
+}
+
+/// A source of lyrics. When several providers find lyrics, the one ranked highest
+/// wins.
+pub trait LyricsProvider: Send + Sync {
+    /// Identifier used in the `lyrics_sources` config, the cache and the panel title.
+    fn name(&self) -> &'static str;
+    /// Default rank, highest first; the order in `lyrics_sources` overrides it.
+    fn priority(&self) -> i32;
+    /// How long to wait for a result before giving up on this provider.
+    fn timeout(&self) -> Duration;
+    /// Local providers only read the track's own files. They are asked before any
+    /// network provider, and a hit means the network isn't touched.
+    fn is_local(&self) -> bool {
+        false
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss>;
+    /// Every match worth offering in the lyrics picker, best first.
+    fn search(&self, query: &LyricsQuery) -> Vec<LyricsCandidate> {
+        self.fetch(query)
+            .ok()
+            .map(|lyrics| LyricsCandidate {
+                artist: query.artist.clone(),
+                title: query.title.clone(),
+                detail: String::new(),
+                lyrics,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// A match offered in the lyrics picker.
+pub struct LyricsCandidate {
+    pub artist: String,
+    pub title: String,
+    /// Album, length or the like, to tell versions apart
+    pub detail: String,
+    pub lyrics: LyricsResult,
+}
+
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
+
+struct SidecarProvider;
+
+impl LyricsProvider for SidecarProvider {
+    fn name(&self) -> &'static str {
+        "sidecar"
+    }
+    fn priority(&self) -> i32 {
+        100
+    }
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+    fn is_local(&self) -> bool {
+        true
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss> {
+        sidecar_lyrics(&query.path).ok_or(Miss::NotFound)
+    }
+}
+
+struct EmbeddedProvider;
+
+impl LyricsProvider for EmbeddedProvider {
+    fn name(&self) -> &'static str {
+        "embedded"
+    }
+    fn priority(&self) -> i32 {
+        90
+    }
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+    fn is_local(&self) -> bool {
+        true
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss> {
+        tagged_lyrics(&query.path).ok_or(Miss::NotFound)
+    }
+}
+
+struct LrclibProvider;
+
+impl LyricsProvider for LrclibProvider {
+    fn name(&self) -> &'static str {
+        "lrclib"
+    }
+    // Ranked above the other web services since it has timed lyrics
+    fn priority(&self) -> i32 {
+        60
+    }
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss> {
+        fetch_lyrics_lrclib(&agent(self.timeout()), &query.artist, &query.title)
+    }
+    fn search(&self, query: &LyricsQuery) -> Vec<LyricsCandidate> {
+        let hits = lrclib_search(&agent(self.timeout()), &query.artist, &query.title);
+        hits.unwrap_or_default()
+            .iter()
+            .filter_map(|hit| {
+                let text = |key: &str| hit[key].as_str().unwrap_or_default().to_string();
+                let mut detail = text("albumName");
+                if let Some(secs) = hit["duration"].as_f64() {
+                    let secs = secs.round() as u64;
+                    detail.push_str(&format!(" {}:{:02}", secs / 60, secs % 60));
+                }
+                Some(LyricsCandidate {
+                    artist: text("artistName"),
+                    title: text("trackName"),
+                    detail: detail.trim().to_string(),
+                    lyrics: lrclib_lyrics(hit)?,
+                })
+            })
+            .collect()
+    }
+}
+
+struct OvhProvider;
+
+impl LyricsProvider for OvhProvider {
+    fn name(&self) -> &'static str {
+        "ovh"
+    }
+    fn priority(&self) -> i32 {
+        50
+    }
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss> {
+        fetch_lyrics_ovh(&agent(self.timeout()), &query.artist, &query.title)
+    }
+}
+
+/// Genius hits offered in the picker; each costs a page download.
+const GENIUS_CANDIDATES: usize = 5;
+
+struct GeniusProvider;
+
+impl LyricsProvider for GeniusProvider {
+    fn name(&self) -> &'static str {
+        "genius"
+    }
+    fn priority(&self) -> i32 {
+        40
+    }
+    // A search and then the song page
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(15)
+    }
+    fn fetch(&self, query: &LyricsQuery) -> Result<LyricsResult, Miss> {
+        fetch_lyrics_genius(&agent(self.timeout()), &query.artist, &query.title)
+    }
+    fn search(&self, query: &LyricsQuery) -> Vec<LyricsCandidate> {
+        let agent = agent(self.timeout());
+        let hits = genius_search(&agent, &query.artist, &query.title).unwrap_or_default();
+        // Song pages are fetched side by side; the search already cost a round trip
+        thread::scope(|scope| {
+            let pages: Vec<_> = hits
+                .into_iter()
+                .take(GENIUS_CANDIDATES)
+                .map(|hit| {
+                    let agent = &agent;
+                    scope.spawn(move || {
+                        let (artist, title) = (hit.artist.clone(), hit.title.clone());
+                        let lyrics = genius_lyrics(agent, hit).ok()?;
+                        Some(LyricsCandidate { artist, title, detail: String::new(), lyrics })
+                    })
+                })
+                .collect();
+            pages.into_iter().filter_map(|p| p.join().ok().flatten()).collect()
+        })
+    }
+}
+
+/// How long the best result so far waits for higher-ranked providers that are
+/// still working before it is shown anyway.
+const GRACE: Duration = Duration::from_millis(1500);
+
+/// The enabled providers, highest ranked first.
+pub struct LyricsRegistry {
+    providers: Vec<Arc<dyn LyricsProvider>>,
+}
+
+impl LyricsRegistry {
+    fn builtin() -> Vec<Arc<dyn LyricsProvider>> {
+        vec![
+            Arc::new(SidecarProvider),
+            Arc::new(EmbeddedProvider),
+            Arc::new(LrclibProvider),
+            Arc::new(OvhProvider),
+            Arc::new(GeniusProvider),
+        ]
+    }
+
+    /// Providers as configured in `~/.config/tui-player/lyrics_sources`: names
+    /// separated by spaces, commas or newlines (`#` comments), highest ranked first.
+    /// Providers it doesn't list are disabled; without the file every provider is
+    /// used, ranked by its own priority.
+    pub fn load() -> Self {
+        let mut builtin = Self::builtin();
+        let Ok(content) = fs::read_to_string(config_dir().join("lyrics_sources")) else {
+            builtin.sort_by_key(|p| std::cmp::Reverse(p.priority()));
+            return LyricsRegistry { providers: builtin };
+        };
+        let mut providers: Vec<Arc<dyn LyricsProvider>> = Vec::new();
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for name in line.split([',', ' ', '\t']) {
+                if let Some(i) = builtin.iter().position(|p| p.name().eq_ignore_ascii_case(name)) {
+                    providers.push(builtin.remove(i));
+                }
+            }
+        }
+        LyricsRegistry { providers }
+    }
+
+    /// Lyrics pinned in the picker win outright. Otherwise run the local providers,
+    /// then (if they found nothing) the network ones, passing results to `send` as
+    /// `run_providers` orders them. Cached network results, or a recent "not found",
+    /// stand in for the network providers.
+    fn run(&self, query: LyricsQuery, mut send: impl FnMut(LyricsResult)) {
+        if let Some(pinned) = load_pinned_lyrics(&query.artist, &query.title) {
+            send(pinned);
+            return;
+        }
+        let query = Arc::new(query);
+        let (local, network): (Vec<_>, Vec<_>) =
+            self.providers.iter().cloned().partition(|p| p.is_local());
+        if run_providers(&local, &query, &mut send) == Outcome::Found || network.is_empty() {
+            return;
+        }
+        if let Some(cached) = load_cached_lyrics(&query.artist, &query.title) {
+            send(cached);
+            return;
+        }
+        let key = lyrics_key(&query.artist, &query.title);
+        if cache::is_negative(Kind::Lyrics, &key) {
+            return;
+        }
+        let outcome = run_providers(&network, &query, |lr| {
+            save_lyrics_cache(&query.artist, &query.title, &lr);
+            send(lr);
+        });
+        // Only trusted when every service answered; an outage is retried next time
+        if outcome == Outcome::NotFound {
+            cache::put_negative(Kind::Lyrics, &key);
+        }
+    }
+
+    /// Every candidate from every provider, each sent as its provider finishes.
+    /// The channel disconnects once all providers are done.
+    fn search(&self, query: LyricsQuery) -> mpsc::Receiver<LyricsCandidate> {
+        let (tx, rx) = mpsc::channel();
+        let query = Arc::new(query);
+        for provider in &self.providers {
+            let (provider, query, tx) = (provider.clone(), query.clone(), tx.clone());
+            thread::spawn(move || {
+                for mut candidate in provider.search(&query) {
+                    candidate.lyrics.provider = provider.name().to_string();
+                    if tx.send(candidate).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        rx
+    }
+}
+
+/// How a `run_providers` round ended.
+#[derive(PartialEq)]
+enum Outcome {
+    Found,
+    /// Every provider answered that it has nothing
+    NotFound,
+    /// Nothing found, but some provider failed or timed out
+    Failed,
+}
+
+/// Ask every provider in `providers` (highest ranked first) at once. The best result
+/// is passed on as soon as every provider ranked above it has finished or timed
+/// out, or once it has waited `GRACE` for them. After that only timed lyrics follow,
+/// to replace untimed ones, under the same rule.
+fn run_providers(
+    providers: &[Arc<dyn LyricsProvider>],
+    query: &Arc<LyricsQuery>,
+    mut send: impl FnMut(LyricsResult),
+) -> Outcome {
+    let start = Instant::now();
+    let (done_tx, done_rx) = mpsc::channel();
+    for (i, provider) in providers.iter().enumerate() {
+        let (provider, query, done_tx) = (provider.clone(), query.clone(), done_tx.clone());
+        thread::spawn(move || {
+            let result = provider.fetch(&query).map(|mut lr| {
+                lr.provider = provider.name().to_string();
+                lr
+            });
+            let _ = done_tx.send((i, result));
+        });
+    }
+    drop(done_tx);
+
+    let deadlines: Vec<Instant> = providers.iter().map(|p| start + p.timeout()).collect();
+    let mut running = vec![true; providers.len()];
+    // Results not sent yet, with when they arrived
+    let mut results: Vec<Option<(Instant, LyricsResult)>> =
+        providers.iter().map(|_| None).collect();
+    // Whether what was sent so far is timed
+    let mut sent: Option<bool> = None;
+    let mut not_found = 0;
+    loop {
+        let now = Instant::now();
+        for (running, &deadline) in running.iter_mut().zip(&deadlines) {
+            *running &= now < deadline;
+        }
+        let mut wake = deadlines
+            .iter()
+            .zip(&running)
+            .filter(|(_, r)| **r)
+            .map(|(&d, _)| d)
+            .min();
+        let best = results.iter().position(|r| {
+            r.as_ref().is_some_and(|(_, lr)| sent.is_none() || lr.synced.is_some())
+        });
+        if let Some(b) = best {
+            let ready_at = results[b].as_ref().map_or(now, |(at, _)| *at + GRACE);
+            if !running[..b].contains(&true) || now >= ready_at {
+                if let Some((_, lr)) = results[b].take() {
+                    sent = Some(lr.synced.is_some());
+                    send(lr);
+                }
+                continue;
+            }
+            wake = Some(wake.map_or(ready_at, |w| w.min(ready_at)));
+        }
+        if sent == Some(true) {
+            break;
+        }
+        let Some(wake) = wake else {
+            break; // nothing running and nothing left to send
+        };
+        match done_rx.recv_timeout(wake.saturating_duration_since(now)) {
+            Ok((i, result)) if running[i] => {
+                running[i] = false;
+                match result {
+                    Ok(lr) => results[i] = Some((Instant::now(), lr)),
+                    Err(Miss::NotFound) => not_found += 1,
+                    Err(Miss::Failed) => {}
+                }
+            }
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => running.fill(false),
+        }
+    }
+    match sent {
+        Some(_) => Outcome::Found,
+        None if not_found == providers.len() => Outcome::NotFound,
+        None => Outcome::Failed,
+    }
+}
+
+/// Cache key (and pin file name) for a track's lyrics.
+fn lyrics_key(artist: &str, title: &str) -> String {
+    format!("{}\0{}", artist.to_lowercase(), title.to_lowercase())
+}
+
+fn lyrics_to_json(result: &LyricsResult) -> String {
+    serde_json::json!({
+        "text": result.text,
+        "url": result.url,
+        "art_url": result.art_url,
+        "lrc": result.synced.as_deref().map(lrc::to_lrc),
+        "provider": result.provider,
+    })
+    .to_string()
+}
+
+fn lyrics_from_json(data: &[u8]) -> Option<LyricsResult> {
+    let v: serde_json::Value = serde_json::from_slice(data).ok()?;
     Some(LyricsResult {
         text: v["text"].as_str()?.to_string(),
         url: v["url"].as_str()?.to_string(),
         art_url: v["art_url"].as_str().map(|s| s.to_string()),
+        synced: v["lrc"].as_str().and_then(lrc::parse_lrc),
+        provider: v["provider"].as_str().unwrap_or_default().to_string(),
     })
 }
 
+fn load_cached_lyrics(artist: &str, title: &str) -> Option<LyricsResult> {
+    lyrics_from_json(&cache::get(Kind::Lyrics, &lyrics_key(artist, title))?)
+}
+
 fn save_lyrics_cache(artist: &str, title: &str, result: &LyricsResult) {
-    let path = lyrics_cache_path(artist, title);
-    if let Some(parent) = path.parent() {
-        let _ = fs::create_dir_all(parent);
+    cache::put(Kind::Lyrics, &lyrics_key(artist, title), lyrics_to_json(result).as_bytes());
+}
+
+/// Drop the cached lyrics for a track, including a "not found" result.
+pub fn invalidate_lyrics(artist: &str, title: &str) {
+    cache::remove(Kind::Lyrics, &lyrics_key(artist, title));
+}
+
+// Pins are choices, not cache: they live with the config and are never evicted
+fn lyrics_pin_path(artist: &str, title: &str) -> PathBuf {
+    config_dir()
+        .join("lyrics_pins")
+        .join(format!("{}.json", cache_hash(&lyrics_key(artist, title))))
+}
+
+fn load_pinned_lyrics(artist: &str, title: &str) -> Option<LyricsResult> {
+    lyrics_from_json(&fs::read(lyrics_pin_path(artist, title)).ok()?)
+}
+
+fn save_pinned_lyrics(artist: &str, title: &str, result: &LyricsResult) {
+    let path = lyrics_pin_path(artist, title);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
     }
-    let json = serde_json::json!({
-        "text": result.text,
-        "url": result.url,
-        "art_url": result.art_url,
-    });
-    let _ = fs::write(path, json.to_string());
+    let _ = fs::write(path, lyrics_to_json(result));
+}
+
+/// Forget the lyrics pinned for a track, so the next lookup asks the providers again.
+pub fn unpin_lyrics(artist: &str, title: &str) {
+    let _ = fs::remove_file(lyrics_pin_path(artist, title));
 }
 
-pub fn spawn_lyrics_fetchers(artist: String, title: String) -> mpsc::Receiver<Option<LyricsResult>> {
+/// Fetch lyrics for the track at `path` from the configured providers (see
+/// `LyricsRegistry::run`). The channel disconnects when there is nothing more to come.
+pub fn spawn_lyrics_fetchers(
+    path: PathBuf,
+    artist: String,
+    title: String,
+) -> mpsc::Receiver<Option<LyricsResult>> {
     let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let query = LyricsQuery { path, artist, title };
+        LyricsRegistry::load().run(query, |lr| {
+            let _ = tx.send(Some(lr));
+        });
+    });
+    rx
+}
+
+// --- Picker ---
 
-    // Check cache first
-    if let Some(cached) = load_cached_lyrics(&artist, &title) {
-        let _ = tx.send(Some(cached));
-        return rx;
+/// Split a picker query at the first " - " into artist and title.
+fn split_query(query: &str) -> (String, String) {
+    match query.split_once(" - ") {
+        Some((artist, title)) => (artist.trim().to_string(), title.trim().to_string()),
+        None => (String::new(), query.trim().to_string()),
     }
+}
 
-    // Spawn one thread per source — first Some result wins
-    let tx1 = tx.clone();
-    let a1 = artist.clone();
-    let t1 = title.clone();
-    thread::spawn(move || {
-        let result = fetch_lyrics_ovh(&a1, &t1);
-        if let Some(ref lr) = result {
-            save_lyrics_cache(&a1, &t1, lr);
+/// Popup listing what every provider found for a track, to pick the right lyrics
+/// when the automatic choice is wrong.
+pub struct LyricsPicker {
+    /// Tags of the track the choice is pinned for
+    pub artist: String,
+    pub title: String,
+    path: PathBuf,
+    /// Search text, "artist - title"
+    pub query: String,
+    pub editing: bool,
+    pub candidates: Vec<LyricsCandidate>,
+    pub selected: usize,
+    rx: Option<mpsc::Receiver<LyricsCandidate>>,
+}
+
+impl LyricsPicker {
+    pub fn open(path: PathBuf, artist: String, title: String) -> Self {
+        let query = match artist.as_str() {
+            "" => title.clone(),
+            a => format!("{a} - {title}"),
+        };
+        let mut picker = LyricsPicker {
+            artist,
+            title,
+            path,
+            query,
+            editing: false,
+            candidates: Vec::new(),
+            selected: 0,
+            rx: None,
+        };
+        picker.search();
+        picker
+    }
+
+    /// Search again with the current query.
+    pub fn search(&mut self) {
+        let (artist, title) = split_query(&self.query);
+        let query = LyricsQuery { path: self.path.clone(), artist, title };
+        self.candidates.clear();
+        self.selected = 0;
+        self.rx = Some(LyricsRegistry::load().search(query));
+    }
+
+    /// Take in candidates found since the last call.
+    pub fn poll(&mut self) {
+        let Some(ref rx) = self.rx else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(candidate) => self.candidates.push(candidate),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.rx = None;
+                    break;
+                }
+            }
         }
-        let _ = tx1.send(result);
-    });
+    }
 
-    let tx2 = tx;
-    thread::spawn(move || {
-        let result = fetch_lyrics_genius(&artist, &title);
-        if let Some(ref lr) = result {
-            save_lyrics_cache(&artist, &title, lr);
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.candidates.len().saturating_sub(1));
+    }
+
+    /// Pin the highlighted candidate as the track's lyrics and return it.
+    pub fn pick(mut self) -> Option<LyricsResult> {
+        if self.selected >= self.candidates.len() {
+            return None;
         }
-        let _ = tx2.send(result);
-    });
+        let lyrics = self.candidates.swap_remove(self.selected).lyrics;
+        save_pinned_lyrics(&self.artist, &self.title, &lyrics);
+        Some(lyrics)
+    }
+}
 
-    rx
+pub fn draw_lyrics_picker(frame: &mut Frame, picker: &LyricsPicker, theme: &Theme) {
+    let area = frame.area();
+    let popup_w = 76u16.min(area.width);
+    let popup_h = (area.height * 4 / 5).max(12).min(area.height);
+    let popup_area = Rect::new(
+        area.width.saturating_sub(popup_w) / 2,
+        area.height.saturating_sub(popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+    frame.render_widget(Clear, popup_area);
+
+    let hint = if picker.editing {
+        " Enter: Search  Esc: Cancel "
+    } else {
+        " ↑/↓: Select  Enter: Use  /: Edit search  d: Unpin  Esc: Close "
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Find Lyrics ")
+        .title_bottom(Line::from(hint));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let dim = Style::default().fg(theme.dimmed);
+    let query_style = Style::default().fg(if picker.editing { theme.accent } else { theme.text });
+    let status = match (picker.rx.is_some(), picker.candidates.len()) {
+        (true, n) => format!("Searching… {n} found"),
+        (false, 0) => "Nothing found".to_string(),
+        (false, 1) => "1 result".to_string(),
+        (false, n) => format!("{n} results"),
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(" Search: ", dim),
+            Span::styled(picker.query.clone(), query_style),
+            Span::styled(if picker.editing { "▏" } else { "" }, query_style),
+        ]),
+        Line::styled(format!(" {status}"), dim),
+        Line::raw(""),
+    ];
+
+    // Candidate list on top, a preview of the highlighted one below
+    let list_h = (inner.height.saturating_sub(lines.len() as u16) / 2).max(1) as usize;
+    let first = picker.selected.saturating_sub(list_h - 1);
+    for (i, c) in picker.candidates.iter().enumerate().skip(first).take(list_h) {
+        let is_sel = i == picker.selected;
+        let name_style = if is_sel {
+            Style::default()
+                .fg(Color::Black)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        let name = match c.artist.as_str() {
+            "" => c.title.clone(),
+            artist => format!("{artist} – {}", c.title),
+        };
+        let mut spans = vec![
+            Span::styled(if is_sel { " > " } else { "   " }, Style::default().fg(theme.accent)),
+            Span::styled(format!("{:<9}", c.lyrics.provider), dim),
+            Span::styled(format!(" {name} "), name_style),
+        ];
+        if !c.detail.is_empty() {
+            spans.push(Span::styled(format!(" {}", c.detail), dim));
+        }
+        if c.lyrics.synced.is_some() {
+            spans.push(Span::styled(" [synced]", Style::default().fg(theme.secondary)));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines.extend((picker.candidates.len().saturating_sub(first)..list_h).map(|_| Line::raw("")));
+
+    if let Some(c) = picker.candidates.get(picker.selected) {
+        lines.push(Line::styled(format!(" {}", c.lyrics.url), dim));
+        lines.extend(
+            c.lyrics
+                .text
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| Line::styled(format!("   {l}"), Style::default().fg(theme.text))),
+        );
+    }
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+// --- Sync offset ---
+
+fn lyrics_offset_path(track: &Path) -> PathBuf {
+    config_dir()
+        .join("lyrics_offsets")
+        .join(cache_hash(&track.to_string_lossy()))
+}
+
+/// Per-track delay of synced lyrics in ms (positive shows lines later).
+pub fn load_lyrics_offset(track: &Path) -> i64 {
+    fs::read_to_string(lyrics_offset_path(track))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+pub fn save_lyrics_offset(track: &Path, offset_ms: i64) {
+    let path = lyrics_offset_path(track);
+    if offset_ms == 0 {
+        let _ = fs::remove_file(path);
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, offset_ms.to_string());
+}
+
+/// Panel title naming the provider the lyrics came from, and the sync offset.
+fn panel_title(provider: &str, offset_ms: i64) -> String {
+    let mut title = " Lyrics".to_string();
+    if !provider.is_empty() {
+        title.push_str(&format!(" · {provider}"));
+    }
+    if offset_ms != 0 {
+        title.push_str(&format!(" ({:+.1}s)", offset_ms as f64 / 1000.0));
+    }
+    title.push(' ');
+    title
 }
 
 /// Draw the expanded lyrics panel.
@@ -285,6 +1119,10 @@ pub fn draw_lyrics(
     let max_scroll = total_lines.saturating_sub(visible_height);
     *lyrics_scroll = (*lyrics_scroll).min(max_scroll);
 
+    let provider = match lyrics {
+        Some(lr) if !lyrics_loading => lr.provider.as_str(),
+        _ => "",
+    };
     let lyrics_widget = Paragraph::new(lyrics_lines)
         .scroll((*lyrics_scroll as u16, 0))
         .style(Style::default().fg(theme.text))
@@ -292,7 +1130,78 @@ pub fn draw_lyrics(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title(" Lyrics "),
+                .title(panel_title(provider, 0)),
+        );
+    frame.render_widget(lyrics_widget, area);
+}
+
+/// Draw timed lyrics centred on the line being sung at `position`. Lines already
+/// sung are dimmed; within the current line, words already sung are highlighted
+/// when the source has word timings.
+pub fn draw_synced_lyrics(
+    frame: &mut Frame,
+    area: Rect,
+    lyrics: &LyricsResult,
+    position: Duration,
+    offset_ms: i64,
+    theme: &Theme,
+) {
+    let lines = lyrics.synced.as_deref().unwrap_or_default();
+    let lyrics_url = lyrics.url.as_str();
+    let pos = Duration::from_millis((position.as_millis() as i64 - offset_ms).max(0) as u64);
+    let current = lrc::current_line(lines, pos);
+
+    let mut lyrics_lines: Vec<Line> = Vec::new();
+    if !lyrics_url.is_empty() {
+        lyrics_lines.push(Line::from(Span::styled(lyrics_url, Style::default().fg(theme.dimmed))));
+        lyrics_lines.push(Line::raw(""));
+    }
+    let header = lyrics_lines.len();
+    let sung = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+    for (i, line) in lines.iter().enumerate() {
+        let styled = match current {
+            Some(c) if i == c && !line.words.is_empty() => {
+                let split = line
+                    .words
+                    .iter()
+                    .rev()
+                    .find(|(at, _)| *at <= pos)
+                    .map_or(0, |&(_, byte)| {
+                        // Highlight through the end of the word being sung
+                        line.words
+                            .iter()
+                            .find(|(_, b)| *b > byte)
+                            .map_or(line.text.len(), |&(_, b)| b)
+                    });
+                Line::from(vec![
+                    Span::styled(line.text[..split].to_string(), sung),
+                    Span::styled(
+                        line.text[split..].to_string(),
+                        Style::default().fg(theme.text).add_modifier(Modifier::BOLD),
+                    ),
+                ])
+            }
+            Some(c) if i == c => Line::styled(line.text.clone(), sung),
+            Some(c) if i < c => Line::styled(line.text.clone(), Style::default().fg(theme.dimmed)),
+            _ => Line::styled(line.text.clone(), Style::default().fg(theme.text)),
+        };
+        lyrics_lines.push(styled);
+    }
+
+    // Keep the current line in the middle of the panel
+    let visible_height = area.height.saturating_sub(2) as usize;
+    let max_scroll = lyrics_lines.len().saturating_sub(visible_height);
+    let scroll = current
+        .map_or(0, |c| (header + c).saturating_sub(visible_height / 2))
+        .min(max_scroll);
+
+    let lyrics_widget = Paragraph::new(lyrics_lines)
+        .scroll((scroll as u16, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(panel_title(&lyrics.provider, offset_ms)),
         );
     frame.render_widget(lyrics_widget, area);
 }
@@ -1,13 +1,13 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     env, fs, io,
     os::unix::fs::OpenOptionsExt,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, Ordering},
         mpsc, Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use symphonia::core::{
@@ -28,28 +28,123 @@ use ratatui::{
 use tui_tree_widget::{TreeItem, TreeState};
 use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink, Source};
 mod now_playing;
-use now_playing::{draw_art_overlay, spawn_art_fetch, ArtPixels, ART_COLS, ART_ROWS};
+use now_playing::{
+    draw_art_overlay, spawn_art_fetch, spawn_local_art, AlbumArt, ART_COLS, ART_ROWS,
+};
 
 mod visualizer;
 use visualizer::VisMode;
 
+mod cache;
 mod lyrics;
+mod lrc;
 use lyrics::{spawn_lyrics_fetchers, LyricsResult};
 
 mod eq;
+mod eq_presets;
+mod eq_rules;
+mod browse;
 mod file_browser;
+mod library;
 mod gauge;
+mod graphics;
 mod progress;
 mod volume;
 mod controls;
+mod queue;
+mod playlist;
+mod mpris;
+mod ipc;
+mod scrobble;
+mod search;
+mod stretch;
+mod stereo;
+mod limiter;
+mod loudness;
+mod replaygain;
+mod tag_writer;
+mod sleep;
+mod watch;
 pub mod theme;
 use theme::{Theme, THEMES};
 
 const PIPE_PATH: &str = "/tmp/tui-player.pipe";
+const NOTICE_TIMEOUT: Duration = Duration::from_secs(4);
+/// How long the clip indicator stays lit after the last over.
+const CLIP_HOLD: Duration = Duration::from_millis(1500);
 
 pub type SampleBuf = Arc<Mutex<VecDeque<f32>>>;
+/// Playback speed in percent, shared by every source.
+type SharedSpeed = Arc<AtomicU32>;
+/// One track's ReplayGain multiplier as `f32` bits, so a finished loudness scan or
+/// a mode change reaches a source that is already playing.
+type SharedGain = Arc<AtomicU32>;
+/// The user's volume as `f32` bits. The sink applies it after the audio chain, so
+/// the limiter and the clip check need to know how much it will amplify.
+type SharedVolume = Arc<AtomicU32>;
 const SAMPLE_BUF_SIZE: usize = 8192;
 
+/// Loops longer than this are re-read from the decoder instead of buffered
+/// (a minute of 48 kHz stereo f32 is about 23 MB).
+const MAX_LOOP_BUFFER_SECS: f64 = 60.0;
+/// Shortest A-B loop that can be set.
+const MIN_LOOP_LENGTH: Duration = Duration::from_millis(200);
+
+/// Per-source state shared between `App` and one track's `PipedSource`: the A-B
+/// loop points, plus the offsets that map the sink's played time back onto the
+/// track's own timeline (see `App::position()`).
+#[derive(Default)]
+struct TrackTimeline {
+    points: Mutex<Option<(Duration, Duration)>>,
+    /// Total time jumped back by A-B loop wraps
+    rewound_us: AtomicU64,
+    /// Track time gained (or lost, if negative) by time-stretching
+    stretched_us: AtomicI64,
+}
+
+type SharedTimeline = Arc<TrackTimeline>;
+
+impl TrackTimeline {
+    fn new(a: Option<Duration>, b: Option<Duration>) -> SharedTimeline {
+        let ab = TrackTimeline::default();
+        ab.set(a, b);
+        Arc::new(ab)
+    }
+
+    fn set(&self, a: Option<Duration>, b: Option<Duration>) {
+        if let Ok(mut points) = self.points.lock() {
+            *points = match (a, b) {
+                (Some(a), Some(b)) if b > a => Some((a, b)),
+                _ => None,
+            };
+        }
+    }
+
+    /// Convert played time (`sink.get_pos()` since this source started) to
+    /// time elapsed on the track's timeline.
+    fn track_time(&self, played: Duration) -> Duration {
+        let played = played.as_micros() as i64;
+        let stretched = self.stretched_us.load(Ordering::Relaxed);
+        let rewound = self.rewound_us.load(Ordering::Relaxed) as i64;
+        Duration::from_micros((played + stretched - rewound).max(0) as u64)
+    }
+}
+
+/// Shared state a `PipedSource` reads its settings from and reports back through.
+/// `finished`, `gain` and `timeline` belong to one track; the rest are the player's.
+struct DspHandles {
+    pipe_ready: Arc<AtomicBool>,
+    samples: SampleBuf,
+    eq_params: eq::SharedEqParams,
+    speed: SharedSpeed,
+    stereo_params: stereo::SharedStereoParams,
+    clipped: Arc<AtomicBool>,
+    volume: SharedVolume,
+    finished: Arc<AtomicBool>,
+    gain: SharedGain,
+    timeline: SharedTimeline,
+}
+
 // Source wrapper that applies EQ, writes to pipe, and captures samples for visualization
 struct PipedSource<S> {
     inner: S,
@@ -62,28 +157,68 @@ struct PipedSource<S> {
     channels: u16,
     update_counter: u32,
     finished: Arc<AtomicBool>,
+    /// ReplayGain multiplier last read from `shared_gain`
     normalize_gain: f32,
+    shared_gain: SharedGain,
+    sample_rate: u32,
+    timeline: SharedTimeline,
+    /// Loop points last read from `timeline`
+    ab_points: Option<(Duration, Duration)>,
+    /// Loop range in interleaved samples (B shrinks to the end of the stream if past it)
+    ab_range: Option<(u64, u64)>,
+    /// Interleaved index of the next sample
+    pos: u64,
+    /// Decoded A→B audio. Once complete, loops replay from here without seeking.
+    loop_buf: Vec<f32>,
+    replaying: bool,
+    speed: SharedSpeed,
+    stretch: Option<stretch::TimeStretch>,
+    /// Audio handed back by the stretcher when returning to 1×
+    pending: VecDeque<f32>,
+    /// Track time gained by stretching, in seconds
+    stretched_secs: f64,
+    stereo_params: stereo::SharedStereoParams,
+    stereo: stereo::StereoParams,
+    /// Processed right sample of the current frame, emitted on the next call
+    stereo_pending: Option<f32>,
+    /// Created the first time the limiter is switched on; kept afterwards so
+    /// switching it off and on again doesn't shift the audio by its delay
+    limiter: Option<limiter::Limiter>,
+    limiter_wanted: bool,
+    /// Channel of the next output sample
+    out_channel: u16,
+    /// Set when a sample leaves the sink above full scale
+    clipped: Arc<AtomicBool>,
+    /// Sink volume last read from `shared_volume`
+    volume: f32,
+    shared_volume: SharedVolume,
+    /// Set to end the source early, e.g. a pre-queued track that is no longer next
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl<S> PipedSource<S>
 where
     S: Source<Item = f32>,
 {
-    fn new(
-        source: S,
-        pipe_ready: Arc<AtomicBool>,
-        samples: SampleBuf,
-        eq_params: eq::SharedEqParams,
-        channels: u16,
-        sample_rate: u32,
-        finished: Arc<AtomicBool>,
-        normalize_gain: f32,
-    ) -> Self {
-        let eq_filters = {
+    fn new(source: S, channels: u16, sample_rate: u32, handles: DspHandles) -> Self {
+        let DspHandles {
+            pipe_ready,
+            samples,
+            eq_params,
+            speed,
+            stereo_params,
+            clipped,
+            volume: shared_volume,
+            finished,
+            gain: shared_gain,
+            timeline,
+        } = handles;
+        let (eq_filters, limiter_wanted) = {
             let params = eq_params.lock().unwrap();
-            eq::EqFilters::new(channels, sample_rate as f32, &params)
+            (eq::EqFilters::new(channels, sample_rate as f32, &params), params.limiter)
         };
-        PipedSource {
+        let stereo = *stereo_params.lock().unwrap();
+        let mut piped = PipedSource {
             inner: source,
             pipe: None,
             pipe_ready,
@@ -94,10 +229,255 @@ where
             channels,
             update_counter: 0,
             finished,
-            normalize_gain,
+            normalize_gain: f32::from_bits(shared_gain.load(Ordering::Relaxed)),
+            shared_gain,
+            sample_rate,
+            timeline,
+            ab_points: None,
+            ab_range: None,
+            pos: 0,
+            loop_buf: Vec::new(),
+            replaying: false,
+            speed,
+            stretch: None,
+            pending: VecDeque::new(),
+            stretched_secs: 0.0,
+            stereo_params,
+            stereo,
+            stereo_pending: None,
+            limiter: None,
+            limiter_wanted,
+            out_channel: 0,
+            clipped,
+            volume: f32::from_bits(shared_volume.load(Ordering::Relaxed)),
+            shared_volume,
+            cancel: None,
+        };
+        piped.refresh_ab_loop();
+        piped.refresh_speed();
+        piped
+    }
+
+    /// Set the stream position the inner source was already seeked to.
+    fn starting_at(mut self, start: Duration) -> Self {
+        self.pos = self.to_samples(start);
+        self
+    }
+
+    /// End the stream as soon as `cancel` is set.
+    fn cancelled_by(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn to_samples(&self, d: Duration) -> u64 {
+        (d.as_secs_f64() * self.sample_rate as f64).round() as u64 * self.channels as u64
+    }
+
+    fn to_duration(&self, samples: u64) -> Duration {
+        Duration::from_secs_f64(samples as f64 / (self.sample_rate as f64 * self.channels as f64))
+    }
+
+    /// Pick up changed loop points. Leaving a loop while replaying from memory
+    /// re-syncs the decoder, which is still parked at B.
+    fn refresh_ab_loop(&mut self) {
+        let points = match self.timeline.points.try_lock() {
+            Ok(p) => *p,
+            Err(_) => return,
+        };
+        if points == self.ab_points {
+            return;
+        }
+        self.ab_points = points;
+        self.ab_range = points.map(|(a, b)| (self.to_samples(a), self.to_samples(b)));
+        self.loop_buf.clear();
+        if self.replaying {
+            self.replaying = false;
+            let _ = self.inner.try_seek(self.to_duration(self.pos));
         }
     }
 
+    /// Jump back to A. The first pass seeks the decoder in place; once A→B has
+    /// been recorded, later passes replay the buffer sample-for-sample.
+    fn wrap_to_a(&mut self, a: u64, b: u64) {
+        self.replaying = self.loop_buf.len() as u64 == b - a;
+        if !self.replaying {
+            self.loop_buf.clear();
+            if self.inner.try_seek(self.to_duration(a)).is_err() {
+                // Not seekable: play through until the loop points change
+                self.ab_range = None;
+                return;
+            }
+        }
+        let rewind = self.to_duration(self.pos - a);
+        self.timeline
+            .rewound_us
+            .fetch_add(rewind.as_micros() as u64, Ordering::Relaxed);
+        self.pos = a;
+        self.channel_idx = 0;
+    }
+
+    /// Pick up a changed playback speed. Returning to 1× hands the stretcher's
+    /// buffered audio to `pending` so nothing is skipped.
+    fn refresh_speed(&mut self) {
+        let speed = self.speed.load(Ordering::Relaxed) as f32 / 100.0;
+        if (speed - 1.0).abs() < 0.001 {
+            if let Some(ts) = self.stretch.take() {
+                self.pending.extend(ts.drain());
+            }
+        } else if let Some(ref mut ts) = self.stretch {
+            ts.set_speed(speed);
+        } else {
+            self.stretch = Some(stretch::TimeStretch::new(self.channels, self.sample_rate, speed));
+        }
+    }
+
+    /// Next sample after time-stretching. Each stretched output sample covers
+    /// `speed` samples of track time; the difference is published to the timeline.
+    fn next_stretched(&mut self) -> Option<f32> {
+        if let Some(v) = self.pending.pop_front() {
+            return Some(v);
+        }
+        if self.stretch.is_none() {
+            return self.next_raw();
+        }
+        while self.stretch.as_ref().is_some_and(|ts| ts.wants_input()) {
+            let raw = self.next_raw();
+            let ts = self.stretch.as_mut()?;
+            match raw {
+                Some(v) => ts.push(v),
+                None => ts.end_of_input(),
+            }
+        }
+        let ts = self.stretch.as_mut()?;
+        let v = ts.pop()?;
+        let speed = ts.speed() as f64;
+        self.stretched_secs += (speed - 1.0) / (self.sample_rate as f64 * self.channels as f64);
+        self.timeline
+            .stretched_us
+            .store((self.stretched_secs * 1e6) as i64, Ordering::Relaxed);
+        Some(v)
+    }
+
+    fn next_equalized(&mut self) -> Option<f32> {
+        let raw = self.next_stretched()?;
+
+        // Periodically check for EQ, stereo, gain, loop point and speed changes
+        // (every 4096 samples)
+        self.update_counter += 1;
+        if self.update_counter >= 4096 {
+            self.update_counter = 0;
+            self.normalize_gain = f32::from_bits(self.shared_gain.load(Ordering::Relaxed));
+            self.volume = f32::from_bits(self.shared_volume.load(Ordering::Relaxed));
+            if let Ok(params) = self.eq_params.try_lock() {
+                self.eq_filters.update_if_changed(&params);
+                self.limiter_wanted = params.limiter;
+            }
+            if let Some(ref mut limiter) = self.limiter {
+                limiter.set_enabled(self.limiter_wanted);
+                limiter.set_output_gain(self.volume);
+            }
+            if let Ok(params) = self.stereo_params.try_lock() {
+                self.stereo = *params;
+            }
+            self.refresh_ab_loop();
+            self.refresh_speed();
+        }
+
+        // Apply EQ then ReplayGain normalization
+        let sample = self.eq_filters.process(raw, self.channel_idx as usize)
+            * self.normalize_gain;
+        self.channel_idx = (self.channel_idx + 1) % self.channels;
+        Some(sample)
+    }
+
+    /// Stereo image stage. Works a frame at a time: the left sample is returned
+    /// and the processed right sample held for the next call.
+    fn next_imaged(&mut self) -> Option<f32> {
+        if let Some(r) = self.stereo_pending.take() {
+            return Some(r);
+        }
+        if self.channels != 2 || self.channel_idx != 0 || self.stereo.is_neutral() {
+            return self.next_equalized();
+        }
+        let l = self.next_equalized()?;
+        let Some(r) = self.next_equalized() else {
+            return Some(l);
+        };
+        let (l, r) = self.stereo.apply(l, r);
+        self.stereo_pending = Some(r);
+        Some(l)
+    }
+
+    /// True-peak limiter stage, last in the chain. Only starts on a frame boundary
+    /// so the limiter's channels line up with the stream's.
+    fn next_limited(&mut self) -> Option<f32> {
+        if self.limiter.is_none() && self.limiter_wanted && self.out_channel == 0 {
+            let mut limiter = limiter::Limiter::new(self.channels, self.sample_rate);
+            limiter.set_output_gain(self.volume);
+            self.limiter = Some(limiter);
+        }
+        if self.limiter.is_none() {
+            return self.next_imaged();
+        }
+        loop {
+            if let Some(v) = self.limiter.as_mut()?.pop() {
+                return Some(v);
+            }
+            match self.next_imaged() {
+                Some(v) => self.limiter.as_mut()?.push(v),
+                None => {
+                    let limiter = self.limiter.as_mut()?;
+                    return if limiter.flush() { limiter.pop() } else { None };
+                }
+            }
+        }
+    }
+
+    fn next_raw(&mut self) -> Option<f32> {
+        if let Some((a, b)) = self.ab_range
+            && self.pos >= b
+            && self.pos.is_multiple_of(self.channels as u64)
+        {
+            self.wrap_to_a(a, b);
+        }
+
+        if self.replaying
+            && let Some((a, _)) = self.ab_range
+            && let Some(&v) = self.loop_buf.get((self.pos - a) as usize)
+        {
+            self.pos += 1;
+            return Some(v);
+        }
+
+        let v = match self.inner.next() {
+            Some(v) => v,
+            None => {
+                // B lies past the end of the stream: loop from where it actually ends
+                let (a, _) = self.ab_range?;
+                if self.pos <= a {
+                    return None;
+                }
+                let end = self.pos;
+                self.ab_range = Some((a, end));
+                self.wrap_to_a(a, end);
+                return if self.ab_range.is_some() { self.next_raw() } else { None };
+            }
+        };
+
+        let max_buffered =
+            (MAX_LOOP_BUFFER_SECS * self.sample_rate as f64) as u64 * self.channels as u64;
+        if let Some((a, b)) = self.ab_range
+            && (a..b).contains(&self.pos)
+            && self.loop_buf.len() as u64 == self.pos - a
+            && b - a <= max_buffered
+        {
+            self.loop_buf.push(v);
+        }
+        self.pos += 1;
+        Some(v)
+    }
+
     fn ensure_pipe(&mut self) {
         if self.pipe.is_none() && self.pipe_ready.load(Ordering::Relaxed) {
             let file = fs::OpenOptions::new()
@@ -118,28 +498,22 @@ where
     type Item = f32;
 
     fn next(&mut self) -> Option<f32> {
-        let raw = match self.inner.next() {
+        if self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return None;
+        }
+        let sample = match self.next_limited() {
             Some(v) => v,
             None => {
                 self.finished.store(true, Ordering::Relaxed);
                 return None;
             }
         };
-
-        // Periodically check for EQ parameter changes (every 4096 samples)
-        self.update_counter += 1;
-        if self.update_counter >= 4096 {
-            self.update_counter = 0;
-            if let Ok(params) = self.eq_params.try_lock() {
-                self.eq_filters.update_if_changed(&params);
-            }
+        self.out_channel = (self.out_channel + 1) % self.channels;
+        // The sink's volume comes after this point
+        if sample.abs() * self.volume > 1.0 {
+            self.clipped.store(true, Ordering::Relaxed);
         }
 
-        // Apply EQ then ReplayGain normalization
-        let sample = self.eq_filters.process(raw, self.channel_idx as usize)
-            * self.normalize_gain;
-        self.channel_idx = (self.channel_idx + 1) % self.channels;
-
         // Write to pipe for external scope-tui
         self.ensure_pipe();
         if let Some(ref mut pipe) = self.pipe {
@@ -167,6 +541,10 @@ where
     S: Source<Item = f32>,
 {
     fn current_span_len(&self) -> Option<usize> {
+        // Looping and stretching splice the stream, so the decoder's spans no longer line up
+        if self.ab_range.is_some() || self.stretch.is_some() {
+            return None;
+        }
         self.inner.current_span_len()
     }
 
@@ -183,7 +561,20 @@ where
     }
 
     fn try_seek(&mut self, pos: Duration) -> Result<(), rodio::source::SeekError> {
-        self.inner.try_seek(pos)
+        self.inner.try_seek(pos)?;
+        self.pos = self.to_samples(pos);
+        self.replaying = false;
+        self.loop_buf.clear();
+        self.pending.clear();
+        if let Some(ref mut ts) = self.stretch {
+            *ts = stretch::TimeStretch::new(self.channels, self.sample_rate, ts.speed());
+        }
+        if let Some(ref mut limiter) = self.limiter {
+            *limiter = limiter::Limiter::new(self.channels, self.sample_rate);
+            limiter.set_enabled(self.limiter_wanted);
+        }
+        self.out_channel = 0;
+        Ok(())
     }
 }
 
@@ -196,6 +587,8 @@ struct LayoutRegions {
     lyrics: Rect,
     lyrics_title: Rect,
     eq_inner: Rect,
+    /// Cells reserved for album art drawn by a terminal graphics protocol
+    art: Option<Rect>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -245,8 +638,11 @@ struct QueuedTrack {
     duration: Option<Duration>,
     channels: u16,
     sample_rate: u32,
-    normalize_gain: f32,
+    normalize_gain: SharedGain,
     finished: Arc<AtomicBool>,
+    timeline: SharedTimeline,
+    /// Skips the track in the sink if it stops being the next one before it starts
+    cancel: Arc<AtomicBool>,
 }
 
 struct CrossfadeState {
@@ -257,8 +653,9 @@ struct CrossfadeState {
     duration: Option<Duration>,
     channels: u16,
     sample_rate: u32,
-    normalize_gain: f32,
+    normalize_gain: SharedGain,
     finished: Arc<AtomicBool>,
+    timeline: SharedTimeline,
 }
 
 const CROSSFADE_OPTIONS: [f32; 4] = [0.0, 2.0, 5.0, 8.0];
@@ -329,6 +726,8 @@ struct App {
     file_name: String,
     sink: Sink,
     paused: bool,
+    /// Paused at the start by `stop()`, reported as stopped until played again
+    stopped: bool,
     volume: f32,
     total_duration: Option<Duration>,
     seek_base: Duration,
@@ -343,22 +742,42 @@ struct App {
     regions: LayoutRegions,
     lyrics: Option<LyricsResult>,
     lyrics_scroll: usize,
+    /// Per-track delay applied to synced lyrics, in ms
+    lyrics_offset: i64,
     lyrics_visible: bool,
     lyrics_loading: bool,
     lyrics_url: String,
     lyrics_rx: Option<mpsc::Receiver<Option<LyricsResult>>>,
-    album_art: Option<ArtPixels>,
-    art_rx: Option<mpsc::Receiver<ArtPixels>>,
+    lyrics_picker: Option<lyrics::LyricsPicker>,
+    album_art: Option<AlbumArt>,
+    art_rx: Option<mpsc::Receiver<AlbumArt>>,
+    /// Network art URL held back until the local lookup comes up empty
+    art_fallback: Option<String>,
+    /// Network art URL offered for the current track, so `X` can drop it from the cache
+    art_url: Option<String>,
+    graphics: graphics::Graphics,
     root_dir: Option<PathBuf>,
     browser_open: bool,
     browser_state: TreeState<PathBuf>,
+    /// Tree shown in the browser; playback order follows it
     browser_items: Vec<TreeItem<'static, PathBuf>>,
+    /// Audio files of `browser_items` in display order, collected whenever the tree
+    /// is rebuilt so playback doesn't walk the whole tree on every track
+    browser_files: Vec<PathBuf>,
+    /// Directory (or playlist) tree the library views are built from
+    dir_items: Vec<TreeItem<'static, PathBuf>>,
+    browse_mode: browse::BrowseMode,
     browser_searching: bool,
     browser_search: String,
-    browser_filtered: Vec<PathBuf>,
+    browser_filtered: Vec<search::SearchHit>,
     browser_filter_idx: usize,
     track_loaded: bool,
-    normalize_gain: f32,
+    normalize_gain: SharedGain,
+    /// Where the playing track's gain came from
+    rg_source: replaygain::Source,
+    rg_settings: replaygain::Settings,
+    /// Measures untagged tracks; started the first time one plays
+    loudness: Option<loudness::Scanner>,
     current_finished: Arc<AtomicBool>,
     queued_track: Option<QueuedTrack>,
     info_open: bool,
@@ -372,22 +791,77 @@ struct App {
     volume_hover_col: Option<u16>,
     dragging: bool,
     eq_hover_band: Option<usize>,
+    /// Parametric EQ cursor: row 0 is the preamp, then one row per filter
+    eq_param_row: usize,
+    eq_param_field: eq::ParamField,
+    /// Path being typed for an EqualizerAPO / AutoEQ import
+    eq_import: Option<String>,
+    eq_presets: Option<eq_presets::PresetPicker>,
+    /// Label of the EQ rule that matched the current track
+    eq_rule: Option<String>,
+    /// The user's own EQ, put back once a track no rule matches plays
+    eq_manual: Option<eq::EqParams>,
+    /// Rules ignored for the rest of the session
+    eq_rules_off: bool,
     waveform: SharedWaveform,
     crossfade_duration: f32,
     crossfade: Option<CrossfadeState>,
     theme_idx: usize,
     theme_open: bool,
     mini_mode: bool,
+    queue: queue::PlayQueue,
+    queue_open: bool,
+    queue_selected: usize,
+    playlist_path: Option<PathBuf>,
+    notice: Option<(String, Instant)>,
+    mpris: Option<mpris::MprisServer>,
+    ipc: Option<ipc::IpcServer>,
+    scrobbler: Option<scrobble::Scrobbler>,
+    listen: Option<scrobble::ListenTracker>,
+    loop_a: Option<Duration>,
+    loop_b: Option<Duration>,
+    timeline: SharedTimeline,
+    speed: SharedSpeed,
+    stereo_params: stereo::SharedStereoParams,
+    stereo_open: bool,
+    /// Raised by the audio chain when output clips; drained every frame
+    clipped: Arc<AtomicBool>,
+    /// When clipping was last seen, for the volume bar's indicator
+    clip_at: Option<Instant>,
+    /// `volume`, shared with the playing sources
+    output_volume: SharedVolume,
+    sleep: Option<sleep::SleepTimer>,
+    sleep_menu: sleep::SleepMenu,
+    sleep_open: bool,
+    library: library::SharedLibrary,
+    /// Full file list from the startup library scan, once the walk finishes
+    library_scan: Option<mpsc::Receiver<Vec<PathBuf>>>,
+    /// Changes below `root_dir` while the player runs
+    watcher: Option<mpsc::Receiver<watch::FsChange>>,
+    /// Background probes of files the watcher added; each signals once indexed
+    library_indexing: Vec<mpsc::Receiver<()>>,
+    /// Notice reporting how the playlist export being written went
+    playlist_export: Option<mpsc::Receiver<String>>,
 }
 
 impl App {
     fn position(&self) -> Duration {
-        self.seek_base + self.sink.get_pos()
+        self.seek_base + self.timeline.track_time(self.sink.get_pos())
     }
 
     fn theme(&self) -> &'static Theme {
         &THEMES[self.theme_idx]
     }
+
+    /// Show a short-lived message on the bottom row.
+    fn set_notice(&mut self, msg: String) {
+        self.notice = Some((msg, Instant::now()));
+    }
+
+    /// Whether the clip indicator should be lit: held briefly so single overs show.
+    fn clipping(&self) -> bool {
+        self.clip_at.is_some_and(|t| t.elapsed() < CLIP_HOLD)
+    }
 }
 
 pub fn config_dir() -> PathBuf {
@@ -395,6 +869,15 @@ pub fn config_dir() -> PathBuf {
     PathBuf::from(home).join(".config").join("tui-player")
 }
 
+/// A path typed by the user, with a leading `~/` meaning the home directory.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let path = path.trim();
+    match path.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
 pub fn cache_hash(s: &str) -> String {
     let mut h: u64 = 0xcbf29ce484222325; // FNV-1a offset basis
     for b in s.bytes() {
@@ -404,10 +887,6 @@ pub fn cache_hash(s: &str) -> String {
     format!("{h:016x}")
 }
 
-fn clear_cache() {
-    let _ = fs::remove_dir_all(config_dir().join("cache"));
-}
-
 fn load_volume() -> f32 {
     fs::read_to_string(config_dir().join("volume"))
         .ok()
@@ -505,6 +984,21 @@ fn save_crossfade(duration: f32) {
     let _ = fs::write(dir.join("crossfade"), format!("{duration}"));
 }
 
+/// Playback speed in percent (100 = normal).
+fn load_speed() -> u32 {
+    fs::read_to_string(config_dir().join("speed"))
+        .ok()
+        .and_then(|s| s.trim().parse::<f32>().ok())
+        .map(|s| (s.clamp(stretch::MIN_SPEED, stretch::MAX_SPEED) * 100.0).round() as u32)
+        .unwrap_or(100)
+}
+
+fn save_speed(percent: u32) {
+    let dir = config_dir();
+    let _ = fs::create_dir_all(&dir);
+    let _ = fs::write(dir.join("speed"), format!("{}", percent as f32 / 100.0));
+}
+
 fn load_mini_mode() -> bool {
     fs::read_to_string(config_dir().join("mini_mode"))
         .ok()
@@ -518,6 +1012,39 @@ fn save_mini_mode(enabled: bool) {
     let _ = fs::write(dir.join("mini_mode"), format!("{enabled}"));
 }
 
+/// Per-file A-B loop points, stored as `<a_ms> [<b_ms>]` under `ab_loops/`.
+fn ab_loop_path(track: &Path) -> PathBuf {
+    config_dir()
+        .join("ab_loops")
+        .join(cache_hash(&track.to_string_lossy()))
+}
+
+fn load_ab_loop(track: &Path) -> (Option<Duration>, Option<Duration>) {
+    let content = fs::read_to_string(ab_loop_path(track)).unwrap_or_default();
+    let mut parts = content
+        .split_whitespace()
+        .map(|s| s.parse::<u64>().ok().map(Duration::from_millis));
+    let a = parts.next().flatten();
+    let b = parts.next().flatten().filter(|b| a.is_some_and(|a| *b > a));
+    (a, b)
+}
+
+fn save_ab_loop(track: &Path, a: Option<Duration>, b: Option<Duration>) {
+    let path = ab_loop_path(track);
+    let Some(a) = a else {
+        let _ = fs::remove_file(path);
+        return;
+    };
+    let mut content = a.as_millis().to_string();
+    if let Some(b) = b {
+        content.push_str(&format!(" {}", b.as_millis()));
+    }
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, content);
+}
+
 fn create_pipe() {
     let _ = fs::remove_file(PIPE_PATH);
     unsafe {
@@ -530,25 +1057,44 @@ fn remove_pipe() {
     let _ = fs::remove_file(PIPE_PATH);
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct TrackMeta {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
     pub date: Option<String>,
     pub genre: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
 }
 
+impl TrackMeta {
+    /// Four-digit year from the date tag ("2003", "2003-05-12").
+    pub fn year(&self) -> Option<String> {
+        let date = self.date.as_deref()?.trim();
+        let year = date.get(..4)?;
+        year.bytes().all(|b| b.is_ascii_digit()).then(|| year.to_string())
+    }
+}
+
+#[derive(Default)]
 struct ProbeInfo {
     duration: Option<Duration>,
     meta: TrackMeta,
-    replay_gain_db: Option<f32>,
+    rg_track_db: Option<f32>,
+    rg_album_db: Option<f32>,
+    rg_track_peak: Option<f32>,
+    rg_album_peak: Option<f32>,
+    codec: Option<String>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
 }
 
-fn probe_file(path: &PathBuf) -> ProbeInfo {
+fn probe_file(path: &Path) -> ProbeInfo {
     let file = match fs::File::open(path) {
         Ok(f) => f,
-        Err(_) => return ProbeInfo { duration: None, meta: TrackMeta::default(), replay_gain_db: None },
+        Err(_) => return ProbeInfo::default(),
     };
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
@@ -561,7 +1107,7 @@ fn probe_file(path: &PathBuf) -> ProbeInfo {
         .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
     {
         Ok(p) => p,
-        Err(_) => return ProbeInfo { duration: None, meta: TrackMeta::default(), replay_gain_db: None },
+        Err(_) => return ProbeInfo::default(),
     };
 
     // Extract duration
@@ -572,6 +1118,18 @@ fn probe_file(path: &PathBuf) -> ProbeInfo {
         Some(Duration::from_secs_f64(time.seconds as f64 + time.frac))
     });
 
+    // Stream format
+    let (codec, sample_rate, channels) = match probed.format.default_track() {
+        Some(track) => (
+            symphonia::default::get_codecs()
+                .get_codec(track.codec_params.codec)
+                .map(|d| d.short_name.to_string()),
+            track.codec_params.sample_rate,
+            track.codec_params.channels.map(|c| c.count() as u16),
+        ),
+        None => (None, None, None),
+    };
+
     // Extract metadata tags
     let mut meta = TrackMeta::default();
 
@@ -592,8 +1150,20 @@ fn probe_file(path: &PathBuf) -> ProbeInfo {
         }
     }
 
-    let mut rg_track: Option<f32> = None;
-    let mut rg_album: Option<f32> = None;
+    /// Track/disc numbers come as integers or strings like "3/12".
+    fn tag_number(value: &Value) -> Option<u32> {
+        match value {
+            Value::UnsignedInt(n) => u32::try_from(*n).ok(),
+            Value::SignedInt(n) => u32::try_from(*n).ok(),
+            Value::String(s) => s.split('/').next()?.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    let mut rg_track_db: Option<f32> = None;
+    let mut rg_album_db: Option<f32> = None;
+    let mut rg_track_peak: Option<f32> = None;
+    let mut rg_album_peak: Option<f32> = None;
 
     for tag in &all_tags {
         match tag.std_key {
@@ -602,6 +1172,15 @@ fn probe_file(path: &PathBuf) -> ProbeInfo {
             }
             Some(StandardTagKey::Artist) | Some(StandardTagKey::AlbumArtist) => {
                 if meta.artist.is_none() { meta.artist = tag_string(&tag.value); }
+                if tag.std_key == Some(StandardTagKey::AlbumArtist) && meta.album_artist.is_none() {
+                    meta.album_artist = tag_string(&tag.value);
+                }
+            }
+            Some(StandardTagKey::TrackNumber) if meta.track_number.is_none() => {
+                meta.track_number = tag_number(&tag.value);
+            }
+            Some(StandardTagKey::DiscNumber) if meta.disc_number.is_none() => {
+                meta.disc_number = tag_number(&tag.value);
             }
             Some(StandardTagKey::Album) => {
                 if meta.album.is_none() { meta.album = tag_string(&tag.value); }
@@ -613,23 +1192,36 @@ fn probe_file(path: &PathBuf) -> ProbeInfo {
                 if meta.genre.is_none() { meta.genre = tag_string(&tag.value); }
             }
             Some(StandardTagKey::ReplayGainTrackGain) => {
-                if rg_track.is_none() {
-                    rg_track = tag_string(&tag.value).and_then(|s| parse_gain_db(&s));
+                if rg_track_db.is_none() {
+                    rg_track_db = tag_string(&tag.value).and_then(|s| parse_gain_db(&s));
                 }
             }
             Some(StandardTagKey::ReplayGainAlbumGain) => {
-                if rg_album.is_none() {
-                    rg_album = tag_string(&tag.value).and_then(|s| parse_gain_db(&s));
+                if rg_album_db.is_none() {
+                    rg_album_db = tag_string(&tag.value).and_then(|s| parse_gain_db(&s));
                 }
             }
+            Some(StandardTagKey::ReplayGainTrackPeak) if rg_track_peak.is_none() => {
+                rg_track_peak = tag_string(&tag.value).and_then(|s| s.trim().parse().ok());
+            }
+            Some(StandardTagKey::ReplayGainAlbumPeak) if rg_album_peak.is_none() => {
+                rg_album_peak = tag_string(&tag.value).and_then(|s| s.trim().parse().ok());
+            }
             _ => {}
         }
     }
 
-    // Prefer track gain, fall back to album gain
-    let replay_gain_db = rg_track.or(rg_album);
-
-    ProbeInfo { duration, meta, replay_gain_db }
+    ProbeInfo {
+        duration,
+        meta,
+        rg_track_db,
+        rg_album_db,
+        rg_track_peak,
+        rg_album_peak,
+        codec,
+        sample_rate,
+        channels,
+    }
 }
 
 /// Parse a ReplayGain string like "-6.5 dB" or "-6.5" into f32 dB value.
@@ -638,17 +1230,14 @@ fn parse_gain_db(s: &str) -> Option<f32> {
     s.parse::<f32>().ok()
 }
 
-/// Convert ReplayGain dB to linear multiplier. Returns 1.0 if None.
-fn rg_to_linear(db: Option<f32>) -> f32 {
-    match db {
-        Some(g) => 10.0f32.powf(g / 20.0),
-        None => 1.0,
-    }
-}
-
 impl App {
-    fn new_with_track(path: &PathBuf, root_dir: Option<PathBuf>) -> Self {
-        let probe = probe_file(path);
+    fn new_with_track(
+        path: &PathBuf,
+        root_dir: Option<PathBuf>,
+        browser_items: Vec<TreeItem<'static, PathBuf>>,
+        library: library::SharedLibrary,
+    ) -> Self {
+        let probe = library::probe(&library, path);
         let file_name = probe.meta.title.clone().unwrap_or_else(|| {
             path.file_name()
                 .map(|n| n.to_string_lossy().to_string())
@@ -667,24 +1256,38 @@ impl App {
         let pipe_ready = Arc::new(AtomicBool::new(true));
         let samples: SampleBuf = Arc::new(Mutex::new(VecDeque::with_capacity(SAMPLE_BUF_SIZE)));
         let eq_params = Arc::new(Mutex::new(eq::load_eq()));
-
-        let normalize_gain = rg_to_linear(probe.replay_gain_db);
+        let speed: SharedSpeed = Arc::new(AtomicU32::new(load_speed()));
+        let stereo_params = Arc::new(Mutex::new(stereo::load_stereo()));
+        let clipped = Arc::new(AtomicBool::new(false));
+        let output_volume: SharedVolume = Arc::new(AtomicU32::new(volume.to_bits()));
+
+        // Album order and background measuring are sorted out by refresh_gain() once
+        // the app is up; a measurement cached earlier is good to use already
+        let rg_settings = replaygain::load_settings();
+        let (gain, rg_source) =
+            replaygain::select(&probe, &rg_settings, false, loudness::cached(path));
+        let normalize_gain: SharedGain = Arc::new(AtomicU32::new(gain.to_bits()));
         let file = fs::File::open(path).expect("failed to open file");
         let buf = io::BufReader::new(file);
         let source = Decoder::new(buf).expect("failed to decode audio file");
         let channels = source.channels();
         let sample_rate = source.sample_rate();
         let current_finished = Arc::new(AtomicBool::new(false));
-        let piped = PipedSource::new(
-            source,
-            Arc::clone(&pipe_ready),
-            Arc::clone(&samples),
-            Arc::clone(&eq_params),
-            channels,
-            sample_rate,
-            Arc::clone(&current_finished),
-            normalize_gain,
-        );
+        let (loop_a, loop_b) = load_ab_loop(path);
+        let timeline = TrackTimeline::new(loop_a, loop_b);
+        let handles = DspHandles {
+            pipe_ready: Arc::clone(&pipe_ready),
+            samples: Arc::clone(&samples),
+            eq_params: Arc::clone(&eq_params),
+            speed: Arc::clone(&speed),
+            stereo_params: Arc::clone(&stereo_params),
+            clipped: Arc::clone(&clipped),
+            volume: Arc::clone(&output_volume),
+            finished: Arc::clone(&current_finished),
+            gain: Arc::clone(&normalize_gain),
+            timeline: Arc::clone(&timeline),
+        };
+        let piped = PipedSource::new(source, channels, sample_rate, handles);
         sink.append(piped);
 
         // Spawn background lyrics fetch from multiple sources
@@ -696,21 +1299,17 @@ impl App {
         });
         let has_query = !lyrics_title.is_empty();
         let lyrics_rx = if has_query {
-            Some(spawn_lyrics_fetchers(lyrics_artist, lyrics_title))
+            Some(spawn_lyrics_fetchers(path.clone(), lyrics_artist, lyrics_title))
         } else {
             None
         };
 
-        let browser_items = root_dir
-            .as_ref()
-            .map(|d| file_browser::scan_directory(d))
-            .unwrap_or_default();
-
         App {
             file_path: path.clone(),
             file_name,
             sink,
             paused: false,
+            stopped: false,
             volume,
             total_duration,
             seek_base: Duration::ZERO,
@@ -725,22 +1324,33 @@ impl App {
             regions: LayoutRegions::default(),
             lyrics: None,
             lyrics_scroll: 0,
+            lyrics_offset: lyrics::load_lyrics_offset(path),
             lyrics_visible: load_lyrics_visible(),
             lyrics_loading: has_query,
             lyrics_url: String::new(),
             lyrics_rx,
+            lyrics_picker: None,
             album_art: None,
-            art_rx: None,
+            art_rx: Some(spawn_local_art(path.to_path_buf(), ART_COLS, ART_ROWS)),
+            art_fallback: None,
+            art_url: None,
+            graphics: graphics::Graphics::new(graphics::Protocol::HalfBlocks),
             root_dir,
             browser_open: false,
             browser_state: TreeState::default(),
+            dir_items: browser_items.clone(),
+            browser_files: file_browser::collect_audio_files(&browser_items),
             browser_items,
+            browse_mode: browse::load_browse_mode(),
             browser_searching: false,
             browser_search: String::new(),
             browser_filtered: Vec::new(),
             browser_filter_idx: 0,
             track_loaded: true,
             normalize_gain,
+            rg_source,
+            rg_settings,
+            loudness: None,
             current_finished,
             queued_track: None,
             info_open: false,
@@ -754,6 +1364,13 @@ impl App {
             volume_hover_col: None,
             dragging: false,
             eq_hover_band: None,
+            eq_param_row: 0,
+            eq_param_field: eq::ParamField::Gain,
+            eq_import: None,
+            eq_presets: None,
+            eq_rule: None,
+            eq_manual: None,
+            eq_rules_off: false,
             waveform: {
                 let wf: SharedWaveform = Arc::new(Mutex::new(Vec::new()));
                 if let Some(d) = total_duration {
@@ -766,10 +1383,40 @@ impl App {
             theme_idx: theme::load_theme(),
             theme_open: false,
             mini_mode: load_mini_mode(),
+            queue: queue::PlayQueue::default(),
+            queue_open: false,
+            queue_selected: 0,
+            playlist_path: None,
+            notice: None,
+            mpris: None,
+            ipc: None,
+            scrobbler: None,
+            listen: None,
+            loop_a,
+            loop_b,
+            timeline,
+            speed,
+            stereo_params,
+            stereo_open: false,
+            clipped,
+            clip_at: None,
+            output_volume,
+            sleep: None,
+            sleep_menu: sleep::load_sleep_menu(),
+            sleep_open: false,
+            library,
+            library_scan: None,
+            watcher: None,
+            library_indexing: Vec::new(),
+            playlist_export: None,
         }
     }
 
-    fn new_idle(root_dir: PathBuf) -> Self {
+    fn new_idle(
+        root_dir: PathBuf,
+        browser_items: Vec<TreeItem<'static, PathBuf>>,
+        library: library::SharedLibrary,
+    ) -> Self {
         let stream = OutputStreamBuilder::from_default_device()
             .expect("failed to find audio device")
             .open_stream_or_fallback()
@@ -781,8 +1428,11 @@ impl App {
         let pipe_ready = Arc::new(AtomicBool::new(true));
         let samples: SampleBuf = Arc::new(Mutex::new(VecDeque::with_capacity(SAMPLE_BUF_SIZE)));
         let eq_params = Arc::new(Mutex::new(eq::load_eq()));
+        let speed: SharedSpeed = Arc::new(AtomicU32::new(load_speed()));
+        let stereo_params = Arc::new(Mutex::new(stereo::load_stereo()));
+        let clipped = Arc::new(AtomicBool::new(false));
+        let output_volume: SharedVolume = Arc::new(AtomicU32::new(volume.to_bits()));
 
-        let browser_items = file_browser::scan_directory(&root_dir);
         let mut browser_state = TreeState::default();
         browser_state.select_first();
 
@@ -791,6 +1441,7 @@ impl App {
             file_name: String::new(),
             sink,
             paused: true,
+            stopped: false,
             volume,
             total_duration: None,
             seek_base: Duration::ZERO,
@@ -805,22 +1456,33 @@ impl App {
             regions: LayoutRegions::default(),
             lyrics: None,
             lyrics_scroll: 0,
+            lyrics_offset: 0,
             lyrics_visible: load_lyrics_visible(),
             lyrics_loading: false,
             lyrics_url: String::new(),
             lyrics_rx: None,
+            lyrics_picker: None,
             album_art: None,
             art_rx: None,
+            art_fallback: None,
+            art_url: None,
+            graphics: graphics::Graphics::new(graphics::Protocol::HalfBlocks),
             root_dir: Some(root_dir),
             browser_open: true,
             browser_state,
+            dir_items: browser_items.clone(),
+            browser_files: file_browser::collect_audio_files(&browser_items),
             browser_items,
+            browse_mode: browse::load_browse_mode(),
             browser_searching: false,
             browser_search: String::new(),
             browser_filtered: Vec::new(),
             browser_filter_idx: 0,
             track_loaded: false,
-            normalize_gain: 1.0,
+            normalize_gain: Arc::new(AtomicU32::new(1f32.to_bits())),
+            rg_source: replaygain::Source::Off,
+            rg_settings: replaygain::load_settings(),
+            loudness: None,
             current_finished: Arc::new(AtomicBool::new(false)),
             queued_track: None,
             info_open: false,
@@ -834,23 +1496,57 @@ impl App {
             volume_hover_col: None,
             dragging: false,
             eq_hover_band: None,
+            eq_param_row: 0,
+            eq_param_field: eq::ParamField::Gain,
+            eq_import: None,
+            eq_presets: None,
+            eq_rule: None,
+            eq_manual: None,
+            eq_rules_off: false,
             waveform: Arc::new(Mutex::new(Vec::new())),
             crossfade_duration: load_crossfade(),
             crossfade: None,
             theme_idx: theme::load_theme(),
             theme_open: false,
             mini_mode: load_mini_mode(),
+            queue: queue::PlayQueue::default(),
+            queue_open: false,
+            queue_selected: 0,
+            playlist_path: None,
+            notice: None,
+            mpris: None,
+            ipc: None,
+            scrobbler: None,
+            listen: None,
+            loop_a: None,
+            loop_b: None,
+            timeline: Arc::default(),
+            speed,
+            stereo_params,
+            stereo_open: false,
+            clipped,
+            clip_at: None,
+            output_volume,
+            sleep: None,
+            sleep_menu: sleep::load_sleep_menu(),
+            sleep_open: false,
+            library,
+            library_scan: None,
+            watcher: None,
+            library_indexing: Vec::new(),
+            playlist_export: None,
         }
     }
 
     fn switch_track(&mut self, path: &PathBuf) {
+        self.consume_queue(path);
         if let Some(cf) = self.crossfade.take() {
             cf.sink.stop();
         }
         self.sink.stop();
         self.queued_track = None;
 
-        let probe = probe_file(path);
+        let probe = library::probe(&self.library, path);
         self.file_name = probe.meta.title.clone().unwrap_or_else(|| {
             path.file_name()
                 .map(|n| n.to_string_lossy().to_string())
@@ -860,7 +1556,10 @@ impl App {
         self.file_path = path.clone();
         self.seek_base = Duration::ZERO;
         self.paused = false;
-        self.normalize_gain = rg_to_linear(probe.replay_gain_db);
+        self.stopped = false;
+        let (gain, source) = self.track_gain(path, &probe);
+        self.normalize_gain = Arc::new(AtomicU32::new(gain.to_bits()));
+        self.rg_source = source;
 
         let new_sink = Sink::connect_new(self.stream.mixer());
         new_sink.set_volume(self.volume);
@@ -872,15 +1571,14 @@ impl App {
         let sample_rate = source.sample_rate();
         self.sample_rate = sample_rate;
         self.current_finished = Arc::new(AtomicBool::new(false));
+        (self.loop_a, self.loop_b) = load_ab_loop(path);
+        self.lyrics_offset = lyrics::load_lyrics_offset(path);
+        self.timeline = TrackTimeline::new(self.loop_a, self.loop_b);
         let piped = PipedSource::new(
             source,
-            Arc::clone(&self.pipe_ready),
-            Arc::clone(&self.samples),
-            Arc::clone(&self.eq_params),
             self.channels,
             sample_rate,
-            Arc::clone(&self.current_finished),
-            self.normalize_gain,
+            self.dsp_handles(&self.current_finished, &self.normalize_gain, &self.timeline),
         );
         new_sink.append(piped);
         self.sink = new_sink;
@@ -895,8 +1593,7 @@ impl App {
         self.lyrics_loading = false;
         self.lyrics_url.clear();
         self.lyrics_rx = None;
-        self.album_art = None;
-        self.art_rx = None;
+        self.start_art();
         self.waveform = Arc::new(Mutex::new(Vec::new()));
         if let Some(d) = self.total_duration {
             spawn_waveform_scan(path.clone(), d, Arc::clone(&self.waveform));
@@ -910,13 +1607,42 @@ impl App {
                 .unwrap_or_default()
         });
         if !lyrics_title.is_empty() {
-            self.lyrics_rx = Some(spawn_lyrics_fetchers(lyrics_artist, lyrics_title));
+            self.lyrics_rx = Some(spawn_lyrics_fetchers(
+                self.file_path.clone(),
+                lyrics_artist,
+                lyrics_title,
+            ));
             self.lyrics_loading = true;
         }
 
         self.meta = probe.meta;
+        self.apply_eq_rules();
         self.track_loaded = true;
         self.queue_next_track();
+        self.sync_mpris();
+        self.scrobble_now_playing();
+    }
+
+    /// Handles for a new source of the track that reports through `finished`,
+    /// `gain` and `timeline`.
+    fn dsp_handles(
+        &self,
+        finished: &Arc<AtomicBool>,
+        gain: &SharedGain,
+        timeline: &SharedTimeline,
+    ) -> DspHandles {
+        DspHandles {
+            pipe_ready: Arc::clone(&self.pipe_ready),
+            samples: Arc::clone(&self.samples),
+            eq_params: Arc::clone(&self.eq_params),
+            speed: Arc::clone(&self.speed),
+            stereo_params: Arc::clone(&self.stereo_params),
+            clipped: Arc::clone(&self.clipped),
+            volume: Arc::clone(&self.output_volume),
+            finished: Arc::clone(finished),
+            gain: Arc::clone(gain),
+            timeline: Arc::clone(timeline),
+        }
     }
 
     fn toggle_pause(&mut self) {
@@ -932,17 +1658,32 @@ impl App {
             }
         }
         self.paused = !self.paused;
+        self.stopped = false;
+    }
+
+    /// Pause and rewind to the start of the track.
+    fn stop(&mut self) {
+        if !self.paused {
+            self.toggle_pause();
+        }
+        self.seek_to(Duration::ZERO);
+        self.stopped = true;
     }
 
     fn volume_up(&mut self) {
         self.volume = ((self.volume * 20.0).round() + 1.0).min(40.0) / 20.0;
-        self.sink.set_volume(self.volume);
-        save_volume(self.volume);
+        self.apply_volume();
     }
 
     fn volume_down(&mut self) {
         self.volume = ((self.volume * 20.0).round() - 1.0).max(0.0) / 20.0;
+        self.apply_volume();
+    }
+
+    /// Hand a changed `volume` to the sink and the audio chain, and save it.
+    fn apply_volume(&mut self) {
         self.sink.set_volume(self.volume);
+        self.output_volume.store(self.volume.to_bits(), Ordering::Relaxed);
         save_volume(self.volume);
     }
 
@@ -974,16 +1715,14 @@ impl App {
         let sample_rate = source.sample_rate();
         let _ = source.try_seek(clamped);
         self.current_finished = Arc::new(AtomicBool::new(false));
+        self.timeline = TrackTimeline::new(self.loop_a, self.loop_b);
         let piped = PipedSource::new(
             source,
-            Arc::clone(&self.pipe_ready),
-            Arc::clone(&self.samples),
-            Arc::clone(&self.eq_params),
             self.channels,
             sample_rate,
-            Arc::clone(&self.current_finished),
-            self.normalize_gain,
-        );
+            self.dsp_handles(&self.current_finished, &self.normalize_gain, &self.timeline),
+        )
+        .starting_at(clamped);
         new_sink.append(piped);
 
         if self.paused {
@@ -998,17 +1737,31 @@ impl App {
         }
 
         self.queue_next_track();
+        if let Some(ref m) = self.mpris {
+            m.emit_seeked(clamped.as_micros() as i64);
+        }
     }
 
     fn set_volume(&mut self, vol: f32) {
         self.volume = vol.clamp(0.0, 2.0);
         // Snap to 5% grid
         self.volume = (self.volume * 20.0).round() / 20.0;
-        self.sink.set_volume(self.volume);
-        save_volume(self.volume);
+        self.apply_volume();
+    }
+
+    /// Track that directory-order navigation continues from: while queued tracks play,
+    /// the one that was playing when the queue took over, else the current track.
+    fn order_anchor<'a>(&'a self, files: &[PathBuf]) -> &'a PathBuf {
+        match self.queue.anchor {
+            Some(ref anchor) if files.contains(anchor) => anchor,
+            _ => &self.file_path,
+        }
     }
 
     fn find_next_path(&self) -> Option<PathBuf> {
+        if let Some(next) = self.queue.front().filter(|_| self.repeat_mode != RepeatMode::One) {
+            return Some(next.clone());
+        }
         let files = file_browser::collect_audio_files(&self.browser_items);
         if files.is_empty() {
             return None;
@@ -1016,11 +1769,12 @@ impl App {
         if self.repeat_mode == RepeatMode::One {
             return Some(self.file_path.clone());
         }
+        let current = self.order_anchor(&files);
         if self.shuffle && !self.shuffle_order.is_empty() {
             let pos = self
                 .shuffle_order
                 .iter()
-                .position(|&i| files.get(i) == Some(&self.file_path));
+                .position(|&i| files.get(i) == Some(current));
             match pos {
                 Some(p) if p + 1 < self.shuffle_order.len() => {
                     files.get(self.shuffle_order[p + 1]).cloned()
@@ -1031,7 +1785,7 @@ impl App {
                 _ => None,
             }
         } else {
-            let idx = files.iter().position(|f| f == &self.file_path);
+            let idx = files.iter().position(|f| f == current);
             match idx {
                 Some(i) if i + 1 < files.len() => files.get(i + 1).cloned(),
                 Some(_) if self.repeat_mode == RepeatMode::All => files.first().cloned(),
@@ -1048,11 +1802,12 @@ impl App {
         if self.repeat_mode == RepeatMode::One {
             return Some(self.file_path.clone());
         }
+        let current = self.order_anchor(&files);
         if self.shuffle && !self.shuffle_order.is_empty() {
             let pos = self
                 .shuffle_order
                 .iter()
-                .position(|&i| files.get(i) == Some(&self.file_path));
+                .position(|&i| files.get(i) == Some(current));
             match pos {
                 Some(p) if p > 0 => files.get(self.shuffle_order[p - 1]).cloned(),
                 Some(_) if self.repeat_mode == RepeatMode::All => {
@@ -1061,7 +1816,7 @@ impl App {
                 _ => None,
             }
         } else {
-            let idx = files.iter().position(|f| f == &self.file_path);
+            let idx = files.iter().position(|f| f == current);
             match idx {
                 Some(i) if i > 0 => files.get(i - 1).cloned(),
                 Some(_) if self.repeat_mode == RepeatMode::All => files.last().cloned(),
@@ -1070,9 +1825,395 @@ impl App {
         }
     }
 
+    /// Pop the queue head if `path` is it, remembering where directory order left off.
+    /// Any other track means directory order (or a track picked by hand) takes over
+    /// again. Must run before `file_path` is replaced with the new track.
+    fn consume_queue(&mut self, path: &PathBuf) {
+        if self.queue.front() != Some(path) {
+            self.queue.anchor = None;
+            return;
+        }
+        self.queue.pop_front();
+        // Only the track that was playing when the queue took over counts; later
+        // queued tracks leave it alone
+        if self.queue.anchor.is_none() {
+            self.queue.anchor = Some(self.file_path.clone());
+        }
+        self.queue_selected = self.queue_selected.min(self.queue.len().saturating_sub(1));
+    }
+
+    /// Re-plan the pre-buffered next track after the queue changed. When the
+    /// already-appended track is no longer the right one, it is skipped and the new
+    /// next track appended behind it; the playing track carries on untouched.
+    fn refresh_queued_track(&mut self) {
+        if !self.track_loaded || self.crossfade_duration > 0.0 {
+            return; // crossfade picks the next track when it starts
+        }
+        // The queued track may already be playing; advancing re-plans from there
+        if self.current_finished.load(Ordering::Relaxed) {
+            return;
+        }
+        let next = self.find_next_path();
+        match self.queued_track {
+            Some(ref q) if Some(&q.path) == next.as_ref() => {}
+            Some(_) => {
+                if let Some(stale) = self.queued_track.take() {
+                    stale.cancel.store(true, Ordering::Relaxed);
+                }
+                self.queue_next_track();
+            }
+            None => self.queue_next_track(),
+        }
+    }
+
+    /// Shift synced lyrics for this track by `delta_ms` (positive shows lines later).
+    fn nudge_lyrics_offset(&mut self, delta_ms: i64) {
+        self.lyrics_offset += delta_ms;
+        lyrics::save_lyrics_offset(&self.file_path, self.lyrics_offset);
+        let secs = self.lyrics_offset as f64 / 1000.0;
+        self.set_notice(format!("Lyrics offset {secs:+.1}s"));
+    }
+
+    /// Artist and title lyrics are looked up and cached by; the file name stands in
+    /// for a missing title.
+    fn lyrics_tags(&self) -> (String, String) {
+        let artist = self.meta.artist.clone().unwrap_or_default();
+        let title = self.meta.title.clone().unwrap_or_else(|| {
+            self.file_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        (artist, title)
+    }
+
+    /// Drop the current lyrics and look them up again.
+    fn refetch_lyrics(&mut self) {
+        self.lyrics = None;
+        self.lyrics_scroll = 0;
+        self.lyrics_loading = false;
+        self.lyrics_url.clear();
+        self.lyrics_rx = None;
+        let (artist, title) = self.lyrics_tags();
+        if !title.is_empty() {
+            self.lyrics_rx = Some(spawn_lyrics_fetchers(self.file_path.clone(), artist, title));
+            self.lyrics_loading = true;
+        }
+    }
+
+    fn open_lyrics_picker(&mut self) {
+        let (artist, title) = self.lyrics_tags();
+        let picker = lyrics::LyricsPicker::open(self.file_path.clone(), artist, title);
+        self.lyrics_picker = Some(picker);
+    }
+
+    /// Pin the picker's highlighted lyrics, and show them if that track is still playing.
+    fn pick_lyrics(&mut self, picker: lyrics::LyricsPicker) {
+        let tags = (picker.artist.clone(), picker.title.clone());
+        let Some(lr) = picker.pick() else {
+            return;
+        };
+        if self.track_loaded && self.lyrics_tags() == tags {
+            self.lyrics_url = lr.url.clone();
+            self.lyrics = Some(lr);
+            self.lyrics_scroll = 0;
+            self.lyrics_loading = false;
+            self.lyrics_rx = None;
+        }
+        self.set_notice("Lyrics pinned for this track".into());
+    }
+
+    /// Look up art for the current track, starting with the file and its folder.
+    fn start_art(&mut self) {
+        self.album_art = None;
+        self.art_fallback = None;
+        self.art_url = None;
+        self.art_rx = Some(spawn_local_art(self.file_path.clone(), ART_COLS, ART_ROWS));
+    }
+
+    /// Art URL from a lyrics source; only downloaded when there is no local art.
+    fn offer_art_url(&mut self, url: String) {
+        if self.album_art.is_some() {
+            return;
+        }
+        self.art_url = Some(url.clone());
+        if self.art_rx.is_some() {
+            self.art_fallback = Some(url);
+        } else {
+            self.art_rx = Some(spawn_art_fetch(url, ART_COLS, ART_ROWS));
+        }
+    }
+
+    fn poll_art(&mut self) {
+        let Some(ref rx) = self.art_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(art) => {
+                self.album_art = Some(art);
+                self.art_rx = None;
+                self.art_fallback = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.art_rx = self
+                    .art_fallback
+                    .take()
+                    .map(|url| spawn_art_fetch(url, ART_COLS, ART_ROWS));
+            }
+        }
+    }
+
+    /// Swap in the browser tree from the background library scan if files were added
+    /// or removed since the index was last saved.
+    fn poll_library_scan(&mut self) {
+        let Some(ref rx) = self.library_scan else {
+            return;
+        };
+        let files = match rx.try_recv() {
+            Ok(files) => files,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.library_scan = None;
+                return;
+            }
+        };
+        self.library_scan = None;
+        let Some(ref root) = self.root_dir else {
+            return;
+        };
+        let items = file_browser::build_tree(root, &files);
+        if file_browser::collect_audio_files(&items)
+            == file_browser::collect_audio_files(&self.dir_items)
+        {
+            return;
+        }
+        self.set_dir_files(files, &[]);
+    }
+
+    /// Apply files added, removed or renamed below `root_dir` since the last poll.
+    fn poll_watcher(&mut self) {
+        // Library views regroup once newly added files have their tags indexed
+        let before = self.library_indexing.len();
+        self.library_indexing
+            .retain(|rx| matches!(rx.try_recv(), Err(mpsc::TryRecvError::Empty)));
+        if self.library_indexing.len() < before && self.browse_mode != browse::BrowseMode::Files {
+            self.set_dir_files(file_browser::tree_files(&self.dir_items), &[]);
+        }
+
+        let Some(ref rx) = self.watcher else {
+            return;
+        };
+        let changes: Vec<watch::FsChange> = rx.try_iter().collect();
+        if changes.is_empty() {
+            return;
+        }
+        let mut files = file_browser::tree_files(&self.dir_items);
+        let mut added = Vec::new();
+        let mut renames = Vec::new();
+        for change in changes {
+            match change {
+                watch::FsChange::Added(path) => {
+                    if file_browser::is_audio_file(&path) {
+                        // Also re-probes a file rewritten in place (e.g. retagged)
+                        added.push(path.clone());
+                        files.push(path);
+                    }
+                }
+                watch::FsChange::Removed(path) => {
+                    files.retain(|f| !f.starts_with(&path));
+                    self.library.lock().unwrap().remove_under(&path);
+                    self.queue.remove_under(&path);
+                }
+                watch::FsChange::Renamed(from, to) => {
+                    let mut matched = false;
+                    for f in files.iter_mut().filter(|f| f.starts_with(&from)) {
+                        *f = file_browser::renamed(f, &from, &to);
+                        matched = true;
+                    }
+                    // Renaming "x.tmp" to "x.flac" makes a file appear
+                    if !matched && file_browser::is_audio_file(&to) {
+                        added.push(to.clone());
+                        files.push(to.clone());
+                    }
+                    files.retain(|f| file_browser::is_audio_file(f));
+                    self.library.lock().unwrap().rename_under(&from, &to);
+                    self.queue.rename_under(&from, &to);
+                    renames.push((from, to));
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        files.retain(|f| seen.insert(f.clone()));
+        if !added.is_empty() {
+            let rx = library::spawn_index(Arc::clone(&self.library), added);
+            self.library_indexing.push(rx);
+        }
+        self.set_dir_files(files, &renames);
+    }
+
+    /// Replace the directory tree with one built from `files`, keeping the browser
+    /// selection, open folders and shuffle order across the change. `renames` are
+    /// (from, to) paths applied since the old tree was built.
+    fn set_dir_files(&mut self, files: Vec<PathBuf>, renames: &[(PathBuf, PathBuf)]) {
+        let Some(ref root) = self.root_dir else {
+            return;
+        };
+        let follow = |path: &PathBuf| {
+            renames
+                .iter()
+                .fold(path.clone(), |p, (from, to)| file_browser::renamed(&p, from, to))
+        };
+        let old_files = file_browser::tree_files(&self.browser_items);
+        let old_order: Vec<PathBuf> = self
+            .shuffle_order
+            .iter()
+            .filter_map(|&i| old_files.get(i))
+            .map(follow)
+            .collect();
+
+        self.dir_items = file_browser::build_tree(root, &files);
+        self.file_path = follow(&self.file_path);
+        if let Some(ref mut queued) = self.queued_track {
+            queued.path = follow(&queued.path);
+        }
+        self.rebuild_browser_view();
+
+        // Known tracks keep their shuffled positions; new ones go at random after them
+        if self.shuffle {
+            let files = &self.browser_files;
+            let index: HashMap<&PathBuf, usize> =
+                files.iter().enumerate().map(|(i, f)| (f, i)).collect();
+            let mut order: Vec<usize> =
+                old_order.iter().filter_map(|p| index.get(p).copied()).collect();
+            let kept: HashSet<usize> = order.iter().copied().collect();
+            order.extend(shuffle_indices(files.len()).into_iter().filter(|i| !kept.contains(i)));
+            self.shuffle_order = order;
+        }
+
+        for opened in self.browser_state.opened().clone() {
+            let moved: Vec<PathBuf> = opened.iter().map(follow).collect();
+            if moved != opened {
+                self.browser_state.close(&opened);
+                self.browser_state.open(moved);
+            }
+        }
+        let selected: Vec<PathBuf> = self.browser_state.selected().iter().map(follow).collect();
+        // A deleted file leaves the cursor on its folder
+        let depth = file_browser::existing_depth(&self.browser_items, &selected);
+        if depth == 0 {
+            self.browser_state.select_first();
+        } else {
+            self.browser_state.select(selected[..depth].to_vec());
+        }
+        if self.browser_searching {
+            self.refresh_search();
+        }
+        // A deleted current track keeps playing from its open handle; reloading it would fail
+        if self.file_path.is_file() {
+            self.refresh_queued_track();
+        }
+    }
+
+    /// Rebuild the browser tree for the current browse mode. Library views are built
+    /// from the index, so albums play in disc/track order.
+    fn rebuild_browser_view(&mut self) {
+        self.browser_items = match self.browse_mode {
+            browse::BrowseMode::Files => self.dir_items.clone(),
+            mode => {
+                let files = file_browser::collect_audio_files(&self.dir_items);
+                browse::build_view(mode, &files, &self.library.lock().unwrap())
+            }
+        };
+        self.browser_files = file_browser::collect_audio_files(&self.browser_items);
+        // Any permutation of the right length still shuffles the reordered list
+        if self.shuffle && self.shuffle_order.len() != self.browser_files.len() {
+            self.regenerate_shuffle();
+        }
+    }
+
+    /// Re-run the browser search for the current query.
+    fn refresh_search(&mut self) {
+        self.browser_filtered = search::search(
+            &self.browser_items,
+            &self.browser_search,
+            &self.library.lock().unwrap(),
+            self.root_dir.as_deref(),
+        );
+    }
+
+    fn cycle_browse_mode(&mut self) {
+        self.browse_mode = self.browse_mode.next();
+        browse::save_browse_mode(self.browse_mode);
+        self.rebuild_browser_view();
+        self.browser_state = TreeState::default();
+        self.browser_state.select_first();
+        self.refresh_queued_track();
+    }
+
     fn regenerate_shuffle(&mut self) {
-        let files = file_browser::collect_audio_files(&self.browser_items);
-        self.shuffle_order = shuffle_indices(files.len());
+        self.shuffle_order = shuffle_indices(self.browser_files.len());
+    }
+
+    /// Save the current play order (shuffle-aware) as an M3U8 playlist under the
+    /// config directory. Tags are probed on a background thread.
+    fn export_playlist(&mut self) {
+        let files = &self.browser_files;
+        if files.is_empty() {
+            return;
+        }
+        let order: Vec<PathBuf> = if self.shuffle && !self.shuffle_order.is_empty() {
+            self.shuffle_order
+                .iter()
+                .filter_map(|&i| files.get(i).cloned())
+                .collect()
+        } else {
+            files.clone()
+        };
+        let name = self
+            .playlist_path
+            .as_ref()
+            .or(self.root_dir.as_ref())
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "playlist".into());
+        let dest = config_dir().join("playlists").join(format!("{name}.m3u8"));
+        self.set_notice(format!("Saving playlist to {}", dest.display()));
+        let library = Arc::clone(&self.library);
+        let (tx, rx) = mpsc::channel();
+        self.playlist_export = Some(rx);
+        std::thread::spawn(move || {
+            let entries: Vec<playlist::PlaylistEntry> = order
+                .into_iter()
+                .map(|path| {
+                    let probe = library::probe(&library, &path);
+                    let title = match (probe.meta.artist, probe.meta.title) {
+                        (Some(artist), Some(title)) => Some(format!("{artist} - {title}")),
+                        (None, Some(title)) => Some(title),
+                        _ => None,
+                    };
+                    playlist::PlaylistEntry { path, title, duration: probe.duration }
+                })
+                .collect();
+            let _ = tx.send(match playlist::save_m3u8(&dest, &entries) {
+                Ok(()) => format!("Saved playlist to {}", dest.display()),
+                Err(e) => format!("Saving playlist failed: {e}"),
+            });
+        });
+    }
+
+    fn poll_playlist_export(&mut self) {
+        let Some(ref rx) = self.playlist_export else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(msg) => {
+                self.playlist_export = None;
+                self.set_notice(msg);
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.playlist_export = None,
+        }
     }
 
     fn next_track(&mut self) {
@@ -1099,7 +2240,7 @@ impl App {
             None => return,
         };
 
-        let probe = probe_file(&next_path);
+        let probe = library::probe(&self.library, &next_path);
         let file_name = probe.meta.title.clone().unwrap_or_else(|| {
             next_path
                 .file_name()
@@ -1118,18 +2259,19 @@ impl App {
         };
         let channels = source.channels();
         let sample_rate = source.sample_rate();
-        let normalize_gain = rg_to_linear(probe.replay_gain_db);
+        let (gain, _) = self.track_gain(&next_path, &probe);
+        let normalize_gain: SharedGain = Arc::new(AtomicU32::new(gain.to_bits()));
         let finished = Arc::new(AtomicBool::new(false));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (loop_a, loop_b) = load_ab_loop(&next_path);
+        let timeline = TrackTimeline::new(loop_a, loop_b);
         let piped = PipedSource::new(
             source,
-            Arc::clone(&self.pipe_ready),
-            Arc::clone(&self.samples),
-            Arc::clone(&self.eq_params),
             channels,
             sample_rate,
-            Arc::clone(&finished),
-            normalize_gain,
-        );
+            self.dsp_handles(&finished, &normalize_gain, &timeline),
+        )
+        .cancelled_by(Arc::clone(&cancel));
         self.sink.append(piped);
 
         self.queued_track = Some(QueuedTrack {
@@ -1141,6 +2283,8 @@ impl App {
             sample_rate,
             normalize_gain,
             finished,
+            timeline,
+            cancel,
         });
     }
 
@@ -1149,6 +2293,7 @@ impl App {
             Some(q) => q,
             None => return,
         };
+        self.consume_queue(&queued.path);
 
         self.file_path = queued.path;
         self.file_name = queued.file_name;
@@ -1158,6 +2303,9 @@ impl App {
         self.sample_rate = queued.sample_rate;
         self.normalize_gain = queued.normalize_gain;
         self.current_finished = queued.finished;
+        self.timeline = queued.timeline;
+        (self.loop_a, self.loop_b) = load_ab_loop(&self.file_path);
+        self.lyrics_offset = lyrics::load_lyrics_offset(&self.file_path);
 
         // Reset lyrics, art, and waveform
         self.lyrics = None;
@@ -1165,8 +2313,7 @@ impl App {
         self.lyrics_loading = false;
         self.lyrics_url.clear();
         self.lyrics_rx = None;
-        self.album_art = None;
-        self.art_rx = None;
+        self.start_art();
         self.waveform = Arc::new(Mutex::new(Vec::new()));
         if let Some(d) = self.total_duration {
             spawn_waveform_scan(self.file_path.clone(), d, Arc::clone(&self.waveform));
@@ -1181,14 +2328,23 @@ impl App {
                 .unwrap_or_default()
         });
         if !lyrics_title.is_empty() {
-            self.lyrics_rx = Some(spawn_lyrics_fetchers(lyrics_artist, lyrics_title));
+            self.lyrics_rx = Some(spawn_lyrics_fetchers(
+                self.file_path.clone(),
+                lyrics_artist,
+                lyrics_title,
+            ));
             self.lyrics_loading = true;
         }
 
         self.meta = queued.meta;
+        self.apply_eq_rules();
+        self.refresh_gain();
 
         // Queue the next-next track
         self.queue_next_track();
+        self.sync_mpris();
+        self.scrobble_now_playing();
+        self.count_sleep_track();
     }
 
     fn start_crossfade(&mut self) {
@@ -1197,7 +2353,7 @@ impl App {
             None => return,
         };
 
-        let probe = probe_file(&next_path);
+        let probe = library::probe(&self.library, &next_path);
         let file_name = probe.meta.title.clone().unwrap_or_else(|| {
             next_path
                 .file_name()
@@ -1216,21 +2372,20 @@ impl App {
         };
         let channels = source.channels();
         let sample_rate = source.sample_rate();
-        let normalize_gain = rg_to_linear(probe.replay_gain_db);
+        let (gain, _) = self.track_gain(&next_path, &probe);
+        let normalize_gain: SharedGain = Arc::new(AtomicU32::new(gain.to_bits()));
 
         let new_sink = Sink::connect_new(self.stream.mixer());
         new_sink.set_volume(0.0);
 
         let finished = Arc::new(AtomicBool::new(false));
+        let (loop_a, loop_b) = load_ab_loop(&next_path);
+        let timeline = TrackTimeline::new(loop_a, loop_b);
         let piped = PipedSource::new(
             source,
-            Arc::clone(&self.pipe_ready),
-            Arc::clone(&self.samples),
-            Arc::clone(&self.eq_params),
             channels,
             sample_rate,
-            Arc::clone(&finished),
-            normalize_gain,
+            self.dsp_handles(&finished, &normalize_gain, &timeline),
         );
         new_sink.append(piped);
         if self.paused {
@@ -1247,6 +2402,7 @@ impl App {
             sample_rate,
             normalize_gain,
             finished,
+            timeline,
         });
     }
 
@@ -1255,6 +2411,7 @@ impl App {
             Some(cf) => cf,
             None => return,
         };
+        self.consume_queue(&cf.path);
 
         self.sink.stop();
         self.sink = cf.sink;
@@ -1268,6 +2425,9 @@ impl App {
         self.sample_rate = cf.sample_rate;
         self.normalize_gain = cf.normalize_gain;
         self.current_finished = cf.finished;
+        self.timeline = cf.timeline;
+        (self.loop_a, self.loop_b) = load_ab_loop(&self.file_path);
+        self.lyrics_offset = lyrics::load_lyrics_offset(&self.file_path);
         self.queued_track = None;
 
         // Reset lyrics, art, and waveform
@@ -1276,31 +2436,665 @@ impl App {
         self.lyrics_loading = false;
         self.lyrics_url.clear();
         self.lyrics_rx = None;
-        self.album_art = None;
-        self.art_rx = None;
+        self.start_art();
         self.waveform = Arc::new(Mutex::new(Vec::new()));
         if let Some(d) = self.total_duration {
             spawn_waveform_scan(self.file_path.clone(), d, Arc::clone(&self.waveform));
         }
 
-        // Spawn new lyrics fetchers
-        let lyrics_artist = cf.meta.artist.clone().unwrap_or_default();
-        let lyrics_title = cf.meta.title.clone().unwrap_or_else(|| {
-            self.file_path
-                .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default()
-        });
-        if !lyrics_title.is_empty() {
-            self.lyrics_rx = Some(spawn_lyrics_fetchers(lyrics_artist, lyrics_title));
-            self.lyrics_loading = true;
+        // Spawn new lyrics fetchers
+        let lyrics_artist = cf.meta.artist.clone().unwrap_or_default();
+        let lyrics_title = cf.meta.title.clone().unwrap_or_else(|| {
+            self.file_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        if !lyrics_title.is_empty() {
+            self.lyrics_rx = Some(spawn_lyrics_fetchers(
+                self.file_path.clone(),
+                lyrics_artist,
+                lyrics_title,
+            ));
+            self.lyrics_loading = true;
+        }
+
+        self.meta = cf.meta;
+        self.apply_eq_rules();
+        self.refresh_gain();
+        self.sync_mpris();
+        self.scrobble_now_playing();
+        self.count_sleep_track();
+    }
+
+    /// Whether `path` is being played as part of its album: shuffle is off and a
+    /// neighbour in directory order is from the same album.
+    fn in_album_order(&self, path: &Path, album: Option<&str>) -> bool {
+        let Some(album) = album.filter(|a| !a.trim().is_empty()) else {
+            return false;
+        };
+        if self.shuffle {
+            return false;
+        }
+        let files = &self.browser_files;
+        let Some(i) = files.iter().position(|f| f == path) else {
+            return false;
+        };
+        [i.checked_sub(1), Some(i + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|j| files.get(j))
+            .any(|f| library::probe(&self.library, f).meta.album.as_deref() == Some(album))
+    }
+
+    /// ReplayGain for `path` under the current settings. Untagged tracks use their
+    /// measurement when there is one, and are queued for measuring when not.
+    fn track_gain(&mut self, path: &Path, probe: &ProbeInfo) -> (f32, replaygain::Source) {
+        let tagged = probe.rg_track_db.is_some() || probe.rg_album_db.is_some();
+        let measure = !tagged
+            && self.rg_settings.analyze
+            && self.rg_settings.mode != replaygain::Mode::Off;
+        let measured = if measure { loudness::cached(path) } else { None };
+        if measure && measured.is_none() {
+            self.loudness.get_or_insert_with(loudness::Scanner::start).request(path);
+        }
+        let in_album = self.in_album_order(path, probe.meta.album.as_deref());
+        replaygain::select(probe, &self.rg_settings, in_album, measured)
+    }
+
+    /// Recompute the playing track's gain; the source picks it up within 4096 samples.
+    fn refresh_gain(&mut self) {
+        if !self.track_loaded {
+            return;
+        }
+        let path = self.file_path.clone();
+        let probe = library::probe(&self.library, &path);
+        let (gain, source) = self.track_gain(&path, &probe);
+        self.normalize_gain.store(gain.to_bits(), Ordering::Relaxed);
+        self.rg_source = source;
+    }
+
+    /// Apply loudness measurements that finished, to the playing track and to one
+    /// already lined up to follow it.
+    fn poll_loudness(&mut self) {
+        let Some(ref scanner) = self.loudness else {
+            return;
+        };
+        let done: Vec<PathBuf> = scanner.done.try_iter().collect();
+        for path in done {
+            let current = self.track_loaded && path == self.file_path;
+            let upcoming = self
+                .queued_track
+                .as_ref()
+                .map(|q| (&q.path, &q.normalize_gain))
+                .into_iter()
+                .chain(self.crossfade.as_ref().map(|cf| (&cf.path, &cf.normalize_gain)))
+                .find(|(p, _)| **p == path)
+                .map(|(_, gain)| Arc::clone(gain));
+            if !current && upcoming.is_none() {
+                continue;
+            }
+            let probe = library::probe(&self.library, &path);
+            let (gain, source) = self.track_gain(&path, &probe);
+            if current {
+                self.normalize_gain.store(gain.to_bits(), Ordering::Relaxed);
+                self.rg_source = source;
+            }
+            if let Some(shared) = upcoming {
+                shared.store(gain.to_bits(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn cycle_replay_gain_mode(&mut self) {
+        self.rg_settings.mode = self.rg_settings.mode.next();
+        replaygain::save_mode(self.rg_settings.mode);
+        self.refresh_gain();
+        self.set_notice(format!("ReplayGain: {}", self.rg_settings.mode.name()));
+    }
+
+    /// Load the EQ preset of the rule matching the current track, or put the user's
+    /// own EQ back when none does.
+    fn apply_eq_rules(&mut self) {
+        let rules = if self.eq_rules_off { Vec::new() } else { eq_rules::load_rules() };
+        let matched = eq_rules::find(&rules, &self.file_path, &self.meta)
+            .and_then(|rule| Some((rule.label(), eq_presets::find(&rule.preset)?)));
+        let label = matched.as_ref().map(|(label, _)| label.clone());
+        if let Ok(mut params) = self.eq_params.lock() {
+            match matched {
+                Some((_, preset)) => {
+                    self.eq_manual.get_or_insert_with(|| params.clone());
+                    eq_presets::apply(&preset, &mut params);
+                }
+                None => {
+                    if let Some(manual) = self.eq_manual.take() {
+                        *params = manual;
+                        eq::save_eq(&params);
+                    }
+                }
+            }
+        }
+        if label.is_some() && label != self.eq_rule {
+            self.set_notice(format!("EQ rule: {}", label.as_deref().unwrap_or_default()));
+        }
+        self.eq_rule = label;
+    }
+
+    /// Save the EQ after an edit. While a rule's preset plays, the user's own EQ is
+    /// `eq_manual`: switches (on/off, auto preamp, limiter) are saved into it, and
+    /// editing the curve makes the edited curve the user's own and lets the rule go.
+    fn save_eq(&mut self, curve_edited: bool) {
+        let Ok(params) = self.eq_params.lock() else {
+            return;
+        };
+        let mut released = false;
+        match self.eq_manual.as_mut() {
+            Some(manual) if !curve_edited => {
+                manual.enabled = params.enabled;
+                manual.auto_preamp = params.auto_preamp;
+                manual.limiter = params.limiter;
+                eq::save_eq(manual);
+            }
+            _ => {
+                released = self.eq_manual.take().is_some();
+                eq::save_eq(&params);
+            }
+        }
+        drop(params);
+        if released {
+            self.eq_rule = None;
+            self.set_notice("EQ rule released: the edited EQ is now your own".into());
+        }
+    }
+
+    /// Point the EQ at a renamed user preset's new name, or forget a deleted one,
+    /// so a stale name isn't saved as the preset in use.
+    fn follow_preset(&mut self, from: &str, to: Option<String>) {
+        let follow = |params: &mut eq::EqParams| {
+            let named = params.user_preset.as_deref() == Some(from);
+            if named {
+                params.user_preset = to.clone();
+            }
+            named
+        };
+        let mut changed = follow(&mut self.eq_params.lock().unwrap());
+        if let Some(manual) = self.eq_manual.as_mut() {
+            changed |= follow(manual);
+        }
+        if changed {
+            self.save_eq(false);
+        }
+    }
+
+    /// Bind `preset` to this track's file, album or genre, and apply it.
+    fn bind_eq_rule(&mut self, scope: eq_rules::Scope, preset: String) {
+        let genre = self.meta.genre.as_deref().and_then(|g| g.split([';', '/', ',', '\0']).next());
+        let pattern = match scope {
+            eq_rules::Scope::Path => Some(self.file_path.to_string_lossy().into_owned()),
+            eq_rules::Scope::Album => self.meta.album.clone(),
+            eq_rules::Scope::Genre => genre.map(|g| g.trim().to_string()),
+        };
+        let Some(pattern) = pattern.filter(|p| !p.is_empty()) else {
+            self.set_notice("This track has no tag to bind to".into());
+            return;
+        };
+        if let Err(e) = eq_rules::add_rule(&eq_rules::Rule { scope, pattern, preset }) {
+            self.set_notice(format!("Saving EQ rule failed: {e}"));
+            return;
+        }
+        self.eq_rules_off = false;
+        self.apply_eq_rules();
+    }
+
+    /// Stop or resume applying EQ rules; while off the user's own EQ plays.
+    fn toggle_eq_rules(&mut self) {
+        self.eq_rules_off = !self.eq_rules_off;
+        self.apply_eq_rules();
+        if self.eq_rules_off {
+            self.set_notice("EQ rules off for this session".into());
+        } else if self.eq_rule.is_none() {
+            self.set_notice("EQ rules on".into());
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.sink.empty()
+    }
+
+    fn set_repeat_mode(&mut self, mode: RepeatMode) {
+        self.repeat_mode = mode;
+        save_repeat_mode(mode);
+        // Re-queue next track based on new mode
+        if self.track_loaded {
+            self.queued_track = None;
+            if let Some(cf) = self.crossfade.take() {
+                cf.sink.stop();
+            }
+            self.sink.stop();
+            let pos = self.position();
+            self.seek_to(pos);
+        }
+    }
+
+    fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        save_shuffle(shuffle);
+        if shuffle {
+            self.regenerate_shuffle();
+        } else {
+            self.shuffle_order.clear();
+        }
+        // Re-queue next track based on new mode
+        if self.track_loaded {
+            self.queued_track = None;
+            if let Some(cf) = self.crossfade.take() {
+                cf.sink.stop();
+            }
+            self.sink.stop();
+            let pos = self.position();
+            self.seek_to(pos);
+        }
+    }
+
+    fn format_position(d: Duration) -> String {
+        let ms = d.as_millis();
+        format!("{}:{:02}.{}", ms / 60_000, ms / 1000 % 60, ms / 100 % 10)
+    }
+
+    /// Hand the loop points to the playing source (it picks them up within a few
+    /// milliseconds) and remember them for this file.
+    fn apply_loop(&mut self) {
+        self.timeline.set(self.loop_a, self.loop_b);
+        save_ab_loop(&self.file_path, self.loop_a, self.loop_b);
+    }
+
+    fn set_loop_a(&mut self) {
+        let pos = self.position();
+        self.loop_a = Some(pos);
+        if self.loop_b.is_some_and(|b| b < pos + MIN_LOOP_LENGTH) {
+            self.loop_b = None;
+        }
+        self.apply_loop();
+        self.set_notice(format!("Loop A set at {}", Self::format_position(pos)));
+    }
+
+    fn set_loop_b(&mut self) {
+        let pos = self.position();
+        match self.loop_a {
+            None => self.set_notice("Set loop start with [ first".into()),
+            Some(a) if pos < a + MIN_LOOP_LENGTH => {
+                self.set_notice("Loop end must come after loop start".into());
+            }
+            Some(a) => {
+                self.loop_b = Some(pos);
+                self.apply_loop();
+                self.set_notice(format!(
+                    "Looping {} – {}",
+                    Self::format_position(a),
+                    Self::format_position(pos)
+                ));
+            }
+        }
+    }
+
+    fn clear_loop(&mut self) {
+        if self.loop_a.is_none() {
+            return;
+        }
+        self.loop_a = None;
+        self.loop_b = None;
+        self.apply_loop();
+        self.set_notice("A-B loop cleared".into());
+    }
+
+    fn playback_speed(&self) -> f32 {
+        self.speed.load(Ordering::Relaxed) as f32 / 100.0
+    }
+
+    /// Change tempo without changing pitch, in 5% steps. Playing sources pick the
+    /// new speed up within a few milliseconds.
+    fn set_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(stretch::MIN_SPEED, stretch::MAX_SPEED);
+        let percent = (speed * 20.0).round() as u32 * 5;
+        self.speed.store(percent, Ordering::Relaxed);
+        save_speed(percent);
+        self.set_notice(format!("Speed {:.2}×", percent as f32 / 100.0));
+    }
+
+    fn loop_active(&self) -> bool {
+        self.loop_a.is_some() && self.loop_b.is_some()
+    }
+
+    fn mpris_track_id(&self) -> String {
+        format!(
+            "/org/tui_player/track/t{}",
+            cache_hash(&self.file_path.to_string_lossy())
+        )
+    }
+
+    fn mpris_state(&self) -> mpris::MprisState {
+        let track = if self.track_loaded {
+            Some(mpris::MprisTrack {
+                id: self.mpris_track_id(),
+                path: self.file_path.clone(),
+                title: self.file_name.clone(),
+                artist: self.meta.artist.clone(),
+                album: self.meta.album.clone(),
+                genre: self.meta.genre.clone(),
+                date: self.meta.date.clone(),
+                length_us: self.total_duration.map(|d| d.as_micros() as i64),
+                art_url: self.lyrics.as_ref().and_then(|l| l.art_url.clone()),
+            })
+        } else {
+            None
+        };
+        mpris::MprisState {
+            status: if !self.track_loaded || self.stopped {
+                "Stopped"
+            } else if self.paused {
+                "Paused"
+            } else {
+                "Playing"
+            },
+            loop_status: match self.repeat_mode {
+                RepeatMode::Off => "None",
+                RepeatMode::One => "Track",
+                RepeatMode::All => "Playlist",
+            },
+            shuffle: self.shuffle,
+            volume: self.volume as f64,
+            rate: self.playback_speed() as f64,
+            position_us: if self.track_loaded {
+                self.position().as_micros() as i64
+            } else {
+                0
+            },
+            can_go_next: self.track_loaded && self.root_dir.is_some(),
+            can_go_previous: self.track_loaded && self.root_dir.is_some(),
+            track,
+        }
+    }
+
+    /// Publish current state to MPRIS clients (no-op without a session bus).
+    fn sync_mpris(&self) {
+        if let Some(ref m) = self.mpris {
+            m.update(self.mpris_state());
+        }
+    }
+
+    /// Apply a D-Bus request. Returns true when the client asked the player to quit.
+    fn handle_mpris_command(&mut self, cmd: mpris::MprisCommand) -> bool {
+        use mpris::MprisCommand;
+        match cmd {
+            MprisCommand::Play if self.track_loaded && self.paused => self.toggle_pause(),
+            MprisCommand::Pause if self.track_loaded && !self.paused => self.toggle_pause(),
+            MprisCommand::PlayPause if self.track_loaded => self.toggle_pause(),
+            MprisCommand::Stop if self.track_loaded => self.stop(),
+            MprisCommand::Next if self.track_loaded => self.next_track(),
+            MprisCommand::Previous if self.track_loaded => self.prev_track(),
+            MprisCommand::Seek(offset) if self.track_loaded => {
+                let target = (self.position().as_micros() as i64).saturating_add(offset);
+                let end = self.total_duration.map_or(i64::MAX, |d| d.as_micros() as i64);
+                if target > end {
+                    // Seeking past the end behaves like Next, per the spec
+                    self.next_track();
+                } else {
+                    self.seek_to(Duration::from_micros(target.clamp(0, end) as u64));
+                }
+            }
+            MprisCommand::SetPosition(id, pos) if self.track_loaded => {
+                let in_range = pos >= 0
+                    && self
+                        .total_duration
+                        .is_none_or(|total| pos <= total.as_micros() as i64);
+                if id == self.mpris_track_id() && in_range {
+                    self.seek_to(Duration::from_micros(pos as u64));
+                }
+            }
+            MprisCommand::SetVolume(v) => self.set_volume(v.max(0.0) as f32),
+            // The spec treats a rate of 0 as a pause request
+            MprisCommand::SetRate(r) if r <= 0.0 && self.track_loaded && !self.paused => {
+                self.toggle_pause();
+            }
+            MprisCommand::SetRate(r) if r > 0.0 => self.set_speed(r as f32),
+            MprisCommand::SetShuffle(on) if on != self.shuffle => self.set_shuffle(on),
+            MprisCommand::SetLoopStatus(status) => {
+                let mode = match status.as_str() {
+                    "Track" => RepeatMode::One,
+                    "Playlist" => RepeatMode::All,
+                    _ => RepeatMode::Off,
+                };
+                if mode != self.repeat_mode {
+                    self.set_repeat_mode(mode);
+                }
+            }
+            MprisCommand::OpenUri(uri) => {
+                let path = PathBuf::from(match uri.strip_prefix("file://") {
+                    Some(rest) => playlist::percent_decode(rest),
+                    None => uri,
+                });
+                if path.is_file() && file_browser::is_audio_file(&path) {
+                    self.switch_track(&path);
+                    self.browser_open = false;
+                }
+            }
+            MprisCommand::Quit => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Announce the new track and start counting listened time. Tracks without
+    /// artist and title tags can't be matched by the services and are skipped.
+    fn scrobble_now_playing(&mut self) {
+        self.listen = None;
+        let Some(ref scrobbler) = self.scrobbler else {
+            return;
+        };
+        let (Some(artist), Some(title)) = (self.meta.artist.clone(), self.meta.title.clone()) else {
+            return;
+        };
+        let track = scrobble::ScrobbleTrack {
+            artist,
+            title,
+            album: self.meta.album.clone(),
+            duration: self.total_duration,
+        };
+        scrobbler.now_playing(&track);
+        self.listen = Some(scrobble::ListenTracker::new(track));
+    }
+
+    fn tick_scrobble(&mut self) {
+        let pos = self.position();
+        let Some(ref mut listen) = self.listen else {
+            return;
+        };
+        if let (Some(started_at), Some(scrobbler)) = (listen.update(pos), self.scrobbler.as_ref()) {
+            scrobbler.listen(&listen.track, started_at);
+        }
+    }
+
+    /// Arm or cancel the sleep timer.
+    fn set_sleep(&mut self, timer: Option<sleep::SleepTimer>) {
+        self.sleep = timer;
+        self.sink.set_volume(self.volume);
+        match self.sleep {
+            Some(ref t) => {
+                let msg = t.label();
+                self.set_notice(msg);
+            }
+            None => self.set_notice("Sleep timer off".into()),
+        }
+    }
+
+    /// A track finished naturally (gapless or crossfade transition).
+    fn count_sleep_track(&mut self) {
+        if let Some(sleep::SleepTimer { target: sleep::SleepTarget::Tracks(ref mut n), .. }) =
+            self.sleep
+        {
+            *n = n.saturating_sub(1);
+        }
+    }
+
+    /// Volume multiplier for the sleep fade-out: 1.0 until the last `FADE_SECS`, then
+    /// ramping down to silence when the timer fires.
+    fn sleep_gain(&self) -> f32 {
+        let remaining = match self.sleep.as_ref().map(|t| t.target) {
+            Some(sleep::SleepTarget::At(deadline)) => {
+                deadline.saturating_duration_since(Instant::now())
+            }
+            Some(sleep::SleepTarget::Tracks(0)) => Duration::ZERO,
+            Some(sleep::SleepTarget::Tracks(1)) if !self.loop_active() => {
+                match self.total_duration {
+                    Some(total) => total.saturating_sub(self.position()),
+                    None => return 1.0,
+                }
+            }
+            _ => return 1.0,
+        };
+        (remaining.as_secs_f32() / sleep::FADE_SECS).clamp(0.0, 1.0)
+    }
+
+    /// Pause (and restore the faded volume) once the sleep timer is due.
+    /// Returns true if the player should quit.
+    fn tick_sleep(&mut self) -> bool {
+        let Some(ref timer) = self.sleep else {
+            return false;
+        };
+        let due = match timer.target {
+            sleep::SleepTarget::At(deadline) => Instant::now() >= deadline,
+            sleep::SleepTarget::Tracks(n) => n == 0 || (self.track_loaded && self.is_finished()),
+        };
+        if !due {
+            return false;
+        }
+        let quit = timer.quit;
+        self.sleep = None;
+        // A playlist that already ran out is left to the end-of-queue handling
+        if self.track_loaded && !self.paused && !self.is_finished() {
+            self.toggle_pause();
         }
+        self.sink.set_volume(self.volume);
+        if let Some(ref cf) = self.crossfade {
+            cf.sink.set_volume(self.volume);
+        }
+        self.set_notice("Sleep timer: playback paused".into());
+        quit
+    }
 
-        self.meta = cf.meta;
+    fn ipc_status(&self) -> serde_json::Value {
+        let state = if !self.track_loaded || self.stopped {
+            "stopped"
+        } else if self.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        let repeat = match self.repeat_mode {
+            RepeatMode::Off => "off",
+            RepeatMode::All => "all",
+            RepeatMode::One => "one",
+        };
+        let track = if self.track_loaded {
+            serde_json::json!({
+                "file": self.file_path.display().to_string(),
+                "title": self.meta.title,
+                "artist": self.meta.artist,
+                "album": self.meta.album,
+                "date": self.meta.date,
+                "genre": self.meta.genre,
+            })
+        } else {
+            serde_json::Value::Null
+        };
+        serde_json::json!({
+            "ok": true,
+            "state": state,
+            "track": track,
+            "position": if self.track_loaded { self.position().as_secs_f64() } else { 0.0 },
+            "duration": self.total_duration.map(|d| d.as_secs_f64()),
+            "volume": (self.volume * 100.0).round() as u32,
+            "speed": self.playback_speed(),
+            "repeat": repeat,
+            "shuffle": self.shuffle,
+            "queue": self.queue.len(),
+        })
     }
 
-    fn is_finished(&self) -> bool {
-        self.sink.empty()
+    /// Apply a control-socket command and build its JSON reply.
+    fn handle_ipc_request(&mut self, request: ipc::IpcRequest) -> Result<serde_json::Value, String> {
+        use ipc::{Amount, IpcRequest};
+        let needs_track = !matches!(
+            request,
+            IpcRequest::Volume(_)
+                | IpcRequest::Speed(_)
+                | IpcRequest::Load(_)
+                | IpcRequest::Enqueue(_)
+                | IpcRequest::Status
+                | IpcRequest::Quit
+        );
+        if needs_track && !self.track_loaded {
+            return Err("no track loaded".into());
+        }
+        match request {
+            IpcRequest::Play if self.paused => self.toggle_pause(),
+            IpcRequest::Pause if !self.paused => self.toggle_pause(),
+            IpcRequest::Toggle => self.toggle_pause(),
+            IpcRequest::Stop => self.stop(),
+            IpcRequest::Next => self.next_track(),
+            IpcRequest::Prev => self.prev_track(),
+            IpcRequest::Seek(amount) => {
+                let secs = match amount {
+                    Amount::Absolute(s) => s,
+                    Amount::Relative(d) => self.position().as_secs_f64() + d,
+                };
+                let end = self.total_duration.map_or(f64::INFINITY, |d| d.as_secs_f64());
+                let target = Duration::try_from_secs_f64(secs.clamp(0.0, end));
+                self.seek_to(target.unwrap_or(Duration::MAX));
+            }
+            IpcRequest::Volume(amount) => {
+                let pct = match amount {
+                    Amount::Absolute(p) => p,
+                    Amount::Relative(d) => self.volume as f64 * 100.0 + d,
+                };
+                self.set_volume((pct / 100.0) as f32);
+            }
+            IpcRequest::Speed(amount) => {
+                let speed = match amount {
+                    Amount::Absolute(s) => s as f32,
+                    Amount::Relative(d) => self.playback_speed() + d as f32,
+                };
+                self.set_speed(speed);
+            }
+            IpcRequest::Load(path) => {
+                if !path.is_file() || !file_browser::is_audio_file(&path) {
+                    return Err(format!("not an audio file: {}", path.display()));
+                }
+                self.switch_track(&path);
+                self.browser_open = false;
+            }
+            IpcRequest::Enqueue(path) => {
+                let files = if path.is_dir() {
+                    file_browser::collect_audio_files(&file_browser::scan_directory(&path))
+                } else if path.is_file() && file_browser::is_audio_file(&path) {
+                    vec![path.clone()]
+                } else {
+                    Vec::new()
+                };
+                if files.is_empty() {
+                    return Err(format!("no audio files at {}", path.display()));
+                }
+                let count = files.len();
+                for f in files {
+                    self.queue.append(f);
+                }
+                self.refresh_queued_track();
+                return Ok(serde_json::json!({ "ok": true, "queued": count }));
+            }
+            IpcRequest::Status => return Ok(self.ipc_status()),
+            _ => {}
+        }
+        Ok(serde_json::json!({ "ok": true }))
     }
 }
 
@@ -1316,9 +3110,18 @@ fn has_scope_tui() -> bool {
 
 fn main() -> io::Result<()> {
     let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("ctl") => std::process::exit(ipc::run_client(&args[2..])),
+        Some("cache") => std::process::exit(cache::run_cli(&args[2..])),
+        Some("replaygain") => std::process::exit(replaygain::run_cli(&args[2..])),
+        _ => {}
+    }
     let scope_tui_installed = has_scope_tui();
     if args.len() < 2 {
-        eprintln!("Usage: tui-player <music-file-or-directory>");
+        eprintln!("Usage: tui-player <music-file-directory-or-playlist>");
+        eprintln!("       tui-player ctl <command> [arg]");
+        eprintln!("       tui-player cache stats|prune|clear [lyrics|art|loudness]");
+        eprintln!("       tui-player replaygain scan|write <files-or-directories>");
         if scope_tui_installed {
             eprintln!();
             eprintln!("For external visualization, run in another terminal:");
@@ -1332,6 +3135,17 @@ fn main() -> io::Result<()> {
         std::process::exit(1);
     }
 
+    let playlist_entries = if playlist::is_playlist_file(&path) {
+        let entries = playlist::load_playlist(&path)?;
+        if entries.is_empty() {
+            eprintln!("No playable tracks in playlist: {}", path.display());
+            std::process::exit(1);
+        }
+        Some(entries)
+    } else {
+        None
+    };
+
     if scope_tui_installed {
         create_pipe();
     }
@@ -1347,19 +3161,53 @@ fn main() -> io::Result<()> {
             )
         )?;
     }
+    cache::migrate();
     let mut terminal = ratatui::init();
-    let mut app = if path.is_dir() {
-        App::new_idle(path)
+    let library = library::Library::load_shared();
+    let mut app = if let Some(entries) = playlist_entries {
+        let first = entries[0].path.clone();
+        let root_dir = path.parent().map(|p| p.to_path_buf());
+        let items = playlist::tree_items(&entries);
+        let mut app = App::new_with_track(&first, root_dir, items, library);
+        app.playlist_path = Some(path);
+        app
+    } else if path.is_dir() {
+        let browser_items = library::browser_tree(&library, &path);
+        App::new_idle(path, browser_items, library)
     } else {
         let root_dir = path.parent().map(|p| p.to_path_buf());
-        App::new_with_track(&path, root_dir)
+        let browser_items = root_dir
+            .as_ref()
+            .map(|d| library::browser_tree(&library, d))
+            .unwrap_or_default();
+        App::new_with_track(&path, root_dir, browser_items, library)
     };
+    // Playlists fix their own track list; directories are indexed in the background
+    if app.playlist_path.is_none()
+        && let Some(ref root) = app.root_dir
+    {
+        app.library_scan = Some(library::spawn_update(Arc::clone(&app.library), root.clone()));
+        app.watcher = watch::watch(root);
+    }
+    if app.browse_mode != browse::BrowseMode::Files {
+        app.rebuild_browser_view();
+    }
     app.show_visualizer = scope_tui_installed;
+    app.graphics = graphics::Graphics::new(graphics::detect());
+    app.mpris = mpris::MprisServer::start();
+    app.ipc = ipc::IpcServer::start();
+    app.scrobbler = scrobble::Scrobbler::start();
+    if app.track_loaded {
+        app.scrobble_now_playing();
+    }
     if app.shuffle {
         app.regenerate_shuffle();
     }
+    app.refresh_gain();
     app.queue_next_track();
     let result = run(&mut terminal, &mut app);
+    let _ = app.graphics.sync(None, None, Rect::default());
+    library::save_shared(&app.library);
     ratatui::restore();
     if enhanced_keyboard {
         crossterm::execute!(
@@ -1380,21 +3228,66 @@ fn hit(rect: Rect, col: u16, row: u16) -> bool {
 
 fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
     loop {
+        // Apply requests from MPRIS clients (desktop widgets, headset buttons)
+        let mpris_commands: Vec<mpris::MprisCommand> = app
+            .mpris
+            .as_ref()
+            .map(|m| m.commands.try_iter().collect())
+            .unwrap_or_default();
+        let mut quit = false;
+        for cmd in mpris_commands {
+            quit |= app.handle_mpris_command(cmd);
+        }
+
+        // Apply requests from the control socket (`tui-player ctl`)
+        let ipc_messages: Vec<ipc::IpcMessage> = app
+            .ipc
+            .as_ref()
+            .map(|s| s.requests.try_iter().collect())
+            .unwrap_or_default();
+        for msg in ipc_messages {
+            if let ipc::IpcRequest::Quit = msg.request {
+                quit = true;
+                let _ = msg.reply.send(serde_json::json!({ "ok": true }).to_string());
+                continue;
+            }
+            let reply = match app.handle_ipc_request(msg.request) {
+                Ok(v) => v.to_string(),
+                Err(e) => ipc::error_reply(&e),
+            };
+            let _ = msg.reply.send(reply);
+        }
+        if quit {
+            break;
+        }
+        app.sync_mpris();
+        app.poll_library_scan();
+        app.poll_watcher();
+        app.poll_playlist_export();
+        app.poll_loudness();
+        if app.clipped.swap(false, Ordering::Relaxed) {
+            app.clip_at = Some(Instant::now());
+        }
+
         if app.track_loaded {
+            app.tick_scrobble();
             // Poll lyrics results — first Some wins, keep trying until all sources done
+            let mut art_url = None;
             if let Some(ref rx) = app.lyrics_rx {
                 loop {
                     match rx.try_recv() {
+                        // Only timed lyrics may replace lyrics already shown
+                        Ok(Some(lr)) if lr.synced.is_none() && app.lyrics.is_some() => {}
                         Ok(Some(lr)) => {
                             app.lyrics_url = lr.url.clone();
-                            if let Some(ref art_url) = lr.art_url {
-                                app.art_rx =
-                                    Some(spawn_art_fetch(art_url.clone(), ART_COLS, ART_ROWS));
-                            }
+                            art_url = lr.art_url.clone().or(art_url);
+                            let synced = lr.synced.is_some();
                             app.lyrics = Some(lr);
                             app.lyrics_loading = false;
-                            app.lyrics_rx = None;
-                            break;
+                            if synced {
+                                app.lyrics_rx = None;
+                                break;
+                            }
                         }
                         Ok(None) => {}
                         Err(mpsc::TryRecvError::Empty) => break,
@@ -1406,24 +3299,31 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                     }
                 }
             }
-
-            // Poll album art download
-            if let Some(ref rx) = app.art_rx {
-                if let Ok(pixels) = rx.try_recv() {
-                    app.album_art = Some(pixels);
-                    app.art_rx = None;
-                }
+            if let Some(url) = art_url {
+                app.offer_art_url(url);
+            }
+            app.poll_art();
+            if let Some(picker) = app.lyrics_picker.as_mut() {
+                picker.poll();
             }
 
         }
 
-        terminal.draw(|f| draw(f, &mut *app))?;
+        let screen = terminal.draw(|f| draw(f, &mut *app))?.area;
+        if app.graphics.sync(app.album_art.as_ref(), app.regions.art, screen)? {
+            terminal.clear()?;
+        }
 
         if event::poll(Duration::from_millis(50))? {
             match event::read()? {
                 Event::Key(key) if key.kind == KeyEventKind::Press => {
-                    // Quit always works
-                    if key.code == KeyCode::Char('q')
+                    // Quit always works, except while typing a lyrics search or in the
+                    // sleep timer, where `q` toggles quitting when the timer ends
+                    let typing = app.lyrics_picker.as_ref().is_some_and(|p| p.editing)
+                        || app.sleep_open
+                        || (app.eq_open && app.eq_import.is_some())
+                        || app.eq_presets.as_ref().is_some_and(|p| p.prompt.is_some());
+                    if (key.code == KeyCode::Char('q') && !typing)
                         || (key.code == KeyCode::Char('c')
                             && key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL))
                     {
@@ -1449,10 +3349,7 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                         }
                         KeyCode::Media(MediaKeyCode::Stop) => {
                             if app.track_loaded {
-                                if !app.paused {
-                                    app.toggle_pause();
-                                }
-                                app.seek_to(Duration::ZERO);
+                                app.stop();
                             }
                         }
                         KeyCode::Media(MediaKeyCode::TrackNext) => {
@@ -1493,10 +3390,7 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 }
                                 KeyCode::Backspace => {
                                     app.browser_search.pop();
-                                    app.browser_filtered = file_browser::filter_files(
-                                        &app.browser_items,
-                                        &app.browser_search,
-                                    );
+                                    app.refresh_search();
                                     if app.browser_filter_idx >= app.browser_filtered.len() {
                                         app.browser_filter_idx =
                                             app.browser_filtered.len().saturating_sub(1);
@@ -1514,7 +3408,7 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 }
                                 KeyCode::Enter => {
                                     if let Some(path) =
-                                        app.browser_filtered.get(app.browser_filter_idx).cloned()
+                                        app.browser_filtered.get(app.browser_filter_idx).map(|h| h.path.clone())
                                     {
                                         app.switch_track(&path);
                                         app.browser_open = false;
@@ -1524,12 +3418,17 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                         app.browser_filter_idx = 0;
                                     }
                                 }
+                                KeyCode::Tab => {
+                                    if let Some(path) =
+                                        app.browser_filtered.get(app.browser_filter_idx).map(|h| h.path.clone())
+                                    {
+                                        app.queue.append(path);
+                                        app.refresh_queued_track();
+                                    }
+                                }
                                 KeyCode::Char(c) => {
                                     app.browser_search.push(c);
-                                    app.browser_filtered = file_browser::filter_files(
-                                        &app.browser_items,
-                                        &app.browser_search,
-                                    );
+                                    app.refresh_search();
                                     app.browser_filter_idx = 0;
                                 }
                                 _ => {}
@@ -1550,8 +3449,20 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                     app.browser_state.key_right();
                                 }
                                 KeyCode::Enter => {
-                                    if let Some(path) =
-                                        file_browser::selected_file(&app.browser_state)
+                                    let album = app
+                                        .browser_state
+                                        .selected()
+                                        .last()
+                                        .is_some_and(|id| browse::is_album_node(id));
+                                    let album_start = file_browser::selected_files(
+                                        &app.browser_state,
+                                        &app.browser_items,
+                                    )
+                                    .into_iter()
+                                    .next()
+                                    .filter(|_| album);
+                                    if let Some(path) = file_browser::selected_file(&app.browser_state)
+                                        .or(album_start)
                                     {
                                         app.switch_track(&path);
                                         app.browser_open = false;
@@ -1559,13 +3470,27 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                         app.browser_state.toggle_selected();
                                     }
                                 }
+                                KeyCode::Tab => app.cycle_browse_mode(),
+                                KeyCode::Char('a') => {
+                                    for path in file_browser::selected_files(
+                                        &app.browser_state,
+                                        &app.browser_items,
+                                    ) {
+                                        app.queue.append(path);
+                                    }
+                                    app.refresh_queued_track();
+                                }
+                                KeyCode::Char('p') => {
+                                    app.queue.insert_next(file_browser::selected_files(
+                                        &app.browser_state,
+                                        &app.browser_items,
+                                    ));
+                                    app.refresh_queued_track();
+                                }
                                 KeyCode::Char('/') => {
                                     app.browser_searching = true;
                                     app.browser_search.clear();
-                                    app.browser_filtered = file_browser::filter_files(
-                                        &app.browser_items,
-                                        "",
-                                    );
+                                    app.refresh_search();
                                     app.browser_filter_idx = 0;
                                 }
                                 KeyCode::Esc | KeyCode::Char('f') => {
@@ -1576,6 +3501,199 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 _ => {}
                             }
                         }
+                    } else if app.queue_open {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.queue_selected = app.queue_selected.saturating_sub(1);
+                            }
+                            KeyCode::Down if !app.queue.is_empty() => {
+                                app.queue_selected =
+                                    (app.queue_selected + 1).min(app.queue.len() - 1);
+                            }
+                            KeyCode::Char('K') => {
+                                app.queue_selected = app.queue.move_up(app.queue_selected);
+                                app.refresh_queued_track();
+                            }
+                            KeyCode::Char('J') => {
+                                app.queue_selected = app.queue.move_down(app.queue_selected);
+                                app.refresh_queued_track();
+                            }
+                            KeyCode::Char('d') | KeyCode::Delete => {
+                                app.queue.remove(app.queue_selected);
+                                app.queue_selected =
+                                    app.queue_selected.min(app.queue.len().saturating_sub(1));
+                                app.refresh_queued_track();
+                            }
+                            KeyCode::Char('c') => {
+                                app.queue.clear();
+                                app.queue_selected = 0;
+                                app.refresh_queued_track();
+                            }
+                            KeyCode::Enter => {
+                                if let Some(path) = app.queue.remove(app.queue_selected) {
+                                    // Move to the head so switch_track consumes it like a
+                                    // normal queue advance
+                                    app.queue.insert_next(vec![path.clone()]);
+                                    app.switch_track(&path);
+                                    app.queue_open = false;
+                                }
+                            }
+                            KeyCode::Esc | KeyCode::Char('u') => {
+                                app.queue_open = false;
+                            }
+                            _ => {}
+                        }
+                    } else if let Some(picker) = app.eq_presets.as_mut() {
+                        if let Some((_, text)) = picker.prompt.as_mut() {
+                            match key.code {
+                                KeyCode::Enter => {
+                                    let params = app.eq_params.lock().unwrap().clone();
+                                    let result = picker.submit(&params);
+                                    let moved = picker.moved.take();
+                                    match result {
+                                        Ok(msg) | Err(msg) => app.set_notice(msg),
+                                    }
+                                    if let Some((from, to)) = moved {
+                                        app.follow_preset(&from, to);
+                                    }
+                                }
+                                KeyCode::Esc => picker.prompt = None,
+                                KeyCode::Backspace => {
+                                    text.pop();
+                                }
+                                KeyCode::Char(c) => text.push(c),
+                                _ => {}
+                            }
+                        } else {
+                            let mut notice = None;
+                            match key.code {
+                                KeyCode::Up => picker.select_prev(),
+                                KeyCode::Down => picker.select_next(),
+                                KeyCode::Enter => {
+                                    if let Ok(mut params) = app.eq_params.lock() {
+                                        eq_presets::apply(picker.selected(), &mut params);
+                                        eq::save_eq(&params);
+                                    }
+                                    // A preset picked by hand becomes the user's own EQ
+                                    app.eq_manual = None;
+                                    app.eq_rule = None;
+                                    notice = Some(format!("EQ preset: {}", picker.selected().name));
+                                    app.eq_presets = None;
+                                }
+                                KeyCode::Char(c @ ('t' | 'a' | 'g')) => {
+                                    let scope = match c {
+                                        't' => eq_rules::Scope::Path,
+                                        'a' => eq_rules::Scope::Album,
+                                        _ => eq_rules::Scope::Genre,
+                                    };
+                                    let preset = picker.selected().name.clone();
+                                    app.eq_presets = None;
+                                    app.bind_eq_rule(scope, preset);
+                                }
+                                KeyCode::Char('s') => {
+                                    notice = picker.start(eq_presets::Prompt::SaveAs).err();
+                                }
+                                KeyCode::Char('r') => {
+                                    notice = picker.start(eq_presets::Prompt::Rename).err();
+                                }
+                                KeyCode::Char('i') => {
+                                    notice = picker.start(eq_presets::Prompt::Import).err();
+                                }
+                                KeyCode::Char('x') => {
+                                    notice = picker.start(eq_presets::Prompt::Export).err();
+                                }
+                                KeyCode::Char('d') | KeyCode::Delete => {
+                                    notice = Some(match picker.delete_selected() {
+                                        Ok(msg) | Err(msg) => msg,
+                                    });
+                                    if let Some((from, to)) = picker.moved.take() {
+                                        app.follow_preset(&from, to);
+                                    }
+                                }
+                                KeyCode::Esc | KeyCode::Char('P') => app.eq_presets = None,
+                                _ => {}
+                            }
+                            if let Some(msg) = notice {
+                                app.set_notice(msg);
+                            }
+                        }
+                    } else if app.eq_open && app.eq_import.is_some() {
+                        let path = app.eq_import.as_mut().unwrap();
+                        match key.code {
+                            KeyCode::Enter => {
+                                let path = app.eq_import.take().unwrap_or_default();
+                                match eq::import_apo(&path) {
+                                    Ok(imported) => {
+                                        let count = imported.filters.len();
+                                        if let Ok(mut params) = app.eq_params.lock() {
+                                            params.parametric = imported;
+                                            params.mode = eq::EqMode::Parametric;
+                                            params.user_preset = None;
+                                        }
+                                        app.save_eq(true);
+                                        app.eq_param_row = 0;
+                                        app.set_notice(format!("Imported {count} EQ filters"));
+                                    }
+                                    Err(e) => app.set_notice(e),
+                                }
+                            }
+                            KeyCode::Esc => app.eq_import = None,
+                            KeyCode::Backspace => {
+                                path.pop();
+                            }
+                            KeyCode::Char(c) => path.push(c),
+                            _ => {}
+                        }
+                    } else if app.eq_open
+                        && app.eq_params.lock().unwrap().mode == eq::EqMode::Parametric
+                    {
+                        let mut params = app.eq_params.lock().unwrap();
+                        let before = params.clone();
+                        let rows = params.parametric.filters.len() + 1;
+                        let row = app.eq_param_row.min(rows - 1);
+                        let mut toggle_rules = false;
+                        match key.code {
+                            KeyCode::Up => app.eq_param_row = row.saturating_sub(1),
+                            KeyCode::Down => app.eq_param_row = (row + 1).min(rows - 1),
+                            KeyCode::Left => app.eq_param_field = app.eq_param_field.cycle(false),
+                            KeyCode::Right => app.eq_param_field = app.eq_param_field.cycle(true),
+                            KeyCode::Char(c @ ('+' | '=' | '-')) => {
+                                params.parametric.adjust(row, app.eq_param_field, c != '-');
+                            }
+                            KeyCode::Char('a') => {
+                                app.eq_param_row = params.parametric.add_filter(row);
+                            }
+                            KeyCode::Char('d') | KeyCode::Delete => {
+                                params.parametric.remove_filter(row);
+                                app.eq_param_row = row.min(params.parametric.filters.len());
+                            }
+                            KeyCode::Char('0') => {
+                                params.parametric = eq::ParametricEq::default();
+                                params.user_preset = None;
+                                app.eq_param_row = 0;
+                            }
+                            KeyCode::Char('s') => params.enabled = !params.enabled,
+                            KeyCode::Char('m') => params.mode = eq::EqMode::Graphic,
+                            KeyCode::Char('A') => params.auto_preamp = !params.auto_preamp,
+                            KeyCode::Char('l') => params.limiter = !params.limiter,
+                            KeyCode::Char('i') => app.eq_import = Some(String::new()),
+                            KeyCode::Char('P') => {
+                                app.eq_presets = Some(eq_presets::PresetPicker::open(&params));
+                            }
+                            KeyCode::Char('o') => toggle_rules = true,
+                            KeyCode::Esc | KeyCode::Char('e') => app.eq_open = false,
+                            _ => {}
+                        }
+                        let changed = *params != before;
+                        let curve_edited =
+                            params.mode != before.mode || params.parametric != before.parametric;
+                        drop(params);
+                        if changed {
+                            app.save_eq(curve_edited);
+                        }
+                        if toggle_rules {
+                            app.toggle_eq_rules();
+                        }
                     } else if app.eq_open {
                         match key.code {
                             KeyCode::Left => {
@@ -1590,42 +3708,137 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     let g = &mut params.gains[app.eq_selected_band];
                                     *g = (*g + 1.0).min(12.0);
-                                    eq::save_eq(&params);
                                 }
+                                app.save_eq(true);
                             }
                             KeyCode::Down => {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     let g = &mut params.gains[app.eq_selected_band];
                                     *g = (*g - 1.0).max(-12.0);
-                                    eq::save_eq(&params);
                                 }
+                                app.save_eq(true);
                             }
                             KeyCode::Char('p') => {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     params.preset_index =
                                         (params.preset_index + 1) % eq::PRESETS.len();
                                     params.gains = eq::PRESETS[params.preset_index].1;
-                                    eq::save_eq(&params);
+                                    params.user_preset = None;
                                 }
+                                app.save_eq(true);
                             }
                             KeyCode::Char('0') => {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     params.gains = [0.0; eq::NUM_BANDS];
                                     params.preset_index = 0;
-                                    eq::save_eq(&params);
+                                    params.user_preset = None;
                                 }
+                                app.save_eq(true);
                             }
                             KeyCode::Char('s') => {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     params.enabled = !params.enabled;
-                                    eq::save_eq(&params);
                                 }
+                                app.save_eq(false);
+                            }
+                            KeyCode::Char('m') => {
+                                if let Ok(mut params) = app.eq_params.lock() {
+                                    params.mode = eq::EqMode::Parametric;
+                                }
+                                app.save_eq(true);
+                            }
+                            KeyCode::Char('A') => {
+                                if let Ok(mut params) = app.eq_params.lock() {
+                                    params.auto_preamp = !params.auto_preamp;
+                                }
+                                app.save_eq(false);
+                            }
+                            KeyCode::Char('l') => {
+                                if let Ok(mut params) = app.eq_params.lock() {
+                                    params.limiter = !params.limiter;
+                                }
+                                app.save_eq(false);
                             }
+                            KeyCode::Char('P') => {
+                                let params = app.eq_params.lock().unwrap();
+                                app.eq_presets = Some(eq_presets::PresetPicker::open(&params));
+                            }
+                            KeyCode::Char('o') => app.toggle_eq_rules(),
                             KeyCode::Esc | KeyCode::Char('e') => {
                                 app.eq_open = false;
                             }
                             _ => {}
                         }
+                    } else if app.stereo_open {
+                        let mut params = *app.stereo_params.lock().unwrap();
+                        match key.code {
+                            KeyCode::Left => params.nudge_balance(-stereo::BALANCE_STEP),
+                            KeyCode::Right => params.nudge_balance(stereo::BALANCE_STEP),
+                            KeyCode::Char('c') => params.balance = 0.0,
+                            KeyCode::Char('m') => params.mono = !params.mono,
+                            KeyCode::Char('s') => params.swap = !params.swap,
+                            KeyCode::Char('l') => params.mute_left = !params.mute_left,
+                            KeyCode::Char('r') => params.mute_right = !params.mute_right,
+                            KeyCode::Esc | KeyCode::Char('b') => app.stereo_open = false,
+                            _ => {}
+                        }
+                        if let Ok(mut shared) = app.stereo_params.lock()
+                            && *shared != params
+                        {
+                            *shared = params;
+                            stereo::save_stereo(&params);
+                        }
+                    } else if app.sleep_open {
+                        match key.code {
+                            KeyCode::Up => app.sleep_menu.select_prev(),
+                            KeyCode::Down => app.sleep_menu.select_next(),
+                            KeyCode::Left => app.sleep_menu.adjust(false),
+                            KeyCode::Right => app.sleep_menu.adjust(true),
+                            KeyCode::Char('q') => app.sleep_menu.quit = !app.sleep_menu.quit,
+                            KeyCode::Enter => {
+                                let timer = app.sleep_menu.choice();
+                                app.set_sleep(timer);
+                                app.sleep_open = false;
+                            }
+                            KeyCode::Esc | KeyCode::Char('z') => app.sleep_open = false,
+                            _ => {}
+                        }
+                        sleep::save_sleep_menu(&app.sleep_menu);
+                    } else if let Some(picker) = app.lyrics_picker.as_mut() {
+                        if picker.editing {
+                            match key.code {
+                                KeyCode::Enter => {
+                                    picker.editing = false;
+                                    picker.search();
+                                }
+                                KeyCode::Esc => picker.editing = false,
+                                KeyCode::Backspace => {
+                                    picker.query.pop();
+                                }
+                                KeyCode::Char(c) => picker.query.push(c),
+                                _ => {}
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Up => picker.select_prev(),
+                                KeyCode::Down => picker.select_next(),
+                                KeyCode::Char('/') => picker.editing = true,
+                                KeyCode::Enter => {
+                                    if let Some(picker) = app.lyrics_picker.take() {
+                                        app.pick_lyrics(picker);
+                                    }
+                                }
+                                KeyCode::Char('d') => {
+                                    lyrics::unpin_lyrics(&picker.artist, &picker.title);
+                                    app.lyrics_picker = None;
+                                    if app.track_loaded {
+                                        app.refetch_lyrics();
+                                    }
+                                }
+                                KeyCode::Esc | KeyCode::Char('L') => app.lyrics_picker = None,
+                                _ => {}
+                            }
+                        }
                     } else if app.theme_open {
                         match key.code {
                             KeyCode::Up => {
@@ -1685,14 +3898,28 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                             KeyCode::Char('k') => {
                                 app.lyrics_scroll = app.lyrics_scroll.saturating_sub(1);
                             }
+                            KeyCode::Char('{') if app.track_loaded => app.nudge_lyrics_offset(-100),
+                            KeyCode::Char('}') if app.track_loaded => app.nudge_lyrics_offset(100),
                             KeyCode::Char('f') => {
                                 if app.root_dir.is_some() {
+                                    if app.browse_mode != browse::BrowseMode::Files {
+                                        app.rebuild_browser_view(); // pick up newly indexed tags
+                                    }
                                     app.browser_open = true;
                                 }
                             }
                             KeyCode::Char('e') => {
                                 app.eq_open = true;
                             }
+                            KeyCode::Char('b') => {
+                                app.stereo_open = true;
+                            }
+                            KeyCode::Char('z') => {
+                                app.sleep_open = true;
+                            }
+                            KeyCode::Char('u') if app.root_dir.is_some() => {
+                                app.queue_open = true;
+                            }
                             KeyCode::Char('n') => {
                                 if app.track_loaded {
                                     app.next_track();
@@ -1704,73 +3931,43 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 }
                             }
                             KeyCode::Char('r') => {
-                                app.repeat_mode = app.repeat_mode.next();
-                                save_repeat_mode(app.repeat_mode);
-                                // Re-queue next track based on new mode
-                                if app.track_loaded {
-                                    app.queued_track = None;
-                                    if let Some(cf) = app.crossfade.take() {
-                                        cf.sink.stop();
-                                    }
-                                    app.sink.stop();
-                                    let pos = app.position();
-                                    app.seek_to(pos);
-                                }
+                                app.set_repeat_mode(app.repeat_mode.next());
                             }
                             KeyCode::Char('s') => {
-                                app.shuffle = !app.shuffle;
-                                save_shuffle(app.shuffle);
-                                if app.shuffle {
-                                    app.regenerate_shuffle();
-                                } else {
-                                    app.shuffle_order.clear();
-                                }
-                                // Re-queue next track based on new mode
-                                if app.track_loaded {
-                                    app.queued_track = None;
-                                    if let Some(cf) = app.crossfade.take() {
-                                        cf.sink.stop();
-                                    }
-                                    app.sink.stop();
-                                    let pos = app.position();
-                                    app.seek_to(pos);
-                                }
+                                app.set_shuffle(!app.shuffle);
                             }
-                            KeyCode::Char('x') => {
-                                clear_cache();
-                                if app.track_loaded {
-                                    // Re-fetch lyrics and art for current track
-                                    app.lyrics = None;
-                                    app.lyrics_scroll = 0;
-                                    app.lyrics_loading = false;
-                                    app.lyrics_url.clear();
-                                    app.lyrics_rx = None;
-                                    app.album_art = None;
-                                    app.art_rx = None;
-                                    let lyrics_artist =
-                                        app.meta.artist.clone().unwrap_or_default();
-                                    let lyrics_title =
-                                        app.meta.title.clone().unwrap_or_else(|| {
-                                            app.file_path
-                                                .file_stem()
-                                                .map(|s| s.to_string_lossy().to_string())
-                                                .unwrap_or_default()
-                                        });
-                                    if !lyrics_title.is_empty() {
-                                        app.lyrics_rx = Some(spawn_lyrics_fetchers(
-                                            lyrics_artist,
-                                            lyrics_title,
-                                        ));
-                                        app.lyrics_loading = true;
-                                    }
+                            KeyCode::Char('<') => app.set_speed(app.playback_speed() - 0.05),
+                            KeyCode::Char('>') => app.set_speed(app.playback_speed() + 0.05),
+                            KeyCode::Char('=') => app.set_speed(1.0),
+                            KeyCode::Char('[') if app.track_loaded => app.set_loop_a(),
+                            KeyCode::Char(']') if app.track_loaded => app.set_loop_b(),
+                            KeyCode::Char('\\') if app.track_loaded => app.clear_loop(),
+                            KeyCode::Char('x') if app.track_loaded => {
+                                let (artist, title) = app.lyrics_tags();
+                                lyrics::invalidate_lyrics(&artist, &title);
+                                app.refetch_lyrics();
+                            }
+                            KeyCode::Char('X') if app.track_loaded => {
+                                if let Some(url) = app.art_url.take() {
+                                    cache::remove(cache::Kind::Art, &url);
+                                }
+                                app.start_art();
+                                // The network fallback URL comes with the lyrics
+                                let lyrics_art = app.lyrics.as_ref().and_then(|l| l.art_url.clone());
+                                if let Some(url) = lyrics_art {
+                                    app.offer_art_url(url);
                                 }
                             }
+                            KeyCode::Char('L') if app.track_loaded => app.open_lyrics_picker(),
                             KeyCode::Char('i') => {
                                 app.info_open = !app.info_open;
                             }
                             KeyCode::Char('t') => {
                                 app.theme_open = true;
                             }
+                            KeyCode::Char('w') => {
+                                app.export_playlist();
+                            }
                             KeyCode::Char('m') => {
                                 app.mini_mode = !app.mini_mode;
                                 save_mini_mode(app.mini_mode);
@@ -1793,11 +3990,16 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                     app.seek_to(pos);
                                 }
                             }
+                            KeyCode::Char('g') => app.cycle_replay_gain_mode(),
                             _ => {}
                         }
                     }
                 }
-                Event::Mouse(mouse) if app.eq_open => {
+                Event::Mouse(mouse)
+                    if app.eq_open
+                        && app.eq_presets.is_none()
+                        && app.eq_params.lock().unwrap().mode == eq::EqMode::Graphic =>
+                {
                     let col = mouse.column;
                     let row = mouse.row;
                     let inner = app.regions.eq_inner;
@@ -1838,8 +4040,8 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     let g = &mut params.gains[band];
                                     *g = (*g + 1.0).min(12.0);
-                                    eq::save_eq(&params);
                                 }
+                                app.save_eq(true);
                                 app.eq_selected_band = band;
                             }
                         }
@@ -1853,15 +4055,22 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
                                 if let Ok(mut params) = app.eq_params.lock() {
                                     let g = &mut params.gains[band];
                                     *g = (*g - 1.0).max(-12.0);
-                                    eq::save_eq(&params);
                                 }
+                                app.save_eq(true);
                                 app.eq_selected_band = band;
                             }
                         }
                         _ => {}
                     }
                 }
-                Event::Mouse(mouse) if !app.browser_open && !app.eq_open => {
+                Event::Mouse(mouse)
+                    if !app.browser_open
+                        && !app.eq_open
+                        && !app.queue_open
+                        && !app.stereo_open
+                        && !app.sleep_open
+                        && app.lyrics_picker.is_none() =>
+                {
                     let col = mouse.column;
                     let row = mouse.row;
                     match mouse.kind {
@@ -1986,6 +4195,7 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
             && app.track_loaded
             && !app.paused
             && app.crossfade.is_none()
+            && !app.loop_active()
         {
             if let Some(total) = app.total_duration {
                 if total.as_secs_f32() > app.crossfade_duration * 2.0 {
@@ -2002,8 +4212,9 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
             if let Some(total) = app.total_duration {
                 let remaining = total.saturating_sub(app.position()).as_secs_f32();
                 let progress = 1.0 - (remaining / app.crossfade_duration).clamp(0.0, 1.0);
-                app.sink.set_volume(app.volume * (1.0 - progress));
-                cf.sink.set_volume(app.volume * progress);
+                let gain = app.volume * app.sleep_gain();
+                app.sink.set_volume(gain * (1.0 - progress));
+                cf.sink.set_volume(gain * progress);
             }
         }
 
@@ -2024,6 +4235,16 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
             app.advance_to_queued();
         }
 
+        // Sleep timer: fade out over the last 30 seconds, then pause (and maybe quit)
+        if app.sleep.is_some() {
+            if app.crossfade.is_none() {
+                app.sink.set_volume(app.volume * app.sleep_gain());
+            }
+            if app.tick_sleep() {
+                break;
+            }
+        }
+
         // All sources exhausted (no queued track)
         if app.track_loaded && app.is_finished() && !app.paused && app.crossfade.is_none() {
             if app.root_dir.is_some() {
@@ -2105,10 +4326,12 @@ fn draw_track_info(frame: &mut Frame, app: &App, theme: &Theme) {
     }
 
     // ReplayGain
-    if app.normalize_gain != 1.0 {
-        let db = 20.0 * app.normalize_gain.log10();
-        lines.push(label("ReplayGain", &format!("{db:+.1} dB")));
-    }
+    let gain = f32::from_bits(app.normalize_gain.load(Ordering::Relaxed));
+    let rg = match app.rg_source {
+        replaygain::Source::Off => "off".to_string(),
+        source => format!("{:+.1} dB ({})", 20.0 * gain.log10(), source.label()),
+    };
+    lines.push(label("ReplayGain", &rg));
 
     // Tag metadata
     lines.push(ratatui::text::Line::raw(""));
@@ -2151,6 +4374,7 @@ fn draw_track_info(frame: &mut Frame, app: &App, theme: &Theme) {
 
 fn draw(frame: &mut Frame, app: &mut App) {
     let theme = app.theme();
+    app.regions.art = None;
     if !app.track_loaded {
         // Idle screen — no track playing yet
         let area = frame.area();
@@ -2163,7 +4387,7 @@ fn draw(frame: &mut Frame, app: &mut App) {
         frame.render_widget(msg, Rect::new(area.x, y, area.width, 1));
     } else if app.mini_mode {
         let track_pos = {
-            let files = file_browser::collect_audio_files(&app.browser_items);
+            let files = &app.browser_files;
             files
                 .iter()
                 .position(|f| f == &app.file_path)
@@ -2208,10 +4432,17 @@ fn draw(frame: &mut Frame, app: &mut App) {
             app.position(),
             app.total_duration,
             waveform_normalized.as_deref(),
+            &progress::PlaybackMarks {
+                loop_a: app.loop_a,
+                loop_b: app.loop_b,
+                speed: app.playback_speed(),
+                sleep: app.sleep.as_ref().map(|t| t.label()),
+                eq_rule: app.eq_rule.clone(),
+            },
             theme,
         );
 
-        volume::draw_volume(frame, chunks[2], app.volume, theme);
+        volume::draw_volume(frame, chunks[2], app.volume, app.clipping(), theme);
 
         // Hover time tooltip on progress bar top border
         if let (Some(hover_col), Some(total)) = (app.progress_hover_col, app.total_duration) {
@@ -2264,7 +4495,7 @@ fn draw(frame: &mut Frame, app: &mut App) {
         }
     } else {
         let track_pos = {
-            let files = file_browser::collect_audio_files(&app.browser_items);
+            let files = &app.browser_files;
             files
                 .iter()
                 .position(|f| f == &app.file_path)
@@ -2356,12 +4587,29 @@ fn draw(frame: &mut Frame, app: &mut App) {
 
             if let Some(va) = vis_area {
                 visualizer::draw_visualizer(frame, va, app.vis_mode, &app.samples, app.channels, theme);
-                if let Some(ref pixels) = app.album_art {
-                    draw_art_overlay(frame, va, pixels, 0.75);
+                match app.album_art {
+                    Some(_) if app.graphics.high_res() => {
+                        app.regions.art = now_playing::art_rect(va);
+                        if let Some(rect) = app.regions.art {
+                            graphics::reserve(frame, rect);
+                        }
+                    }
+                    Some(ref art) => draw_art_overlay(frame, va, &art.pixels, 0.75),
+                    None => {}
                 }
             }
 
-            if app.lyrics_visible {
+            let synced = app.lyrics.as_ref().filter(|l| l.synced.is_some());
+            if let (true, Some(lr)) = (app.lyrics_visible, synced) {
+                lyrics::draw_synced_lyrics(
+                    frame,
+                    lyrics_rect,
+                    lr,
+                    app.position(),
+                    app.lyrics_offset,
+                    theme,
+                );
+            } else if app.lyrics_visible {
                 lyrics::draw_lyrics(
                     frame,
                     lyrics_rect,
@@ -2395,6 +4643,13 @@ fn draw(frame: &mut Frame, app: &mut App) {
             app.position(),
             app.total_duration,
             waveform_normalized.as_deref(),
+            &progress::PlaybackMarks {
+                loop_a: app.loop_a,
+                loop_b: app.loop_b,
+                speed: app.playback_speed(),
+                sleep: app.sleep.as_ref().map(|t| t.label()),
+                eq_rule: app.eq_rule.clone(),
+            },
             theme,
         );
 
@@ -2423,7 +4678,7 @@ fn draw(frame: &mut Frame, app: &mut App) {
             }
         }
 
-        volume::draw_volume(frame, chunks[3], app.volume, theme);
+        volume::draw_volume(frame, chunks[3], app.volume, app.clipping(), theme);
 
         // Hover volume tooltip on volume bar top border
         if let Some(hover_col) = app.volume_hover_col {
@@ -2476,13 +4731,43 @@ fn draw(frame: &mut Frame, app: &mut App) {
             &app.browser_search,
             &app.browser_filtered,
             app.browser_filter_idx,
+            app.browse_mode.label(),
+            theme,
+        );
+    }
+    if app.queue_open {
+        queue::draw_queue(
+            frame,
+            &app.queue,
+            app.queue_selected,
             app.root_dir.as_deref(),
             theme,
         );
     }
     if app.eq_open {
         let params = app.eq_params.lock().unwrap();
-        app.regions.eq_inner = eq::draw_eq(frame, &params, app.eq_selected_band, app.eq_hover_band, theme);
+        app.regions.eq_inner = eq::draw_eq(
+            frame,
+            &params,
+            app.eq_selected_band,
+            app.eq_hover_band,
+            (app.eq_param_row, app.eq_param_field),
+            app.eq_import.as_deref(),
+            theme,
+        );
+        if let Some(picker) = &app.eq_presets {
+            eq_presets::draw_preset_picker(frame, picker, theme);
+        }
+    }
+    if app.stereo_open {
+        let params = *app.stereo_params.lock().unwrap();
+        stereo::draw_stereo(frame, &params, theme);
+    }
+    if app.sleep_open {
+        sleep::draw_sleep(frame, &app.sleep_menu, app.sleep.as_ref(), theme);
+    }
+    if let Some(ref picker) = app.lyrics_picker {
+        lyrics::draw_lyrics_picker(frame, picker, theme);
     }
     if app.theme_open {
         theme::draw_theme_selector(frame, app.theme_idx);
@@ -2490,4 +4775,85 @@ fn draw(frame: &mut Frame, app: &mut App) {
     if app.info_open && app.track_loaded {
         draw_track_info(frame, app, theme);
     }
+    if let Some((msg, _)) = app.notice.as_ref().filter(|(_, at)| at.elapsed() < NOTICE_TIMEOUT) {
+        let area = frame.area();
+        let rect = Rect::new(area.x, area.bottom().saturating_sub(1), area.width, 1);
+        frame.render_widget(ratatui::widgets::Clear, rect);
+        frame.render_widget(
+            Paragraph::new(Span::styled(format!(" {msg} "), Style::default().fg(theme.secondary))),
+            rect,
+        );
+    }
+    // Popups over the art hide the image until they close
+    if app.regions.art.is_some_and(|rect| graphics::covered(frame.buffer_mut(), rect)) {
+        app.regions.art = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source over `data` with every processing stage neutral, and its timeline.
+    fn piped(
+        channels: u16,
+        data: Vec<f32>,
+    ) -> (PipedSource<rodio::buffer::SamplesBuffer>, SharedTimeline) {
+        let eq = eq::EqParams { enabled: false, ..Default::default() };
+        let timeline: SharedTimeline = Arc::default();
+        let handles = DspHandles {
+            pipe_ready: Arc::default(),
+            samples: Arc::default(),
+            eq_params: Arc::new(Mutex::new(eq)),
+            speed: Arc::new(AtomicU32::new(100)),
+            stereo_params: Arc::default(),
+            clipped: Arc::default(),
+            volume: Arc::new(AtomicU32::new(1f32.to_bits())),
+            finished: Arc::default(),
+            gain: Arc::new(AtomicU32::new(1f32.to_bits())),
+            timeline: Arc::clone(&timeline),
+        };
+        let source = rodio::buffer::SamplesBuffer::new(channels, 1000, data);
+        (PipedSource::new(source, channels, 1000, handles), timeline)
+    }
+
+    fn set_loop(timeline: &SharedTimeline, a_ms: u64, b_ms: u64) {
+        *timeline.points.lock().unwrap() =
+            Some((Duration::from_millis(a_ms), Duration::from_millis(b_ms)));
+    }
+
+    #[test]
+    fn ab_loop_repeats_exact_samples() {
+        let (mut source, timeline) = piped(1, (0..100).map(|i| i as f32).collect());
+        set_loop(&timeline, 10, 20);
+        source.refresh_ab_loop();
+        let out: Vec<f32> = (&mut source).take(40).collect();
+        let expected: Vec<f32> = (0..20).chain(10..20).chain(10..20).map(|i| i as f32).collect();
+        assert_eq!(out, expected);
+        // Two wraps of 10 ms each
+        assert_eq!(timeline.rewound_us.load(Ordering::Relaxed), 20_000);
+    }
+
+    #[test]
+    fn ab_loop_wraps_on_frame_boundaries() {
+        let frames = (0..50).flat_map(|i| [i as f32, -(i as f32)]).collect();
+        let (mut source, timeline) = piped(2, frames);
+        set_loop(&timeline, 5, 8);
+        source.refresh_ab_loop();
+        let out: Vec<f32> = (&mut source).skip(16).take(12).collect();
+        let expected: Vec<f32> =
+            [5, 6, 7, 5, 6, 7].iter().flat_map(|&i| [i as f32, -i as f32]).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn ab_loop_past_the_end_loops_from_the_real_end() {
+        let (mut source, timeline) = piped(1, (0..10).map(|i| i as f32).collect());
+        set_loop(&timeline, 6, 500);
+        source.refresh_ab_loop();
+        let out: Vec<f32> = (&mut source).take(18).collect();
+        let expected: Vec<f32> =
+            (0..10).chain(6..10).chain(6..10).map(|i| i as f32).collect();
+        assert_eq!(out, expected);
+    }
 }
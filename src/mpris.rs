@@ -0,0 +1,435 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+};
+
+use crate::stretch;
+
+use zbus::{
+    blocking::{connection, Connection},
+    interface,
+    zvariant::{ObjectPath, Value},
+};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.tui_player";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_IFACE: &str = "org.mpris.MediaPlayer2.Player";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Requests coming in over D-Bus, applied to `App` by the main loop.
+pub enum MprisCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Relative seek in microseconds
+    Seek(i64),
+    /// Absolute seek in microseconds, only honoured if the track id still matches
+    SetPosition(String, i64),
+    SetVolume(f64),
+    SetRate(f64),
+    SetShuffle(bool),
+    SetLoopStatus(String),
+    OpenUri(String),
+    Quit,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct MprisTrack {
+    /// D-Bus object path identifying the track (`mpris:trackid`)
+    pub id: String,
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub length_us: Option<i64>,
+    pub art_url: Option<String>,
+}
+
+/// Snapshot of player state published over D-Bus.
+#[derive(Clone, PartialEq)]
+pub struct MprisState {
+    pub status: &'static str,
+    pub loop_status: &'static str,
+    pub shuffle: bool,
+    pub volume: f64,
+    pub rate: f64,
+    pub position_us: i64,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub track: Option<MprisTrack>,
+}
+
+impl Default for MprisState {
+    fn default() -> Self {
+        MprisState {
+            status: "Stopped",
+            loop_status: "None",
+            shuffle: false,
+            volume: 1.0,
+            rate: 1.0,
+            position_us: 0,
+            can_go_next: false,
+            can_go_previous: false,
+            track: None,
+        }
+    }
+}
+
+fn file_uri(path: &std::path::Path) -> String {
+    let mut out = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                out.push(b as char);
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn metadata(track: Option<&MprisTrack>) -> HashMap<String, Value<'static>> {
+    let mut map: HashMap<String, Value<'static>> = HashMap::new();
+    let track = match track {
+        Some(t) => t,
+        None => {
+            if let Ok(p) = ObjectPath::try_from(NO_TRACK) {
+                map.insert("mpris:trackid".into(), Value::from(p));
+            }
+            return map;
+        }
+    };
+    if let Ok(p) = ObjectPath::try_from(track.id.clone()) {
+        map.insert("mpris:trackid".into(), Value::from(p));
+    }
+    if let Some(len) = track.length_us {
+        map.insert("mpris:length".into(), Value::from(len));
+    }
+    if let Some(ref art) = track.art_url {
+        map.insert("mpris:artUrl".into(), Value::from(art.clone()));
+    }
+    map.insert("xesam:title".into(), Value::from(track.title.clone()));
+    map.insert("xesam:url".into(), Value::from(file_uri(&track.path)));
+    if let Some(ref artist) = track.artist {
+        map.insert("xesam:artist".into(), Value::from(vec![artist.clone()]));
+    }
+    if let Some(ref album) = track.album {
+        map.insert("xesam:album".into(), Value::from(album.clone()));
+    }
+    if let Some(ref genre) = track.genre {
+        map.insert("xesam:genre".into(), Value::from(vec![genre.clone()]));
+    }
+    if let Some(ref date) = track.date {
+        map.insert("xesam:contentCreated".into(), Value::from(date.clone()));
+    }
+    map
+}
+
+struct Root {
+    commands: mpsc::Sender<MprisCommand>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.commands.send(MprisCommand::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "tui-player".into()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["file".into()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        [
+            "audio/mpeg",
+            "audio/flac",
+            "audio/ogg",
+            "audio/wav",
+            "audio/aac",
+            "audio/mp4",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+}
+
+struct Player {
+    commands: mpsc::Sender<MprisCommand>,
+    state: Arc<Mutex<MprisState>>,
+}
+
+impl Player {
+    fn send(&self, cmd: MprisCommand) {
+        let _ = self.commands.send(cmd);
+    }
+
+    fn snapshot(&self) -> MprisState {
+        self.state.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(MprisCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(MprisCommand::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(MprisCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(MprisCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(MprisCommand::Stop);
+    }
+
+    fn play(&self) {
+        self.send(MprisCommand::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(MprisCommand::Seek(offset));
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        self.send(MprisCommand::SetPosition(track_id.to_string(), position));
+    }
+
+    fn open_uri(&self, uri: String) {
+        self.send(MprisCommand::OpenUri(uri));
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.snapshot().status.into()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> String {
+        self.snapshot().loop_status.into()
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&mut self, value: String) {
+        self.send(MprisCommand::SetLoopStatus(value));
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.snapshot().rate
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, value: f64) {
+        self.send(MprisCommand::SetRate(value));
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.snapshot().shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, value: bool) {
+        self.send(MprisCommand::SetShuffle(value));
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        metadata(self.snapshot().track.as_ref())
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.snapshot().volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, value: f64) {
+        self.send(MprisCommand::SetVolume(value));
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.snapshot().position_us
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        stretch::MIN_SPEED as f64
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        stretch::MAX_SPEED as f64
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.snapshot().can_go_next
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.snapshot().can_go_previous
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.snapshot().track.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.snapshot().track.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.snapshot().track.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// MPRIS2 server on the session bus. Incoming calls are queued on `commands`;
+/// the main loop publishes state with `update`.
+pub struct MprisServer {
+    conn: Connection,
+    state: Arc<Mutex<MprisState>>,
+    pub commands: mpsc::Receiver<MprisCommand>,
+}
+
+impl MprisServer {
+    /// Connect to the session bus and claim the player name. Returns None when no
+    /// session bus is available.
+    pub fn start() -> Option<Self> {
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(MprisState::default()));
+        let conn = connection::Builder::session()
+            .ok()?
+            .serve_at(OBJECT_PATH, Root { commands: tx.clone() })
+            .ok()?
+            .serve_at(
+                OBJECT_PATH,
+                Player {
+                    commands: tx,
+                    state: Arc::clone(&state),
+                },
+            )
+            .ok()?
+            .build()
+            .ok()?;
+        // A second instance falls back to a unique name, as the spec suggests
+        if conn.request_name(BUS_NAME).is_err() {
+            let unique = format!("{BUS_NAME}.instance{}", std::process::id());
+            conn.request_name(unique).ok()?;
+        }
+        Some(MprisServer {
+            conn,
+            state,
+            commands: rx,
+        })
+    }
+
+    /// Store a new snapshot and emit PropertiesChanged for whatever differs from
+    /// the previous one. Position is polled by clients and never signalled.
+    pub fn update(&self, new: MprisState) {
+        let old = match self.state.lock() {
+            Ok(mut s) => std::mem::replace(&mut *s, new.clone()),
+            Err(_) => return,
+        };
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if old.status != new.status {
+            changed.insert("PlaybackStatus", Value::from(new.status));
+        }
+        if old.loop_status != new.loop_status {
+            changed.insert("LoopStatus", Value::from(new.loop_status));
+        }
+        if old.shuffle != new.shuffle {
+            changed.insert("Shuffle", Value::from(new.shuffle));
+        }
+        if old.volume != new.volume {
+            changed.insert("Volume", Value::from(new.volume));
+        }
+        if old.rate != new.rate {
+            changed.insert("Rate", Value::from(new.rate));
+        }
+        if old.can_go_next != new.can_go_next {
+            changed.insert("CanGoNext", Value::from(new.can_go_next));
+        }
+        if old.can_go_previous != new.can_go_previous {
+            changed.insert("CanGoPrevious", Value::from(new.can_go_previous));
+        }
+        if old.track != new.track {
+            changed.insert("Metadata", Value::from(metadata(new.track.as_ref())));
+            if old.track.is_none() || new.track.is_none() {
+                let loaded = new.track.is_some();
+                changed.insert("CanPlay", Value::from(loaded));
+                changed.insert("CanPause", Value::from(loaded));
+                changed.insert("CanSeek", Value::from(loaded));
+            }
+        }
+        if changed.is_empty() {
+            return;
+        }
+        let _ = self.conn.emit_signal(
+            None::<&str>,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(PLAYER_IFACE, changed, Vec::<&str>::new()),
+        );
+    }
+
+    /// Tell clients the position jumped (seek or loop), so they can resync.
+    pub fn emit_seeked(&self, position_us: i64) {
+        if let Ok(mut s) = self.state.lock() {
+            s.position_us = position_us;
+        }
+        let _ = self
+            .conn
+            .emit_signal(None::<&str>, OBJECT_PATH, PLAYER_IFACE, "Seeked", &(position_us,));
+    }
+}
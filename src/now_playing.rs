@@ -1,6 +1,21 @@
-use std::{fs, path::PathBuf, sync::mpsc, thread};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, StandardVisualKey, Visual},
+    probe::Hint,
+};
 
-use crate::{cache_hash, config_dir};
+use crate::cache::{self, Kind};
 
 use ratatui::{
     buffer::Buffer,
@@ -20,26 +35,45 @@ pub const ART_COLS: u16 = ART_ROWS * 2; // 2 cols per row for square aspect
 // Album art pixel grid: rows of (R, G, B) tuples
 pub type ArtPixels = Vec<Vec<(u8, u8, u8)>>;
 
-fn art_cache_path(url: &str) -> PathBuf {
-    config_dir()
-        .join("cache")
-        .join("art")
-        .join(cache_hash(url))
+/// Longest side kept of the source image for terminal graphics protocols.
+const ART_MAX_PX: u32 = 800;
+
+static NEXT_ART_ID: AtomicU32 = AtomicU32::new(1);
+
+pub struct AlbumArt {
+    /// Unique per loaded image, so the graphics renderer notices a change
+    pub id: u32,
+    /// Half-block rendering
+    pub pixels: ArtPixels,
+    /// Full-resolution rendering (kitty, sixel, iTerm2)
+    pub image: image::RgbImage,
 }
 
-pub fn fetch_album_art(url: &str, cols: u16, rows: u16) -> Option<ArtPixels> {
-    let cache_path = art_cache_path(url);
-    let bytes = if let Ok(cached) = fs::read(&cache_path) {
-        cached
-    } else {
-        let downloaded = ureq::get(url).call().ok()?.body_mut().read_to_vec().ok()?;
-        if let Some(parent) = cache_path.parent() {
-            let _ = fs::create_dir_all(parent);
-        }
-        let _ = fs::write(&cache_path, &downloaded);
-        downloaded
+pub fn fetch_album_art(url: &str, cols: u16, rows: u16) -> Option<AlbumArt> {
+    let bytes = match cache::get(Kind::Art, url) {
+        Some(cached) => cached,
+        None if cache::is_negative(Kind::Art, url) => return None,
+        None => match ureq::get(url).call().and_then(|mut r| r.body_mut().read_to_vec()) {
+            Ok(downloaded) => {
+                cache::put(Kind::Art, url, &downloaded);
+                downloaded
+            }
+            // Only a server saying there is no such image is remembered; network
+            // trouble is retried next time
+            Err(ureq::Error::StatusCode(404)) => {
+                cache::put_negative(Kind::Art, url);
+                return None;
+            }
+            Err(_) => return None,
+        },
     };
-    let img = image::load_from_memory(&bytes).ok()?;
+    load_art(&bytes, cols, rows)
+}
+
+/// Decode an image, scale it to `cols` × `rows` half-block cells and keep a
+/// size-capped copy for graphics protocols.
+fn load_art(bytes: &[u8], cols: u16, rows: u16) -> Option<AlbumArt> {
+    let img = image::load_from_memory(bytes).ok()?;
     let px_w = cols as u32;
     let px_h = (rows as u32) * 2; // half-block = 2 pixels per row
     let resized = img.resize_exact(px_w, px_h, image::imageops::FilterType::Lanczos3);
@@ -53,14 +87,87 @@ pub fn fetch_album_art(url: &str, cols: u16, rows: u16) -> Option<ArtPixels> {
         }
         pixels.push(row);
     }
-    Some(pixels)
+    Some(AlbumArt {
+        id: NEXT_ART_ID.fetch_add(1, Ordering::Relaxed),
+        pixels,
+        image: img.thumbnail(ART_MAX_PX, ART_MAX_PX).to_rgb8(),
+    })
+}
+
+/// Picture embedded in the file's tags (ID3 APIC, FLAC PICTURE, MP4 `covr`, Vorbis
+/// METADATA_BLOCK_PICTURE), preferring the front cover.
+fn embedded_art(path: &Path) -> Option<Vec<u8>> {
+    let file = fs::File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+
+    // ID3v2 tags are read by the probe, container tags by the format reader
+    let mut visuals: Vec<Visual> = Vec::new();
+    if let Some(rev) = probed.metadata.get().and_then(|mut m| m.skip_to_latest().cloned()) {
+        visuals.extend(rev.visuals().iter().cloned());
+    }
+    if let Some(rev) = probed.format.metadata().skip_to_latest() {
+        visuals.extend(rev.visuals().iter().cloned());
+    }
+    let is_icon = |v: &Visual| {
+        matches!(v.usage, Some(StandardVisualKey::FileIcon | StandardVisualKey::OtherIcon))
+    };
+    let pick = visuals
+        .iter()
+        .position(|v| v.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.iter().position(|v| !is_icon(v)))?;
+    Some(visuals.swap_remove(pick).data.into_vec())
 }
 
-pub fn spawn_art_fetch(url: String, cols: u16, rows: u16) -> mpsc::Receiver<ArtPixels> {
+/// Cover image names looked for next to the track, most specific first.
+const COVER_NAMES: &[&str] = &["cover", "folder", "front"];
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif", "bmp"];
+
+/// `cover.jpg`, `folder.png`, `front.*` and the like in `dir` (case-insensitive).
+fn folder_art(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+            let stem = path.file_stem()?.to_str()?.to_ascii_lowercase();
+            let rank = COVER_NAMES.iter().position(|n| *n == stem)?;
+            IMAGE_EXTENSIONS.contains(&ext.as_str()).then_some((rank, path))
+        })
+        .min()
+        .map(|(_, path)| path)
+}
+
+/// Look for art without the network: embedded in the file first, then an image in
+/// its directory. The channel disconnects without a value when neither exists.
+pub fn spawn_local_art(path: PathBuf, cols: u16, rows: u16) -> mpsc::Receiver<AlbumArt> {
     let (tx, rx) = mpsc::channel();
     thread::spawn(move || {
-        if let Some(pixels) = fetch_album_art(&url, cols, rows) {
-            let _ = tx.send(pixels);
+        let art = embedded_art(&path)
+            .and_then(|bytes| load_art(&bytes, cols, rows))
+            .or_else(|| {
+                let image = folder_art(path.parent()?)?;
+                load_art(&fs::read(image).ok()?, cols, rows)
+            });
+        if let Some(art) = art {
+            let _ = tx.send(art);
+        }
+    });
+    rx
+}
+
+pub fn spawn_art_fetch(url: String, cols: u16, rows: u16) -> mpsc::Receiver<AlbumArt> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Some(art) = fetch_album_art(&url, cols, rows) {
+            let _ = tx.send(art);
         }
     });
     rx
@@ -182,20 +289,24 @@ pub fn now_playing_height(
     (line1_rows + line2_rows) as u16 + 2
 }
 
-/// Render album art as a small overlay on the top-left corner of a given area,
-/// inset by 1 cell to sit inside the visualizer border.
-pub fn draw_art_overlay(frame: &mut Frame, area: Rect, pixels: &ArtPixels, opacity: f32) {
-    let inner_x = area.x + 1;
-    let inner_y = area.y + 1;
+/// Where album art goes in `area`: the top-left corner, inset by 1 cell to sit
+/// inside the visualizer border. None when there is no room.
+pub fn art_rect(area: Rect) -> Option<Rect> {
     let inner_w = area.width.saturating_sub(2);
     let inner_h = area.height.saturating_sub(2);
     let art_w = ART_COLS.min(inner_w);
     let art_h = ART_ROWS.min(inner_h);
     if art_w == 0 || art_h == 0 {
-        return;
+        return None;
+    }
+    Some(Rect::new(area.x + 1, area.y + 1, art_w, art_h))
+}
+
+/// Render album art as a small half-block overlay at `art_rect(area)`.
+pub fn draw_art_overlay(frame: &mut Frame, area: Rect, pixels: &ArtPixels, opacity: f32) {
+    if let Some(rect) = art_rect(area) {
+        frame.render_widget(AlbumArtWidget::new(pixels, opacity), rect);
     }
-    let art_rect = Rect::new(inner_x, inner_y, art_w, art_h);
-    frame.render_widget(AlbumArtWidget::new(pixels, opacity), art_rect);
 }
 
 /// Draw the compact horizontal Now Playing bar (used when there's no album art).
@@ -279,3 +390,4 @@ pub fn draw_now_playing_mini(
     let line = Line::from(spans);
     frame.render_widget(Paragraph::new(line), area);
 }
+
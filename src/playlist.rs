@@ -0,0 +1,327 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tui_tree_widget::TreeItem;
+
+use crate::file_browser::is_audio_file;
+
+pub const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls"];
+
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| PLAYLIST_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Decode `%XX` escapes in a `file://` URI path.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok())
+        } else {
+            None
+        };
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Resolve a playlist location (absolute, relative to the playlist, or `file://`) to a path.
+/// Remote URLs are not playable and return None.
+fn resolve_location(base: &Path, location: &str) -> Option<PathBuf> {
+    let location = location.trim();
+    if location.is_empty() {
+        return None;
+    }
+    if let Some(rest) = location.strip_prefix("file://") {
+        // file:///abs/path or file://localhost/abs/path
+        let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+        return Some(PathBuf::from(percent_decode(rest)));
+    }
+    if location.contains("://") {
+        return None;
+    }
+    let path = PathBuf::from(location);
+    let resolved = if path.is_absolute() { path } else { base.join(path) };
+    if !resolved.exists() && location.contains('\\') {
+        // Playlists written on Windows use backslash separators
+        let unixy = PathBuf::from(location.replace('\\', "/"));
+        return Some(if unixy.is_absolute() { unixy } else { base.join(unixy) });
+    }
+    Some(resolved)
+}
+
+fn parse_m3u(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut pending_title: Option<String> = None;
+    let mut pending_duration: Option<Duration> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<seconds>[ attributes],<display title>
+            let (head, title) = info.split_once(',').unwrap_or((info, ""));
+            let secs = head
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse::<f64>().ok())
+                .filter(|s| s.is_finite() && *s > 0.0);
+            pending_duration = secs.and_then(|s| Duration::try_from_secs_f64(s).ok());
+            let title = title.trim();
+            pending_title = if title.is_empty() { None } else { Some(title.to_string()) };
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let title = pending_title.take();
+        let duration = pending_duration.take();
+        if let Some(path) = resolve_location(base, line) {
+            entries.push(PlaylistEntry { path, title, duration });
+        }
+    }
+    entries
+}
+
+fn parse_pls(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    // Entries are numbered (File1, Title1, Length1) and may appear in any order
+    let mut numbered: Vec<(u32, PlaylistEntry)> = Vec::new();
+    fn slot(numbered: &mut Vec<(u32, PlaylistEntry)>, n: u32) -> &mut PlaylistEntry {
+        let idx = match numbered.iter().position(|(i, _)| *i == n) {
+            Some(idx) => idx,
+            None => {
+                numbered.push((n, PlaylistEntry { path: PathBuf::new(), title: None, duration: None }));
+                numbered.len() - 1
+            }
+        };
+        &mut numbered[idx].1
+    }
+    for line in content.lines() {
+        let (key, value) = match line.trim().split_once('=') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (field, num) = match key.find(|c: char| c.is_ascii_digit()) {
+            Some(i) => (&key[..i], key[i..].parse::<u32>().ok()),
+            None => continue,
+        };
+        let n = match num {
+            Some(n) => n,
+            None => continue,
+        };
+        match field {
+            "file" => {
+                if let Some(path) = resolve_location(base, value) {
+                    slot(&mut numbered, n).path = path;
+                }
+            }
+            "title" if !value.is_empty() => {
+                slot(&mut numbered, n).title = Some(value.to_string());
+            }
+            "length" => {
+                slot(&mut numbered, n).duration = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s > 0.0)
+                    .and_then(|s| Duration::try_from_secs_f64(s).ok());
+            }
+            _ => {}
+        }
+    }
+    numbered.sort_by_key(|(n, _)| *n);
+    numbered
+        .into_iter()
+        .map(|(_, e)| e)
+        .filter(|e| !e.path.as_os_str().is_empty())
+        .collect()
+}
+
+/// Load an M3U/M3U8 or PLS playlist. Entries that don't exist on disk or aren't
+/// playable audio files are skipped.
+pub fn load_playlist(path: &Path) -> io::Result<Vec<PlaylistEntry>> {
+    let bytes = fs::read(path)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let content = content.trim_start_matches('\u{feff}');
+    let base = path.parent().unwrap_or(Path::new("."));
+    let is_pls = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pls"));
+    let entries = if is_pls {
+        parse_pls(content, base)
+    } else {
+        parse_m3u(content, base)
+    };
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.path.is_file() && is_audio_file(&e.path))
+        .collect())
+}
+
+fn format_extinf_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Build a flat browser tree in playlist order. Tree identifiers must be unique, so
+/// repeated entries keep only their first occurrence.
+pub fn tree_items(entries: &[PlaylistEntry]) -> Vec<TreeItem<'static, PathBuf>> {
+    let mut seen = HashSet::new();
+    entries
+        .iter()
+        .filter(|e| seen.insert(e.path.clone()))
+        .map(|e| {
+            let name = e.title.clone().unwrap_or_else(|| {
+                e.path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+            let text = match e.duration {
+                Some(d) => format!("{name}  ({})", format_extinf_duration(d)),
+                None => name,
+            };
+            TreeItem::new_leaf(e.path.clone(), text)
+        })
+        .collect()
+}
+
+/// Write entries as an extended M3U8 playlist. Paths below the playlist's own
+/// directory are written relative so the playlist stays portable.
+pub fn save_m3u8(path: &Path, entries: &[PlaylistEntry]) -> io::Result<()> {
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut out = String::from("#EXTM3U\n");
+    for e in entries {
+        let secs = e.duration.map(|d| d.as_secs() as i64).unwrap_or(-1);
+        let title = e.title.clone().unwrap_or_else(|| {
+            e.path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        out.push_str(&format!("#EXTINF:{secs},{title}\n"));
+        let location = e.path.strip_prefix(base).unwrap_or(&e.path);
+        out.push_str(&location.to_string_lossy());
+        out.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_extinf_and_relative_paths() {
+        let content = "#EXTM3U\n\
+            #EXTINF:215,Artist - Song\n\
+            music/song.flac\n\
+            \n\
+            # a comment\n\
+            /abs/other.mp3\n\
+            #EXTINF:-1,\n\
+            file:///abs/with%20space.ogg\n\
+            http://example.com/stream.mp3\n";
+        let entries = parse_m3u(content, Path::new("/lists"));
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("/lists/music/song.flac"),
+                Path::new("/abs/other.mp3"),
+                Path::new("/abs/with space.ogg"),
+            ]
+        );
+        assert_eq!(entries[0].title.as_deref(), Some("Artist - Song"));
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(215)));
+        // #EXTINF applies only to the next location
+        assert_eq!(entries[1].title, None);
+        assert_eq!(entries[1].duration, None);
+        // -1 means unknown, and an empty title is no title
+        assert_eq!(entries[2].title, None);
+        assert_eq!(entries[2].duration, None);
+    }
+
+    #[test]
+    fn m3u_extinf_attributes() {
+        let content = "#EXTINF:61 tvg-id=\"x\",Name, with comma\n/a.mp3\n";
+        let entries = parse_m3u(content, Path::new("/"));
+        assert_eq!(entries[0].title.as_deref(), Some("Name, with comma"));
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn m3u_bad_durations_are_unknown() {
+        let content = "#EXTINF:inf,A\n/a.mp3\n#EXTINF:1e30,B\n/b.mp3\n#EXTINF:NaN,C\n/c.mp3\n";
+        let entries = parse_m3u(content, Path::new("/"));
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.duration.is_none()));
+        assert_eq!(entries[1].title.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn pls_numbered_entries() {
+        let content = "[playlist]\n\
+            File2=/abs/b.mp3\n\
+            Title2=Second\n\
+            file1 = one/a.flac\n\
+            Length1=30\n\
+            Length2=-1\n\
+            Title3=No file\n\
+            NumberOfEntries=3\n\
+            Version=2\n";
+        let entries = parse_pls(content, Path::new("/lists"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, Path::new("/lists/one/a.flac"));
+        assert_eq!(entries[0].title, None);
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(30)));
+        assert_eq!(entries[1].path, Path::new("/abs/b.mp3"));
+        assert_eq!(entries[1].title.as_deref(), Some("Second"));
+        assert_eq!(entries[1].duration, None);
+    }
+
+    #[test]
+    fn pls_bad_durations_are_unknown() {
+        let content = "File1=/a.mp3\nLength1=inf\nFile2=/b.mp3\nLength2=1e30\n";
+        let entries = parse_pls(content, Path::new("/"));
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.duration.is_none()));
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%20b%2Fc"), "a b/c");
+        // Malformed escapes are kept as written
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+}
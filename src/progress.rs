@@ -10,6 +10,15 @@ use ratatui::{
 use crate::gauge::RoundedGauge;
 use crate::theme::Theme;
 
+/// A-B loop points, playback speed, sleep timer and EQ rule, shown on the progress bar.
+pub struct PlaybackMarks {
+    pub loop_a: Option<Duration>,
+    pub loop_b: Option<Duration>,
+    pub speed: f32,
+    pub sleep: Option<String>,
+    pub eq_rule: Option<String>,
+}
+
 fn format_duration(d: Duration) -> String {
     let secs = d.as_secs();
     format!("{}:{:02}", secs / 60, secs % 60)
@@ -21,6 +30,7 @@ pub fn draw_progress(
     elapsed: Duration,
     total: Option<Duration>,
     waveform: Option<&[f32]>,
+    marks: &PlaybackMarks,
     theme: &Theme,
 ) {
     let progress_label = match total {
@@ -39,10 +49,23 @@ pub fn draw_progress(
         })
         .unwrap_or(0.0);
 
+    let mut title = String::from(" Progress ");
+    if (marks.speed - 1.0).abs() > 0.001 {
+        title.push_str(&format!("· {:.2}× ", marks.speed));
+    }
+    if let (Some(a), Some(b)) = (marks.loop_a, marks.loop_b) {
+        title.push_str(&format!("· Loop {} – {} ", format_duration(a), format_duration(b)));
+    }
+    if let Some(ref sleep) = marks.sleep {
+        title.push_str(&format!("· {sleep} "));
+    }
+    if let Some(ref rule) = marks.eq_rule {
+        title.push_str(&format!("· EQ {rule} "));
+    }
     let block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
-        .title(" Progress ")
+        .title(title)
         .title(Line::from(format!(" {progress_label} ")).alignment(Alignment::Right));
 
     let mut gauge = RoundedGauge::new(ratio, String::new(), theme.accent)
@@ -51,5 +74,14 @@ pub fn draw_progress(
     if let Some(wf) = waveform {
         gauge = gauge.waveform(wf);
     }
+    if let Some(t) = total.filter(|t| !t.is_zero()) {
+        let ratio_of = |d: Duration| d.as_secs_f64() / t.as_secs_f64();
+        if let Some(a) = marks.loop_a {
+            gauge = gauge.marker(ratio_of(a), '[', theme.secondary);
+        }
+        if let Some(b) = marks.loop_b {
+            gauge = gauge.marker(ratio_of(b), ']', theme.secondary);
+        }
+    }
     frame.render_widget(gauge, area);
 }
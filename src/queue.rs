@@ -0,0 +1,164 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::file_browser;
+use crate::theme::Theme;
+
+/// User-curated play queue, consumed before falling back to directory order.
+#[derive(Default)]
+pub struct PlayQueue {
+    items: VecDeque<PathBuf>,
+    /// Track that was playing when the queue took over, so directory order can
+    /// resume from there once the queue is drained. Only set while queued tracks play.
+    pub anchor: Option<PathBuf>,
+}
+
+impl PlayQueue {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn front(&self) -> Option<&PathBuf> {
+        self.items.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<PathBuf> {
+        self.items.pop_front()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PathBuf> {
+        self.items.iter()
+    }
+
+    pub fn append(&mut self, path: PathBuf) {
+        self.items.push_back(path);
+    }
+
+    /// Insert a batch of tracks right after the currently playing one, keeping their order.
+    pub fn insert_next(&mut self, paths: Vec<PathBuf>) {
+        for path in paths.into_iter().rev() {
+            self.items.push_front(path);
+        }
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Option<PathBuf> {
+        self.items.remove(idx)
+    }
+
+    /// Move the item at `idx` one slot towards the front. Returns its new index.
+    pub fn move_up(&mut self, idx: usize) -> usize {
+        if idx > 0 && idx < self.items.len() {
+            self.items.swap(idx, idx - 1);
+            idx - 1
+        } else {
+            idx
+        }
+    }
+
+    /// Move the item at `idx` one slot towards the back. Returns its new index.
+    pub fn move_down(&mut self, idx: usize) -> usize {
+        if idx + 1 < self.items.len() {
+            self.items.swap(idx, idx + 1);
+            idx + 1
+        } else {
+            idx
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Follow a file or directory rename on disk.
+    pub fn rename_under(&mut self, from: &Path, to: &Path) {
+        for path in self.items.iter_mut().chain(self.anchor.as_mut()) {
+            *path = file_browser::renamed(path, from, to);
+        }
+    }
+
+    /// Drop tracks that were deleted from disk, alone or with their directory.
+    pub fn remove_under(&mut self, path: &Path) {
+        self.items.retain(|p| !p.starts_with(path));
+        if self.anchor.as_ref().is_some_and(|a| a.starts_with(path)) {
+            self.anchor = None;
+        }
+    }
+}
+
+fn popup_area(frame: &Frame) -> Rect {
+    let area = frame.area();
+    let popup_width = (area.width * 70 / 100).max(40).min(area.width);
+    let popup_height = (area.height * 70 / 100).max(10).min(area.height);
+    let popup_x = area.width.saturating_sub(popup_width) / 2;
+    let popup_y = area.height.saturating_sub(popup_height) / 2;
+    Rect::new(popup_x, popup_y, popup_width, popup_height)
+}
+
+pub fn draw_queue(
+    frame: &mut Frame,
+    queue: &PlayQueue,
+    selected: usize,
+    root_dir: Option<&Path>,
+    theme: &Theme,
+) {
+    let popup = popup_area(frame);
+    frame.render_widget(Clear, popup);
+
+    let list_items: Vec<ListItem> = if queue.is_empty() {
+        vec![ListItem::new(Line::from(Span::styled(
+            "Queue is empty — press a in the file browser to add tracks",
+            Style::default().fg(theme.dimmed),
+        )))]
+    } else {
+        queue
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let display = root_dir
+                    .and_then(|r| p.strip_prefix(r).ok())
+                    .map(|rel| rel.to_string_lossy().to_string())
+                    .unwrap_or_else(|| p.display().to_string());
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>3}  ", i + 1), Style::default().fg(theme.dimmed)),
+                    Span::raw(display),
+                ]))
+            })
+            .collect()
+    };
+
+    let list = List::new(list_items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(format!(" Queue ({}) ", queue.len()))
+                .title_bottom(" Enter: Play  d: Remove  K/J: Move  c: Clear  Esc: Close "),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    let mut list_state = ListState::default();
+    if !queue.is_empty() {
+        list_state.select(Some(selected.min(queue.len() - 1)));
+    }
+    frame.render_stateful_widget(list, popup, &mut list_state);
+}
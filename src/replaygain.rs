@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{config_dir, library, loudness, probe_file, tag_writer, ProbeInfo};
+
+/// Which gain tag is used.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Off,
+    Track,
+    Album,
+    /// Album gain while an album plays in order, track gain otherwise
+    Auto,
+}
+
+impl Mode {
+    const ALL: [Mode; 4] = [Mode::Off, Mode::Track, Mode::Album, Mode::Auto];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Off => "off",
+            Mode::Track => "track",
+            Mode::Album => "album",
+            Mode::Auto => "auto",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Mode::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Mode::Off => Mode::Track,
+            Mode::Track => Mode::Album,
+            Mode::Album => Mode::Auto,
+            Mode::Auto => Mode::Off,
+        }
+    }
+}
+
+pub struct Settings {
+    pub mode: Mode,
+    /// Gain for files with neither tags nor a measurement, in dB
+    pub untagged_preamp_db: f32,
+    /// Measure untagged files in the background
+    pub analyze: bool,
+}
+
+/// `untagged_preamp_db` is clamped to ±this.
+const MAX_UNTAGGED_PREAMP_DB: f32 = 24.0;
+
+fn settings_path() -> PathBuf {
+    config_dir().join("replaygain")
+}
+
+/// Read `~/.config/tui-player/replaygain` (`key = value` lines, `#` comments):
+/// `mode` (off, track, album or auto), `untagged_preamp_db`, and `analyze`
+/// (true/false) to measure untagged files while they play.
+pub fn load_settings() -> Settings {
+    parse_settings(&fs::read_to_string(settings_path()).unwrap_or_default())
+}
+
+fn parse_settings(content: &str) -> Settings {
+    let mut settings = Settings {
+        mode: Mode::Auto,
+        untagged_preamp_db: 0.0,
+        analyze: true,
+    };
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "mode" => settings.mode = Mode::parse(value).unwrap_or(settings.mode),
+            "untagged_preamp_db" => {
+                if let Some(db) = value.parse::<f32>().ok().filter(|db| db.is_finite()) {
+                    settings.untagged_preamp_db =
+                        db.clamp(-MAX_UNTAGGED_PREAMP_DB, MAX_UNTAGGED_PREAMP_DB);
+                }
+            }
+            "analyze" => settings.analyze = value != "false",
+            _ => {}
+        }
+    }
+    settings
+}
+
+/// Store `mode`, keeping the file's other lines.
+pub fn save_mode(mode: Mode) {
+    let content = fs::read_to_string(settings_path()).unwrap_or_default();
+    let is_mode = |line: &str| line.split_once('=').is_some_and(|(k, _)| k.trim() == "mode");
+    let mut lines: Vec<&str> = content.lines().filter(|l| !is_mode(l)).collect();
+    let mode_line = format!("mode = {}", mode.name());
+    lines.insert(0, &mode_line);
+    let _ = fs::create_dir_all(config_dir());
+    let _ = fs::write(settings_path(), lines.join("\n") + "\n");
+}
+
+/// Where a track's gain came from, for the track info popup.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Source {
+    Off,
+    Track,
+    Album,
+    Measured,
+    Untagged,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Off => "off",
+            Source::Track => "track",
+            Source::Album => "album",
+            Source::Measured => "measured",
+            Source::Untagged => "untagged",
+        }
+    }
+}
+
+/// Gain for a track as a linear multiplier. The preferred tag falls back to the
+/// other one, then to a measurement, then to the untagged preamp. Gains are
+/// lowered where needed so the matching peak doesn't end up above full scale.
+pub fn select(
+    probe: &ProbeInfo,
+    settings: &Settings,
+    in_album: bool,
+    measured: Option<loudness::Loudness>,
+) -> (f32, Source) {
+    let album = match settings.mode {
+        Mode::Off => return (1.0, Source::Off),
+        Mode::Track => false,
+        Mode::Album => true,
+        Mode::Auto => in_album,
+    };
+    let track_tag = probe.rg_track_db.map(|db| (db, probe.rg_track_peak, Source::Track));
+    let album_tag = probe.rg_album_db.map(|db| (db, probe.rg_album_peak, Source::Album));
+    let tagged = if album { album_tag.or(track_tag) } else { track_tag.or(album_tag) };
+    let (db, peak, source) = match (tagged, measured) {
+        (Some(tag), _) => tag,
+        (None, Some(m)) => (m.gain_db(), Some(m.peak), Source::Measured),
+        (None, None) => {
+            return (10f32.powf(settings.untagged_preamp_db / 20.0), Source::Untagged);
+        }
+    };
+    let gain = 10f32.powf(db / 20.0);
+    match peak {
+        Some(p) if p > 0.0 => (gain.min(1.0 / p), source),
+        _ => (gain, source),
+    }
+}
+
+fn print_result(gain: Option<loudness::Loudness>, what: &str) {
+    match gain {
+        Some(l) => println!("{:>+7.2} dB  peak {:.6}  {what}", l.gain_db(), l.peak),
+        None => println!("{:>10}  {:>13}  {what}", "silent", ""),
+    }
+}
+
+/// Gating blocks and peak of an album's tracks, and which tracks they are.
+#[derive(Default)]
+struct Album {
+    blocks: Vec<f64>,
+    peak: f32,
+    tracks: Vec<usize>,
+}
+
+/// Measure `files`, print their gains and, with `write`, tag them. Albums are the
+/// files of one folder sharing an album tag. Returns how many files failed.
+fn scan(files: &[PathBuf], write: bool) -> usize {
+    let mut failed = 0;
+    let mut measured: Vec<(&Path, Option<loudness::Loudness>)> = Vec::new();
+    let mut albums: BTreeMap<(PathBuf, String), Album> = BTreeMap::new();
+    for file in files {
+        let Some(analysis) = loudness::analyze(file) else {
+            eprintln!("Can't decode {}", file.display());
+            failed += 1;
+            continue;
+        };
+        let result = analysis.loudness();
+        loudness::store(file, result);
+        print_result(result, &file.display().to_string());
+        if let Some(album) = probe_file(file).meta.album.filter(|a| !a.trim().is_empty()) {
+            let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            let album = albums.entry((dir, album)).or_default();
+            album.blocks.extend_from_slice(&analysis.blocks);
+            album.peak = album.peak.max(analysis.peak);
+            album.tracks.push(measured.len());
+        }
+        measured.push((file, result));
+    }
+
+    let mut album_of = vec![None; measured.len()];
+    for ((_, name), album) in &albums {
+        let result = loudness::integrated(&album.blocks)
+            .map(|lufs| loudness::Loudness { lufs, peak: album.peak });
+        print_result(result, &format!("album \"{name}\" ({} tracks)", album.tracks.len()));
+        for &i in &album.tracks {
+            album_of[i] = result;
+        }
+    }
+
+    if !write {
+        return failed;
+    }
+    for ((file, track), album) in measured.iter().zip(album_of) {
+        let Some(track) = track else {
+            continue;
+        };
+        let mut tags = vec![
+            ("REPLAYGAIN_TRACK_GAIN", format!("{:+.2} dB", track.gain_db())),
+            ("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", track.peak)),
+        ];
+        if let Some(album) = album {
+            tags.push(("REPLAYGAIN_ALBUM_GAIN", format!("{:+.2} dB", album.gain_db())));
+            tags.push(("REPLAYGAIN_ALBUM_PEAK", format!("{:.6}", album.peak)));
+        }
+        if let Err(e) = tag_writer::write_tags(file, &tags) {
+            eprintln!("Can't tag {}: {e}", file.display());
+            failed += 1;
+        }
+    }
+    failed
+}
+
+const HELP: &str = "\
+Commands:
+  scan <files or folders>     Measure loudness (EBU R128) and print track and album gains
+  write <files or folders>    Measure, then write REPLAYGAIN_* tags to FLAC and MP3 files";
+
+/// `tui-player replaygain <command>`. Returns the process exit code.
+pub fn run_cli(args: &[String]) -> i32 {
+    let (write, paths) = match args.split_first() {
+        Some((cmd, paths)) if cmd == "scan" && !paths.is_empty() => (false, paths),
+        Some((cmd, paths)) if cmd == "write" && !paths.is_empty() => (true, paths),
+        Some((cmd, _)) if cmd == "help" || cmd == "--help" => {
+            println!("Usage: tui-player replaygain <command>\n\n{HELP}");
+            return 0;
+        }
+        _ => {
+            eprintln!("Usage: tui-player replaygain <command>\n\n{HELP}");
+            return 2;
+        }
+    };
+    let mut files = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            library::walk(&path, &mut files);
+        } else if path.is_file() {
+            files.push(path);
+        } else {
+            eprintln!("Path not found: {}", path.display());
+        }
+    }
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        eprintln!("No audio files found");
+        return 1;
+    }
+    if scan(&files, write) > 0 { 1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: Mode) -> Settings {
+        Settings { mode, untagged_preamp_db: -6.0, analyze: true }
+    }
+
+    fn tagged(track: Option<f32>, album: Option<f32>) -> ProbeInfo {
+        ProbeInfo { rg_track_db: track, rg_album_db: album, ..Default::default() }
+    }
+
+    fn db(gain: f32) -> f32 {
+        (20.0 * gain.log10() * 100.0).round() / 100.0
+    }
+
+    #[test]
+    fn settings_file() {
+        let s = parse_settings("");
+        assert!(s.mode == Mode::Auto && s.analyze);
+        assert_eq!(s.untagged_preamp_db, 0.0);
+
+        let s = parse_settings("mode = album # loud\nuntagged_preamp_db = -4.5\nanalyze = false\n");
+        assert!(s.mode == Mode::Album && !s.analyze);
+        assert_eq!(s.untagged_preamp_db, -4.5);
+
+        assert!(parse_settings("mode = loud").mode == Mode::Auto);
+    }
+
+    #[test]
+    fn untagged_preamp_is_finite_and_bounded() {
+        for bad in ["nan", "inf", "-inf", "quiet"] {
+            let s = parse_settings(&format!("untagged_preamp_db = -3\nuntagged_preamp_db = {bad}"));
+            assert_eq!(s.untagged_preamp_db, -3.0, "{bad}");
+        }
+        assert_eq!(parse_settings("untagged_preamp_db = 1e30").untagged_preamp_db, 24.0);
+        assert_eq!(parse_settings("untagged_preamp_db = -100").untagged_preamp_db, -24.0);
+    }
+
+    #[test]
+    fn tag_preference() {
+        let both = tagged(Some(-3.0), Some(-5.0));
+        let pick = |mode, probe: &ProbeInfo, in_album| {
+            let (gain, source) = select(probe, &settings(mode), in_album, None);
+            (db(gain), source)
+        };
+        assert!(pick(Mode::Off, &both, true) == (0.0, Source::Off));
+        assert!(pick(Mode::Track, &both, true) == (-3.0, Source::Track));
+        assert!(pick(Mode::Album, &both, false) == (-5.0, Source::Album));
+        assert!(pick(Mode::Auto, &both, true) == (-5.0, Source::Album));
+        assert!(pick(Mode::Auto, &both, false) == (-3.0, Source::Track));
+        // Either tag stands in for the other
+        assert!(pick(Mode::Album, &tagged(Some(-3.0), None), true) == (-3.0, Source::Track));
+        assert!(pick(Mode::Track, &tagged(None, Some(-5.0)), false) == (-5.0, Source::Album));
+    }
+
+    #[test]
+    fn fallbacks_and_peak_limit() {
+        let untagged = ProbeInfo::default();
+        let measured = loudness::Loudness { lufs: -14.0, peak: 1.0 };
+        let (gain, source) = select(&untagged, &settings(Mode::Track), false, Some(measured));
+        assert!(db(gain) == -4.0 && source == Source::Measured);
+        let (gain, source) = select(&untagged, &settings(Mode::Track), false, None);
+        assert!(db(gain) == -6.0 && source == Source::Untagged);
+
+        // +6 dB would push a 0.8 peak over full scale
+        let loud = ProbeInfo { rg_track_peak: Some(0.8), ..tagged(Some(6.0), None) };
+        let (gain, _) = select(&loud, &settings(Mode::Track), false, None);
+        assert_eq!(gain, 1.0 / 0.8);
+    }
+}
@@ -0,0 +1,345 @@
+use std::path::{Path, PathBuf};
+
+use tui_tree_widget::TreeItem;
+
+use crate::file_browser::collect_audio_files;
+use crate::library::Library;
+use crate::TrackMeta;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Year,
+    File,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "title" | "t" => Some(Field::Title),
+            "artist" | "a" => Some(Field::Artist),
+            "album" | "b" => Some(Field::Album),
+            "genre" | "g" => Some(Field::Genre),
+            "year" | "date" | "y" => Some(Field::Year),
+            "file" | "path" | "f" => Some(Field::File),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+enum Term {
+    /// Fuzzy text matched against every field
+    Any(String),
+    /// Fuzzy text matched against one field
+    In(Field, String),
+    /// `year:>2005` and friends
+    YearCmp(Cmp, u32),
+}
+
+/// Split on whitespace, keeping `"quoted phrases"` (also after `field:`) together.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_query(query: &str) -> Vec<Term> {
+    tokenize(query)
+        .into_iter()
+        .filter_map(|token| {
+            let Some((name, value)) = token.split_once(':') else {
+                return Some(Term::Any(token));
+            };
+            let Some(field) = Field::parse(name) else {
+                return Some(Term::Any(token));
+            };
+            if value.is_empty() {
+                return None; // still typing the value
+            }
+            if field == Field::Year {
+                let (cmp, rest) = if let Some(r) = value.strip_prefix(">=") {
+                    (Some(Cmp::Ge), r)
+                } else if let Some(r) = value.strip_prefix("<=") {
+                    (Some(Cmp::Le), r)
+                } else if let Some(r) = value.strip_prefix('>') {
+                    (Some(Cmp::Gt), r)
+                } else if let Some(r) = value.strip_prefix('<') {
+                    (Some(Cmp::Lt), r)
+                } else {
+                    (None, value)
+                };
+                if let Some(cmp) = cmp {
+                    // An unfinished comparison ("year:>") filters nothing yet
+                    return rest.trim().parse().ok().map(|y| Term::YearCmp(cmp, y));
+                }
+            }
+            Some(Term::In(field, value.to_string()))
+        })
+        .collect()
+}
+
+fn is_word_start(chars: &[char], i: usize) -> bool {
+    i == 0 || !chars[i - 1].is_alphanumeric()
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Score `needle` against `haystack` (case-insensitive). Contiguous matches beat
+/// scattered ones, and matches at word starts or covering the whole field score
+/// higher. Returns the score and the matched char indices into `haystack`.
+fn score_match(needle: &str, haystack: &str) -> Option<(i32, Vec<usize>)> {
+    let needle: Vec<char> = needle.chars().map(lower).collect();
+    let hay: Vec<char> = haystack.chars().collect();
+    let hay_lower: Vec<char> = hay.iter().map(|&c| lower(c)).collect();
+    if needle.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // Contiguous: take the best occurrence (prefer word starts, then earliest)
+    let n = needle.len();
+    let best_sub = (0..=hay_lower.len().saturating_sub(n))
+        .filter(|&i| hay_lower.len() >= n && hay_lower[i..i + n] == needle[..])
+        .max_by_key(|&i| (is_word_start(&hay, i), std::cmp::Reverse(i)));
+    if let Some(i) = best_sub {
+        let mut score = 100 + 10 * n as i32;
+        if i == 0 {
+            score += 40;
+        } else if is_word_start(&hay, i) {
+            score += 25;
+        }
+        if n == hay.len() {
+            score += 50;
+        }
+        return Some((score, (i..i + n).collect()));
+    }
+
+    // Scattered: greedy left-to-right, rewarding runs and word starts
+    let mut positions = Vec::with_capacity(n);
+    let mut score = 0;
+    let mut next = 0;
+    for &c in &needle {
+        let i = (next..hay_lower.len()).find(|&i| hay_lower[i] == c)?;
+        score += 1;
+        if positions.last() == Some(&(i.wrapping_sub(1))) {
+            score += 5;
+        }
+        if is_word_start(&hay, i) {
+            score += 8;
+        }
+        positions.push(i);
+        next = i + 1;
+    }
+    let span = positions.last().unwrap() - positions[0] + 1;
+    score -= (span - n) as i32 / 4; // gaps
+    Some((score, positions))
+}
+
+/// Searchable text of one track.
+struct Fields {
+    title: Option<String>,
+    artist: Option<String>,
+    album_artist: Option<String>,
+    album: Option<String>,
+    genre: Option<String>,
+    year: Option<String>,
+    file: String,
+}
+
+impl Fields {
+    fn new(path: &Path, meta: Option<&TrackMeta>) -> Self {
+        let file = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let m = meta.cloned().unwrap_or_default();
+        Fields {
+            year: m.year(),
+            title: m.title,
+            artist: m.artist,
+            album_artist: m.album_artist,
+            album: m.album,
+            genre: m.genre,
+            file,
+        }
+    }
+
+    /// Values of `field`; artist covers both artist and album artist.
+    fn get(&self, field: Field) -> Vec<&str> {
+        let values = match field {
+            Field::Title => [self.title.as_deref(), None],
+            Field::Artist => [self.artist.as_deref(), self.album_artist.as_deref()],
+            Field::Album => [self.album.as_deref(), None],
+            Field::Genre => [self.genre.as_deref(), None],
+            Field::Year => [self.year.as_deref(), None],
+            Field::File => [Some(self.file.as_str()), None],
+        };
+        values.into_iter().flatten().collect()
+    }
+}
+
+const ALL_FIELDS: [Field; 6] = [
+    Field::Title,
+    Field::Artist,
+    Field::Album,
+    Field::Genre,
+    Field::Year,
+    Field::File,
+];
+
+/// Best match of `needle` in any of `fields`: (score, field, value index, char indices).
+fn best_in(
+    fields: &Fields,
+    which: &[Field],
+    needle: &str,
+) -> Option<(i32, Field, usize, Vec<usize>)> {
+    which
+        .iter()
+        .flat_map(|&f| {
+            fields
+                .get(f)
+                .into_iter()
+                .enumerate()
+                .filter_map(move |(vi, text)| {
+                    score_match(needle, text).map(|(s, pos)| (s, f, vi, pos))
+                })
+        })
+        .min_by_key(|m| std::cmp::Reverse(m.0)) // ties go to the earlier field
+}
+
+pub struct SearchHit {
+    pub path: PathBuf,
+    /// Display text as (run, highlighted) pieces
+    pub segments: Vec<(String, bool)>,
+}
+
+fn push_text(segments: &mut Vec<(String, bool)>, text: &str, marked: &[usize]) {
+    for (i, c) in text.chars().enumerate() {
+        let hl = marked.contains(&i);
+        match segments.last_mut() {
+            Some((run, h)) if *h == hl => run.push(c),
+            _ => segments.push((c.to_string(), hl)),
+        }
+    }
+}
+
+/// Display line for a hit: "Artist – Title · Album (Year)" for tagged files, the
+/// path relative to the root otherwise. `marks` lists matched char indices per field.
+fn display(
+    path: &Path,
+    fields: &Fields,
+    marks: &[(Field, usize, Vec<usize>)],
+    root_dir: Option<&Path>,
+) -> Vec<(String, bool)> {
+    let marked = |field: Field, value_idx: usize| -> Vec<usize> {
+        marks
+            .iter()
+            .filter(|(f, vi, _)| *f == field && *vi == value_idx)
+            .flat_map(|(_, _, pos)| pos.iter().copied())
+            .collect()
+    };
+    let mut segments = Vec::new();
+    let Some(ref title) = fields.title else {
+        let rel = root_dir
+            .and_then(|r| path.strip_prefix(r).ok())
+            .map(|rel| rel.to_string_lossy().to_string())
+            .unwrap_or_else(|| fields.file.clone());
+        // Highlight the file name part of the relative path
+        let offset = rel.chars().count().saturating_sub(fields.file.chars().count());
+        let pos: Vec<usize> = marked(Field::File, 0).iter().map(|i| i + offset).collect();
+        push_text(&mut segments, &rel, &pos);
+        return segments;
+    };
+    if let Some(ref artist) = fields.artist {
+        push_text(&mut segments, artist, &marked(Field::Artist, 0));
+        push_text(&mut segments, " – ", &[]);
+    }
+    push_text(&mut segments, title, &marked(Field::Title, 0));
+    if let Some(ref album) = fields.album {
+        push_text(&mut segments, " · ", &[]);
+        push_text(&mut segments, album, &marked(Field::Album, 0));
+    }
+    if let Some(ref year) = fields.year {
+        push_text(&mut segments, " (", &[]);
+        push_text(&mut segments, year, &marked(Field::Year, 0));
+        push_text(&mut segments, ")", &[]);
+    }
+    segments
+}
+
+/// Search every audio file in `items`. Free words match any of title, artist,
+/// album, genre, year and file name; `field:value` restricts a word to one field;
+/// `year:>2005` style comparisons filter numerically. All terms must match. Hits
+/// are ranked by match quality, ties kept in tree order.
+pub fn search(
+    items: &[TreeItem<'static, PathBuf>],
+    query: &str,
+    library: &Library,
+    root_dir: Option<&Path>,
+) -> Vec<SearchHit> {
+    let terms = parse_query(query);
+    let mut hits: Vec<(i32, SearchHit)> = Vec::new();
+    'files: for path in collect_audio_files(items) {
+        let fields = Fields::new(&path, library.get(&path).map(|e| &e.meta));
+        let mut score = 0;
+        let mut marks = Vec::new();
+        for term in &terms {
+            let found = match term {
+                Term::Any(text) => best_in(&fields, &ALL_FIELDS, text),
+                Term::In(field, text) => best_in(&fields, &[*field], text),
+                Term::YearCmp(cmp, year) => {
+                    let Some(y) = fields.year.as_deref().and_then(|y| y.parse::<u32>().ok())
+                    else {
+                        continue 'files;
+                    };
+                    let ok = match cmp {
+                        Cmp::Lt => y < *year,
+                        Cmp::Le => y <= *year,
+                        Cmp::Gt => y > *year,
+                        Cmp::Ge => y >= *year,
+                    };
+                    if !ok {
+                        continue 'files;
+                    }
+                    continue;
+                }
+            };
+            let Some((s, field, vi, pos)) = found else {
+                continue 'files;
+            };
+            score += s;
+            marks.push((field, vi, pos));
+        }
+        let segments = display(&path, &fields, &marks, root_dir);
+        hits.push((score, SearchHit { path, segments }));
+    }
+    hits.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    hits.into_iter().map(|(_, hit)| hit).collect()
+}
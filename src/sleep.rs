@@ -0,0 +1,221 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::config_dir;
+use crate::theme::Theme;
+
+/// Volume ramps to silence over this many seconds before the timer fires.
+pub const FADE_SECS: f32 = 30.0;
+
+const PRESET_MINUTES: [u32; 4] = [15, 30, 45, 60];
+const CUSTOM_STEP: u32 = 5;
+const MAX_CUSTOM_MINUTES: u32 = 600;
+const MAX_TRACKS: u32 = 99;
+
+#[derive(Clone, Copy)]
+pub enum SleepTarget {
+    /// Fire at a wall-clock deadline
+    At(Instant),
+    /// Fire once this many more tracks have finished, counting the current one
+    Tracks(u32),
+}
+
+pub struct SleepTimer {
+    pub target: SleepTarget,
+    /// Quit the player instead of just pausing
+    pub quit: bool,
+}
+
+impl SleepTimer {
+    /// Short label for the progress bar title.
+    pub fn label(&self) -> String {
+        match self.target {
+            SleepTarget::At(deadline) => {
+                let secs = deadline.saturating_duration_since(Instant::now()).as_secs();
+                format!("Sleep {}:{:02}", secs / 60, secs % 60)
+            }
+            SleepTarget::Tracks(0 | 1) => "Sleep after this track".into(),
+            SleepTarget::Tracks(n) => format!("Sleep after {n} tracks"),
+        }
+    }
+}
+
+/// Rows of the sleep timer popup, top to bottom.
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Off,
+    Minutes(u32),
+    Custom,
+    EndOfTrack,
+    Tracks,
+}
+
+fn rows() -> Vec<Row> {
+    let mut rows = vec![Row::Off];
+    rows.extend(PRESET_MINUTES.iter().map(|&m| Row::Minutes(m)));
+    rows.extend([Row::Custom, Row::EndOfTrack, Row::Tracks]);
+    rows
+}
+
+/// Popup state. The custom length, track count and quit choice are remembered.
+pub struct SleepMenu {
+    pub selected: usize,
+    pub custom_minutes: u32,
+    pub tracks: u32,
+    pub quit: bool,
+}
+
+impl SleepMenu {
+    pub fn select_prev(&mut self) {
+        let len = rows().len();
+        self.selected = (self.selected + len - 1) % len;
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % rows().len();
+    }
+
+    /// Left/Right on the custom or track-count row.
+    pub fn adjust(&mut self, up: bool) {
+        match rows()[self.selected] {
+            Row::Custom => {
+                self.custom_minutes = if up {
+                    (self.custom_minutes + CUSTOM_STEP).min(MAX_CUSTOM_MINUTES)
+                } else {
+                    self.custom_minutes.saturating_sub(CUSTOM_STEP).max(CUSTOM_STEP)
+                };
+            }
+            Row::Tracks => {
+                self.tracks = if up {
+                    (self.tracks + 1).min(MAX_TRACKS)
+                } else {
+                    self.tracks.saturating_sub(1).max(1)
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Timer for the highlighted row, or None for "Off".
+    pub fn choice(&self) -> Option<SleepTimer> {
+        let minutes = |m: u32| SleepTarget::At(Instant::now() + Duration::from_secs(m as u64 * 60));
+        let target = match rows()[self.selected] {
+            Row::Off => return None,
+            Row::Minutes(m) => minutes(m),
+            Row::Custom => minutes(self.custom_minutes),
+            Row::EndOfTrack => SleepTarget::Tracks(1),
+            Row::Tracks => SleepTarget::Tracks(self.tracks),
+        };
+        Some(SleepTimer { target, quit: self.quit })
+    }
+}
+
+// --- Config persistence ---
+
+pub fn load_sleep_menu() -> SleepMenu {
+    let content = std::fs::read_to_string(config_dir().join("sleep")).unwrap_or_default();
+    let mut lines = content.lines().map(str::trim);
+    let custom_minutes = lines
+        .next()
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(90)
+        .clamp(CUSTOM_STEP, MAX_CUSTOM_MINUTES);
+    let tracks = lines
+        .next()
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(3)
+        .clamp(1, MAX_TRACKS);
+    let quit = lines.next() == Some("true");
+    SleepMenu { selected: 0, custom_minutes, tracks, quit }
+}
+
+pub fn save_sleep_menu(menu: &SleepMenu) {
+    let dir = config_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let content = format!("{}\n{}\n{}", menu.custom_minutes, menu.tracks, menu.quit);
+    let _ = std::fs::write(dir.join("sleep"), content);
+}
+
+// --- Drawing ---
+
+pub fn draw_sleep(frame: &mut Frame, menu: &SleepMenu, active: Option<&SleepTimer>, theme: &Theme) {
+    let rows = rows();
+    let area = frame.area();
+    let popup_w = 40u16.min(area.width);
+    let popup_h = (rows.len() as u16 + 6).min(area.height); // borders + status + gaps + quit row
+    let popup_x = area.width.saturating_sub(popup_w) / 2;
+    let popup_y = area.height.saturating_sub(popup_h) / 2;
+    let popup_area = Rect::new(popup_x, popup_y, popup_w, popup_h);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Sleep Timer ")
+        .title_bottom(Line::from(" ↑/↓: Select  ←/→: Adjust  Enter: Set "));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let status = active.map(|t| t.label()).unwrap_or_else(|| "Off".into());
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw("  "),
+            Span::styled(
+                status,
+                Style::default()
+                    .fg(if active.is_some() { theme.accent } else { theme.dimmed })
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::raw(""),
+    ];
+
+    for (i, row) in rows.iter().enumerate() {
+        let is_sel = i == menu.selected;
+        let label = match *row {
+            Row::Off => "Off".to_string(),
+            Row::Minutes(m) => format!("{m} minutes"),
+            Row::Custom => format!("Custom: ◂ {} min ▸", menu.custom_minutes),
+            Row::EndOfTrack => "After current track".to_string(),
+            Row::Tracks => format!("After ◂ {} ▸ tracks", menu.tracks),
+        };
+        let style = if is_sel {
+            Style::default()
+                .fg(Color::Black)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { " > " } else { "   " }, Style::default().fg(theme.accent)),
+            Span::styled(format!(" {label} "), style),
+        ]));
+    }
+
+    lines.push(Line::raw(""));
+    lines.push(Line::from(vec![
+        Span::raw("  "),
+        Span::styled(format!("{:<16}", "Quit when done"), Style::default().fg(theme.text)),
+        if menu.quit {
+            Span::styled(
+                "[x]",
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled("[ ]", Style::default().fg(theme.dimmed))
+        },
+        Span::styled("  q", Style::default().fg(theme.dimmed)),
+    ]));
+    frame.render_widget(Paragraph::new(lines), inner);
+}
@@ -0,0 +1,179 @@
+use std::sync::{Arc, Mutex};
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::config_dir;
+use crate::theme::Theme;
+
+pub const BALANCE_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct StereoParams {
+    /// -1.0 = left only, 0.0 = centre, 1.0 = right only
+    pub balance: f32,
+    pub mono: bool,
+    pub swap: bool,
+    pub mute_left: bool,
+    pub mute_right: bool,
+}
+
+impl Default for StereoParams {
+    fn default() -> Self {
+        StereoParams {
+            balance: 0.0,
+            mono: false,
+            swap: false,
+            mute_left: false,
+            mute_right: false,
+        }
+    }
+}
+
+impl StereoParams {
+    /// True when the stage would leave audio untouched.
+    pub fn is_neutral(&self) -> bool {
+        *self == StereoParams::default()
+    }
+
+    /// Process one stereo frame: swap, then downmix, then balance, then mute.
+    pub fn apply(&self, left: f32, right: f32) -> (f32, f32) {
+        let (mut l, mut r) = if self.swap { (right, left) } else { (left, right) };
+        if self.mono {
+            let m = (l + r) * 0.5;
+            l = m;
+            r = m;
+        }
+        // Balance only attenuates the far side so centre stays at unity gain
+        l *= (1.0 - self.balance).min(1.0);
+        r *= (1.0 + self.balance).min(1.0);
+        if self.mute_left {
+            l = 0.0;
+        }
+        if self.mute_right {
+            r = 0.0;
+        }
+        (l, r)
+    }
+
+    pub fn nudge_balance(&mut self, delta: f32) {
+        self.balance = ((self.balance + delta) * 10.0).round() / 10.0;
+        self.balance = self.balance.clamp(-1.0, 1.0);
+    }
+}
+
+pub type SharedStereoParams = Arc<Mutex<StereoParams>>;
+
+// --- Config persistence ---
+
+pub fn load_stereo() -> StereoParams {
+    let content = std::fs::read_to_string(config_dir().join("stereo")).unwrap_or_default();
+    let mut lines = content.lines();
+    let balance = lines
+        .next()
+        .and_then(|s| s.trim().parse::<f32>().ok())
+        .unwrap_or(0.0)
+        .clamp(-1.0, 1.0);
+    let mut flag = || lines.next().is_some_and(|s| s.trim() == "true");
+    StereoParams {
+        balance,
+        mono: flag(),
+        swap: flag(),
+        mute_left: flag(),
+        mute_right: flag(),
+    }
+}
+
+pub fn save_stereo(params: &StereoParams) {
+    let dir = config_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let content = format!(
+        "{}\n{}\n{}\n{}\n{}",
+        params.balance, params.mono, params.swap, params.mute_left, params.mute_right
+    );
+    let _ = std::fs::write(dir.join("stereo"), content);
+}
+
+// --- Drawing ---
+
+fn checkbox<'a>(label: &'a str, key: &'a str, on: bool, theme: &Theme) -> Line<'a> {
+    Line::from(vec![
+        Span::raw("  "),
+        Span::styled(format!("{label:<10}"), Style::default().fg(theme.text)),
+        if on {
+            Span::styled(
+                "[x]",
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled("[ ]", Style::default().fg(theme.dimmed))
+        },
+        Span::styled(format!("  {key}"), Style::default().fg(theme.dimmed)),
+    ])
+}
+
+pub fn draw_stereo(frame: &mut Frame, params: &StereoParams, theme: &Theme) {
+    let area = frame.area();
+    let popup_width = 48u16.min(area.width);
+    let popup_height = 11u16.min(area.height);
+    let popup_x = area.width.saturating_sub(popup_width) / 2;
+    let popup_y = area.height.saturating_sub(popup_height) / 2;
+    let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Stereo ")
+        .title_bottom(Line::from(" ←/→: Balance  c: Centre  Esc: Close "));
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    // Balance slider: 21 positions from L to R
+    const SLIDER: usize = 21;
+    let knob = (((params.balance + 1.0) / 2.0) * (SLIDER - 1) as f32).round() as usize;
+    let slider: String = (0..SLIDER)
+        .map(|i| if i == knob { '●' } else if i == SLIDER / 2 { '┼' } else { '━' })
+        .collect();
+    let balance_label = if params.balance.abs() < 0.05 {
+        "Centre".to_string()
+    } else if params.balance < 0.0 {
+        format!("L {:.0}%", -params.balance * 100.0)
+    } else {
+        format!("R {:.0}%", params.balance * 100.0)
+    };
+
+    let lines = vec![
+        Line::raw(""),
+        Line::from(vec![
+            Span::raw("  "),
+            Span::styled(format!("{:<10}", "Balance"), Style::default().fg(theme.text)),
+            Span::styled("L ", Style::default().fg(theme.dimmed)),
+            Span::styled(slider, Style::default().fg(theme.accent)),
+            Span::styled(" R", Style::default().fg(theme.dimmed)),
+        ]),
+        Line::from(vec![
+            Span::raw(" ".repeat(14)),
+            Span::styled(
+                balance_label,
+                Style::default()
+                    .fg(theme.secondary)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::raw(""),
+        checkbox("Mono", "m", params.mono, theme),
+        checkbox("Swap L/R", "s", params.swap, theme),
+        checkbox("Mute L", "l", params.mute_left, theme),
+        checkbox("Mute R", "r", params.mute_right, theme),
+    ];
+    frame.render_widget(Paragraph::new(lines), inner);
+}
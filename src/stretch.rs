@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+/// Analysis/synthesis frame length. ~40 ms keeps transients tight while still
+/// spanning a couple of periods of low voices.
+const FRAME_MS: f32 = 40.0;
+/// How far around the ideal position to search for the best-matching segment.
+const TOLERANCE_MS: f32 = 10.0;
+/// Coarse search step in frames; the best coarse match is refined one frame at a time.
+const COARSE_STEP: usize = 4;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+
+/// WSOLA (waveform-similarity overlap-add) time-stretcher: changes tempo without
+/// changing pitch. Input and output are interleaved samples. Each synthesis hop
+/// copies a Hann-windowed input segment taken `speed` hops further along, shifted
+/// within ±tolerance to line up with the natural continuation of the previous
+/// segment so the overlap-add doesn't phase-cancel.
+pub struct TimeStretch {
+    channels: usize,
+    frame_len: usize,
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+    speed: f32,
+    /// Buffered interleaved input; `input[0]` is absolute input frame `input_start`
+    input: VecDeque<f32>,
+    input_start: usize,
+    /// Absolute input frame count once the source has ended
+    input_end: Option<usize>,
+    /// Ideal input position (in frames) of the next analysis segment
+    analysis_pos: f64,
+    /// Input position of the previously copied segment
+    prev_pos: Option<usize>,
+    /// Overlap-add accumulator, `frame_len` frames
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+}
+
+impl TimeStretch {
+    pub fn new(channels: u16, sample_rate: u32, speed: f32) -> Self {
+        let channels = channels.max(1) as usize;
+        let frame_len = ((sample_rate as f32 * FRAME_MS / 1000.0) as usize / 2 * 2).max(64);
+        let hop = frame_len / 2;
+        // Periodic Hann: at 50% overlap the windows sum to exactly 1
+        let window = (0..frame_len)
+            .map(|i| {
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos()
+            })
+            .collect();
+        TimeStretch {
+            channels,
+            frame_len,
+            hop,
+            tolerance: (sample_rate as f32 * TOLERANCE_MS / 1000.0) as usize,
+            window,
+            speed: speed.clamp(MIN_SPEED, MAX_SPEED),
+            input: VecDeque::new(),
+            input_start: 0,
+            input_end: None,
+            analysis_pos: 0.0,
+            prev_pos: None,
+            overlap: vec![0.0; frame_len * channels],
+            output: VecDeque::new(),
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    fn buffered_end(&self) -> usize {
+        self.input_start + self.input.len() / self.channels
+    }
+
+    /// Absolute input frame the next step reads up to.
+    fn frames_needed(&self) -> usize {
+        let search_end = self.analysis_pos as usize + self.tolerance + self.frame_len;
+        let natural_end = self
+            .prev_pos
+            .map(|p| p + self.hop + self.frame_len)
+            .unwrap_or(0);
+        search_end.max(natural_end)
+    }
+
+    /// True when `pop` can't produce anything until more input is pushed.
+    pub fn wants_input(&self) -> bool {
+        self.output.is_empty()
+            && self.input_end.is_none()
+            && self.buffered_end() < self.frames_needed()
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        self.input.push_back(sample);
+    }
+
+    /// The source ran dry; pad with silence and play out what's buffered.
+    pub fn end_of_input(&mut self) {
+        self.input_end = Some(self.buffered_end());
+    }
+
+    pub fn pop(&mut self) -> Option<f32> {
+        if self.output.is_empty() {
+            self.step();
+        }
+        self.output.pop_front()
+    }
+
+    /// Leave stretch mode without dropping audio: everything already synthesised,
+    /// followed by the raw input from where the synthesis left off.
+    pub fn drain(mut self) -> VecDeque<f32> {
+        let mut out = std::mem::take(&mut self.output);
+        let resume = self
+            .prev_pos
+            .map(|p| p + self.hop)
+            .unwrap_or(self.input_start)
+            .max(self.input_start);
+        let skip = (resume - self.input_start) * self.channels;
+        out.extend(self.input.iter().skip(skip));
+        out
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        frame
+            .checked_sub(self.input_start)
+            .and_then(|f| self.input.get(f * self.channels + channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// Normalised cross-correlation of the segment at `candidate` against the
+    /// natural continuation, over the overlapping half-frame.
+    fn similarity(&self, candidate: usize, natural: usize) -> f32 {
+        let mut dot = 0.0;
+        let mut energy = 1e-9;
+        for i in (0..self.hop).step_by(2) {
+            let x = self.mono(candidate + i);
+            dot += x * self.mono(natural + i);
+            energy += x * x;
+        }
+        dot / energy.sqrt()
+    }
+
+    fn best_match(&self, natural: usize, lo: usize, hi: usize) -> usize {
+        let best_in = |range: &mut dyn Iterator<Item = usize>| {
+            range
+                .map(|c| (c, self.similarity(c, natural)))
+                .fold((lo, f32::MIN), |best, cur| if cur.1 > best.1 { cur } else { best })
+                .0
+        };
+        let coarse = best_in(&mut (lo..=hi).step_by(COARSE_STEP));
+        let fine_lo = coarse.saturating_sub(COARSE_STEP - 1).max(lo);
+        let fine_hi = (coarse + COARSE_STEP - 1).min(hi);
+        best_in(&mut (fine_lo..=fine_hi))
+    }
+
+    fn step(&mut self) {
+        if self
+            .input_end
+            .is_some_and(|end| self.analysis_pos as usize >= end)
+        {
+            return;
+        }
+        let ideal = self.analysis_pos as usize;
+        let chosen = match self.prev_pos {
+            None => ideal,
+            Some(prev) => {
+                let lo = ideal.saturating_sub(self.tolerance).max(self.input_start);
+                let hi = (ideal + self.tolerance).max(lo);
+                self.best_match(prev + self.hop, lo, hi)
+            }
+        };
+
+        let ch = self.channels;
+        for i in 0..self.frame_len {
+            let w = self.window[i];
+            for c in 0..ch {
+                self.overlap[i * ch + c] += w * self.sample(chosen + i, c);
+            }
+        }
+        // The first hop has now received both of its overlapping windows
+        self.output.extend(self.overlap.drain(..self.hop * ch));
+        self.overlap.resize(self.frame_len * ch, 0.0);
+
+        self.prev_pos = Some(chosen);
+        self.analysis_pos += self.hop as f64 * self.speed as f64;
+
+        // Drop input that no later step can reach
+        let keep_from = (self.analysis_pos as usize)
+            .saturating_sub(self.tolerance)
+            .min(chosen + self.hop);
+        let drop = keep_from
+            .saturating_sub(self.input_start)
+            .min(self.input.len() / ch);
+        self.input.drain(..drop * ch);
+        self.input_start += drop;
+    }
+}
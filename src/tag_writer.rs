@@ -0,0 +1,205 @@
+use std::{fs, path::Path};
+
+use crate::lrc::{id3_text, syncsafe};
+
+const FLAC_PADDING: u8 = 1;
+const FLAC_VORBIS_COMMENT: u8 = 4;
+
+/// Set text tags (Vorbis comment names, e.g. `REPLAYGAIN_TRACK_GAIN`) in a FLAC or
+/// MP3 file, replacing earlier values of the same names. The file is rewritten
+/// through a temporary copy, so a failed write leaves the original untouched.
+pub fn write_tags(path: &Path, tags: &[(&str, String)]) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let updated = match ext.to_ascii_lowercase().as_str() {
+        "flac" => flac_with_tags(&data, tags)?,
+        "mp3" => mp3_with_tags(&data, tags)?,
+        _ => return Err("only FLAC and MP3 tags can be written".into()),
+    };
+    let tmp = path.with_extension(format!("{ext}.tmp"));
+    let written = fs::write(&tmp, updated)
+        .and_then(|_| fs::set_permissions(&tmp, fs::metadata(path)?.permissions()))
+        .and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+fn is_replaced(name: &str, tags: &[(&str, String)]) -> bool {
+    tags.iter().any(|(t, _)| t.eq_ignore_ascii_case(name))
+}
+
+// --- FLAC ---
+
+/// A little-endian length-prefixed field, as in Vorbis comments.
+fn take_field<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let field = data.get(4..4 + len)?;
+    *data = &data[4 + len..];
+    Some(field)
+}
+
+fn vorbis_comment(old: Option<&[u8]>, tags: &[(&str, String)]) -> Result<Vec<u8>, String> {
+    let mut vendor: &[u8] = b"tui-player";
+    let mut comments: Vec<Vec<u8>> = Vec::new();
+    if let Some(mut body) = old {
+        let parsed = (|| {
+            vendor = take_field(&mut body)?;
+            let count = u32::from_le_bytes(body.get(..4)?.try_into().ok()?);
+            body = &body[4..];
+            for _ in 0..count {
+                comments.push(take_field(&mut body)?.to_vec());
+            }
+            Some(())
+        })();
+        parsed.ok_or("damaged Vorbis comment block")?;
+    }
+    comments.retain(|c| {
+        let name = c.split(|&b| b == b'=').next().unwrap_or_default();
+        !is_replaced(&String::from_utf8_lossy(name), tags)
+    });
+    comments.extend(tags.iter().map(|(name, value)| format!("{name}={value}").into_bytes()));
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    out.extend_from_slice(vendor);
+    out.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for c in &comments {
+        out.extend_from_slice(&(c.len() as u32).to_le_bytes());
+        out.extend_from_slice(c);
+    }
+    Ok(out)
+}
+
+/// Replace the Vorbis comment block, or add one after STREAMINFO. Other metadata
+/// blocks and the audio are copied as they are.
+fn flac_with_tags(data: &[u8], tags: &[(&str, String)]) -> Result<Vec<u8>, String> {
+    if !data.starts_with(b"fLaC") {
+        return Err("not a FLAC file".into());
+    }
+    let mut blocks: Vec<(u8, &[u8])> = Vec::new();
+    let mut pos = 4;
+    loop {
+        let header = data.get(pos..pos + 4).ok_or("truncated FLAC metadata")?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let body = data.get(pos + 4..pos + 4 + len).ok_or("truncated FLAC metadata")?;
+        blocks.push((header[0] & 0x7f, body));
+        pos += 4 + len;
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+
+    let old = blocks.iter().find(|(kind, _)| *kind == FLAC_VORBIS_COMMENT).map(|b| b.1);
+    let comment = vorbis_comment(old, tags)?;
+    if comment.len() >= 1 << 24 {
+        return Err("Vorbis comment block too large".into());
+    }
+    let at = match blocks.iter().position(|(kind, _)| *kind == FLAC_VORBIS_COMMENT) {
+        Some(i) => {
+            blocks.remove(i);
+            i
+        }
+        None => 1.min(blocks.len()),
+    };
+    blocks.insert(at, (FLAC_VORBIS_COMMENT, &comment));
+    // Padding goes last, as the format asks
+    blocks.sort_by_key(|(kind, _)| *kind == FLAC_PADDING);
+
+    let mut out = b"fLaC".to_vec();
+    for (i, (kind, body)) in blocks.iter().enumerate() {
+        let last = if i + 1 == blocks.len() { 0x80 } else { 0 };
+        out.push(kind | last);
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        out.extend_from_slice(body);
+    }
+    out.extend_from_slice(&data[pos..]);
+    Ok(out)
+}
+
+// --- MP3 (ID3v2) ---
+
+fn encode_syncsafe(n: usize) -> [u8; 4] {
+    [(n >> 21) as u8 & 0x7f, (n >> 14) as u8 & 0x7f, (n >> 7) as u8 & 0x7f, n as u8 & 0x7f]
+}
+
+/// Description of a TXXX frame body.
+fn txxx_description(body: &[u8]) -> Option<String> {
+    let (&enc, rest) = body.split_first()?;
+    Some(id3_text(enc, rest).0)
+}
+
+/// Rewrite the ID3v2.3/2.4 tag at the start of the file with the TXXX frames for
+/// `tags` replaced, or prepend a v2.4 tag when there is none. The extended header
+/// is dropped; every other frame is copied as it is.
+fn mp3_with_tags(data: &[u8], tags: &[(&str, String)]) -> Result<Vec<u8>, String> {
+    let mut version = 4;
+    let mut frames: Vec<u8> = Vec::new();
+    let mut audio = data;
+    if data.len() >= 10 && &data[..3] == b"ID3" {
+        version = data[3];
+        let flags = data[5];
+        if !(3..=4).contains(&version) {
+            return Err(format!("ID3v2.{version} tags can't be updated"));
+        }
+        // Unsynchronised tags would need their 0xFF 0x00 pairs undone first
+        if flags & 0x80 != 0 {
+            return Err("unsynchronised ID3 tags can't be updated".into());
+        }
+        let size = syncsafe(&data[6..10]);
+        let footer = if version == 4 && flags & 0x10 != 0 { 10 } else { 0 };
+        let tag = data.get(10..10 + size).ok_or("truncated ID3 tag")?;
+        audio = data.get(10 + size + footer..).ok_or("truncated ID3 tag")?;
+
+        let mut pos = 0;
+        if flags & 0x40 != 0 {
+            // Extended header: its size excludes itself in v2.3
+            let raw = tag.get(..4).ok_or("truncated ID3 tag")?;
+            pos = match version {
+                4 => syncsafe(raw),
+                _ => u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize + 4,
+            };
+        }
+        while pos + 10 <= tag.len() {
+            let id = &tag[pos..pos + 4];
+            if id[0] == 0 {
+                break; // padding
+            }
+            let raw = &tag[pos + 4..pos + 8];
+            let size = match version {
+                4 => syncsafe(raw),
+                _ => u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]) as usize,
+            };
+            let frame = tag.get(pos..pos + 10 + size).ok_or("truncated ID3 frame")?;
+            let replaced = id == b"TXXX"
+                && txxx_description(&frame[10..]).is_some_and(|d| is_replaced(&d, tags));
+            if !replaced {
+                frames.extend_from_slice(frame);
+            }
+            pos += 10 + size;
+        }
+    }
+
+    for (name, value) in tags {
+        // ISO-8859-1 text: the names and numbers are plain ASCII
+        let mut body = vec![0];
+        body.extend_from_slice(name.as_bytes());
+        body.push(0);
+        body.extend_from_slice(value.as_bytes());
+        frames.extend_from_slice(b"TXXX");
+        match version {
+            4 => frames.extend_from_slice(&encode_syncsafe(body.len())),
+            _ => frames.extend_from_slice(&(body.len() as u32).to_be_bytes()),
+        }
+        frames.extend_from_slice(&[0, 0]);
+        frames.extend_from_slice(&body);
+    }
+
+    let mut out = vec![b'I', b'D', b'3', version, 0, 0];
+    out.extend_from_slice(&encode_syncsafe(frames.len()));
+    out.extend_from_slice(&frames);
+    out.extend_from_slice(audio);
+    Ok(out)
+}
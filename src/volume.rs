@@ -1,6 +1,7 @@
 use ratatui::{
     layout::{Alignment, Rect},
-    text::Line,
+    style::{Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, BorderType, Borders},
     Frame,
 };
@@ -8,9 +9,17 @@ use ratatui::{
 use crate::gauge::RoundedGauge;
 use crate::theme::Theme;
 
-pub fn draw_volume(frame: &mut Frame, area: Rect, volume: f32, theme: &Theme) {
+/// `clipping` flags audio that recently left the EQ/ReplayGain chain above full scale.
+pub fn draw_volume(frame: &mut Frame, area: Rect, volume: f32, clipping: bool, theme: &Theme) {
     let vol_pct = (volume * 100.0) as u16;
     let vol_ratio = (volume / 2.0) as f64;
+    let mut title = vec![Span::raw(" Volume ")];
+    if clipping {
+        title.push(Span::styled(
+            "CLIP ",
+            Style::default().fg(theme.negative).add_modifier(Modifier::BOLD),
+        ));
+    }
     let vol_gauge = RoundedGauge::new(vol_ratio, String::new(), theme.positive)
         .overflow(0.5, theme.negative)
         .dimmed_color(theme.dimmed)
@@ -18,7 +27,7 @@ pub fn draw_volume(frame: &mut Frame, area: Rect, volume: f32, theme: &Theme) {
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title(" Volume ")
+                .title(Line::from(title))
                 .title(Line::from(format!(" {}% ", vol_pct)).alignment(Alignment::Right)),
         );
     frame.render_widget(vol_gauge, area);
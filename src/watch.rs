@@ -0,0 +1,308 @@
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::file_browser::is_audio_file;
+
+/// A change below the watched root. Paths may be files or whole directories.
+pub enum FsChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    Renamed(PathBuf, PathBuf),
+}
+
+const DIR_MASK: u32 = libc::IN_CREATE
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_ONLYDIR;
+
+/// Size of the fixed part of `struct inotify_event`.
+const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// How long a MOVED_FROM waits for its MOVED_TO, which may come in a later read().
+const MOVE_PAIR_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct Inotify {
+    fd: i32,
+    /// Watch descriptor → directory it watches
+    dirs: HashMap<i32, PathBuf>,
+    /// MOVED_FROM halves waiting for their MOVED_TO: cookie, path, is_dir, when seen
+    moved_from: Vec<(u32, PathBuf, bool, Instant)>,
+}
+
+impl Inotify {
+    fn add_watch(&mut self, dir: &Path) {
+        let Ok(cpath) = CString::new(dir.as_os_str().as_bytes()) else {
+            return;
+        };
+        let wd = unsafe { libc::inotify_add_watch(self.fd, cpath.as_ptr(), DIR_MASK) };
+        if wd >= 0 {
+            self.dirs.insert(wd, dir.to_path_buf());
+        }
+    }
+
+    /// Watch `dir` and every directory below it.
+    fn add_tree(&mut self, dir: &Path) {
+        self.add_watch(dir);
+        if let Ok(rd) = std::fs::read_dir(dir) {
+            for entry in rd.flatten() {
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    self.add_tree(&entry.path());
+                }
+            }
+        }
+    }
+
+    /// A directory left the tree: stop watching it and everything below it.
+    fn remove_tree(&mut self, dir: &Path) {
+        let fd = self.fd;
+        self.dirs.retain(|&wd, watched| {
+            let inside = watched.starts_with(dir);
+            if inside {
+                unsafe { libc::inotify_rm_watch(fd, wd) };
+            }
+            !inside
+        });
+    }
+
+    /// MOVED_FROM halves that waited out `MOVE_PAIR_TIMEOUT` were moved out of the tree.
+    fn expire_moves(&mut self, changes: &mut Vec<FsChange>) {
+        let (expired, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.moved_from)
+            .into_iter()
+            .partition(|(_, _, _, seen)| seen.elapsed() >= MOVE_PAIR_TIMEOUT);
+        self.moved_from = waiting;
+        for (_, path, is_dir, _) in expired {
+            if is_dir {
+                self.remove_tree(&path);
+            }
+            changes.push(FsChange::Removed(path));
+        }
+    }
+
+    /// A directory was renamed: keep the descriptors below it pointing at the new path.
+    fn rename_tree(&mut self, from: &Path, to: &Path) {
+        for dir in self.dirs.values_mut() {
+            if let Ok(rest) = dir.strip_prefix(from) {
+                *dir = to.join(rest);
+            }
+        }
+    }
+}
+
+/// Audio files already inside a directory that appeared after we started watching
+/// (created, moved in, or filled before its watch was added).
+fn audio_files_below(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(rd) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            audio_files_below(&path, out);
+        } else if is_audio_file(&path) {
+            out.push(path);
+        }
+    }
+}
+
+/// Watch `root` recursively with inotify. Changes arrive on the returned channel;
+/// the watcher lives for the rest of the process.
+pub fn watch(root: &Path) -> Option<mpsc::Receiver<FsChange>> {
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return None;
+    }
+    let mut inotify = Inotify { fd, dirs: HashMap::new(), moved_from: Vec::new() };
+    inotify.add_tree(root);
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // u32-aligned so event headers can be read in place
+        let mut buf = vec![0u32; 16 * 1024];
+        loop {
+            // Wake up to expire a move whose other half never came
+            if let Some((_, _, _, seen)) = inotify.moved_from.first() {
+                let wait = MOVE_PAIR_TIMEOUT.saturating_sub(seen.elapsed());
+                let mut pfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+                let ready = unsafe { libc::poll(&mut pfd, 1, wait.as_millis() as i32 + 1) };
+                if ready <= 0 {
+                    let mut changes = Vec::new();
+                    inotify.expire_moves(&mut changes);
+                    if !changes.into_iter().all(|c| tx.send(c).is_ok()) {
+                        break;
+                    }
+                    continue;
+                }
+            }
+            let n = unsafe {
+                libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len() * 4)
+            };
+            if n <= 0 {
+                break;
+            }
+            let bytes = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, n as usize) };
+            if !handle_events(&mut inotify, bytes, &tx) {
+                break;
+            }
+        }
+        unsafe { libc::close(fd) };
+    });
+    Some(rx)
+}
+
+/// Translate one read() worth of events; a MOVED_FROM may pair with a MOVED_TO
+/// from a later read. Returns false once nobody is listening.
+fn handle_events(inotify: &mut Inotify, bytes: &[u8], tx: &mpsc::Sender<FsChange>) -> bool {
+    let mut changes = Vec::new();
+    let mut offset = 0;
+    while offset + EVENT_HEADER <= bytes.len() {
+        let event: libc::inotify_event =
+            unsafe { std::ptr::read_unaligned(bytes[offset..].as_ptr() as *const _) };
+        let name_start = offset + EVENT_HEADER;
+        let name_end = (name_start + event.len as usize).min(bytes.len());
+        offset = name_end;
+
+        let name = &bytes[name_start..name_end];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        if event.mask & libc::IN_IGNORED != 0 {
+            inotify.dirs.remove(&event.wd);
+            continue;
+        }
+        let Some(dir) = inotify.dirs.get(&event.wd) else {
+            continue;
+        };
+        let path = dir.join(OsStr::from_bytes(name));
+        let is_dir = event.mask & libc::IN_ISDIR != 0;
+
+        if event.mask & libc::IN_MOVED_FROM != 0 {
+            inotify.moved_from.push((event.cookie, path, is_dir, Instant::now()));
+        } else if event.mask & libc::IN_MOVED_TO != 0 {
+            match inotify.moved_from.iter().position(|(c, ..)| *c == event.cookie) {
+                Some(i) => {
+                    let (_, from, ..) = inotify.moved_from.remove(i);
+                    if is_dir {
+                        inotify.rename_tree(&from, &path);
+                    }
+                    changes.push(FsChange::Renamed(from, path));
+                }
+                // Moved in from outside the tree
+                None if is_dir => {
+                    inotify.add_tree(&path);
+                    let mut files = Vec::new();
+                    audio_files_below(&path, &mut files);
+                    changes.extend(files.into_iter().map(FsChange::Added));
+                }
+                None => changes.push(FsChange::Added(path)),
+            }
+        } else if event.mask & libc::IN_CREATE != 0 && is_dir {
+            inotify.add_tree(&path);
+            let mut files = Vec::new();
+            audio_files_below(&path, &mut files);
+            changes.extend(files.into_iter().map(FsChange::Added));
+        } else if event.mask & libc::IN_CLOSE_WRITE != 0 {
+            // Files count once fully written, so half-copied rips aren't probed
+            changes.push(FsChange::Added(path));
+        } else if event.mask & libc::IN_DELETE != 0 {
+            changes.push(FsChange::Removed(path));
+        }
+    }
+    inotify.expire_moves(&mut changes);
+    changes.into_iter().all(|c| tx.send(c).is_ok())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raw bytes of one inotify event for `name` in the directory watched as `wd`.
+    fn event(wd: i32, mask: u32, cookie: u32, name: &str) -> Vec<u8> {
+        let len = name.len().div_ceil(16) * 16 + 16;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&wd.to_ne_bytes());
+        bytes.extend_from_slice(&mask.to_ne_bytes());
+        bytes.extend_from_slice(&cookie.to_ne_bytes());
+        bytes.extend_from_slice(&(len as u32).to_ne_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.resize(EVENT_HEADER + len, 0);
+        bytes
+    }
+
+    /// Watching /music as descriptor 1 and /music/sub as 2, without a real inotify fd.
+    fn inotify() -> Inotify {
+        let dirs = HashMap::from([(1, PathBuf::from("/music")), (2, PathBuf::from("/music/sub"))]);
+        Inotify { fd: -1, dirs, moved_from: Vec::new() }
+    }
+
+    /// Feed one read() worth of events and describe the changes it produced.
+    fn feed(inotify: &mut Inotify, events: &[Vec<u8>]) -> Vec<String> {
+        let (tx, rx) = mpsc::channel();
+        assert!(handle_events(inotify, &events.concat(), &tx));
+        drop(tx);
+        rx.iter().map(describe).collect()
+    }
+
+    fn describe(change: FsChange) -> String {
+        match change {
+            FsChange::Added(p) => format!("+{}", p.display()),
+            FsChange::Removed(p) => format!("-{}", p.display()),
+            FsChange::Renamed(from, to) => format!("{} -> {}", from.display(), to.display()),
+        }
+    }
+
+    #[test]
+    fn moves_pair_within_one_read() {
+        let mut inotify = inotify();
+        let changes = feed(
+            &mut inotify,
+            &[
+                event(1, libc::IN_MOVED_FROM, 7, "a.flac"),
+                event(2, libc::IN_MOVED_TO, 7, "b.flac"),
+            ],
+        );
+        assert_eq!(changes, ["/music/a.flac -> /music/sub/b.flac"]);
+        assert!(inotify.moved_from.is_empty());
+    }
+
+    #[test]
+    fn moves_pair_across_reads() {
+        let mut inotify = inotify();
+        assert!(feed(&mut inotify, &[event(1, libc::IN_MOVED_FROM, 7, "a.flac")]).is_empty());
+        let changes = feed(&mut inotify, &[event(1, libc::IN_MOVED_TO, 7, "b.flac")]);
+        assert_eq!(changes, ["/music/a.flac -> /music/b.flac"]);
+    }
+
+    #[test]
+    fn unpaired_moves_expire() {
+        let mut inotify = inotify();
+        feed(&mut inotify, &[event(1, libc::IN_MOVED_FROM, 7, "a.flac")]);
+        inotify.moved_from[0].3 = Instant::now() - MOVE_PAIR_TIMEOUT;
+        // A later unrelated event flushes the expired half as a removal
+        let changes = feed(&mut inotify, &[event(1, libc::IN_CLOSE_WRITE, 0, "c.flac")]);
+        assert_eq!(changes, ["+/music/c.flac", "-/music/a.flac"]);
+        // Moved in from outside the tree
+        let changes = feed(&mut inotify, &[event(1, libc::IN_MOVED_TO, 9, "d.flac")]);
+        assert_eq!(changes, ["+/music/d.flac"]);
+    }
+
+    #[test]
+    fn directory_renames_follow_watches() {
+        let mut inotify = inotify();
+        let changes = feed(
+            &mut inotify,
+            &[
+                event(1, libc::IN_MOVED_FROM | libc::IN_ISDIR, 3, "sub"),
+                event(1, libc::IN_MOVED_TO | libc::IN_ISDIR, 3, "renamed"),
+                event(2, libc::IN_DELETE, 0, "x.mp3"),
+            ],
+        );
+        assert_eq!(changes, ["/music/sub -> /music/renamed", "-/music/renamed/x.mp3"]);
+    }
+}